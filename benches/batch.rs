@@ -0,0 +1,61 @@
+//! Measures `BatchEnv` throughput: `cargo bench --bench batch`.
+
+extern crate rust_snakegame;
+
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snakegame::batch::{BatchConfig, BatchEnv};
+use rust_snakegame::env::{Action, ObservationKind};
+
+/// A small batch, where spawning threads costs more than it saves, and a
+/// large one.
+const BATCH_SIZES: [usize; 2] = [64, 4096];
+const RUN_TIME: Duration = Duration::from_secs(2);
+
+fn steps_per_second(config: BatchConfig) -> f64 {
+    let mut batch = BatchEnv::new(config).unwrap();
+    let mut observations = vec![0.0; batch.observation_size() * config.num_envs];
+    let turns = [Action::Keep, Action::Up, Action::Keep, Action::Right];
+    let actions: Vec<Vec<Action>> = (0..turns.len())
+        .map(|offset| {
+            (0..config.num_envs)
+                .map(|i| turns[(i + offset) % turns.len()])
+                .collect()
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut steps = 0;
+    while start.elapsed() < RUN_TIME {
+        batch.step(&actions[steps % actions.len()]);
+        batch.observe(&mut observations);
+        black_box(&observations);
+        steps += 1;
+    }
+    (steps * config.num_envs) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    for observation in [ObservationKind::Features, ObservationKind::Grid] {
+        for num_envs in BATCH_SIZES {
+            for threads in [1, cores.max(4)] {
+                let rate = steps_per_second(BatchConfig {
+                    num_envs,
+                    observation,
+                    threads,
+                    ..BatchConfig::default()
+                });
+                println!(
+                    "{:?} observations, {} envs, {} thread(s): {:.2}M steps/s",
+                    observation,
+                    num_envs,
+                    threads,
+                    rate / 1e6
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::drawing::{draw_rectangle, to_gui_coord};
+use crate::events::{EventSubscriber, GameEvent};
+use crate::game::Game;
+use crate::powerup::PowerUpType;
+
+const TOAST_DURATION: f64 = 3.0;
+const TOAST_WIDTH: f64 = 260.0;
+const TOAST_HEIGHT: f64 = 50.0;
+const TOAST_BACKGROUND: Color = [0.10, 0.10, 0.10, 0.9];
+const TOAST_TITLE_COLOR: Color = [1.0, 0.84, 0.0, 1.0];
+const TOAST_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+const GALLERY_BACKGROUND: Color = [0.0, 0.0, 0.0, 0.85];
+const UNLOCKED_COLOR: Color = [1.0, 0.84, 0.0, 1.0];
+const LOCKED_COLOR: Color = [0.5, 0.5, 0.5, 1.0];
+
+const COMBO_TARGET: u32 = 10;
+const LENGTH_TARGET: usize = 50;
+const SURVIVAL_TARGET: f64 = 300.0;
+const SCORE_TARGET: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Achievement {
+    FirstFood,
+    Combo10,
+    Length50,
+    Survive5Minutes,
+    DoublePowered,
+    ShieldSave,
+    Score1000,
+    FullBoard,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::FirstFood,
+        Achievement::Combo10,
+        Achievement::Length50,
+        Achievement::Survive5Minutes,
+        Achievement::DoublePowered,
+        Achievement::ShieldSave,
+        Achievement::Score1000,
+        Achievement::FullBoard,
+    ];
+
+    /// Stable name used when storing the achievement in a profile.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Achievement::FirstFood => "first_food",
+            Achievement::Combo10 => "combo_10",
+            Achievement::Length50 => "length_50",
+            Achievement::Survive5Minutes => "survive_5_minutes",
+            Achievement::DoublePowered => "double_powered",
+            Achievement::ShieldSave => "shield_save",
+            Achievement::Score1000 => "score_1000",
+            Achievement::FullBoard => "full_board",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Achievement> {
+        Achievement::ALL.iter().copied().find(|a| a.id() == id)
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Achievement::FirstFood => "First Bite",
+            Achievement::Combo10 => "Combo Master",
+            Achievement::Length50 => "Long Snake",
+            Achievement::Survive5Minutes => "Survivor",
+            Achievement::DoublePowered => "Double Powered",
+            Achievement::ShieldSave => "Close Call",
+            Achievement::Score1000 => "High Scorer",
+            Achievement::FullBoard => "Clean Sweep",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstFood => "Eat your first food",
+            Achievement::Combo10 => "Reach a x10 combo",
+            Achievement::Length50 => "Grow to length 50",
+            Achievement::Survive5Minutes => "Stay alive for 5 minutes",
+            Achievement::DoublePowered => "Have speed and shield active at once",
+            Achievement::ShieldSave => "Survive a crash with a shield",
+            Achievement::Score1000 => "Score 1000 points in one game",
+            Achievement::FullBoard => "Fill the whole board",
+        }
+    }
+}
+
+/// Tracks the first player's progress towards achievements from the game's
+/// events, and shows a toast for each one unlocked.
+pub struct Achievements {
+    unlocked: Vec<Achievement>,
+    newly_unlocked: Vec<Achievement>,
+    toasts: VecDeque<(Achievement, f64)>,
+    alive_time: f64,
+    show_gallery: bool,
+}
+
+impl Achievements {
+    /// Starts from the achievements already stored, ignoring unknown ids.
+    pub fn new(unlocked: &[String]) -> Achievements {
+        Achievements {
+            unlocked: unlocked
+                .iter()
+                .filter_map(|id| Achievement::from_id(id))
+                .collect(),
+            newly_unlocked: Vec::new(),
+            toasts: VecDeque::new(),
+            alive_time: 0.0,
+            show_gallery: false,
+        }
+    }
+
+    pub fn unlocked(&self) -> &[Achievement] {
+        &self.unlocked
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    pub fn toggle_gallery(&mut self) {
+        self.show_gallery = !self.show_gallery;
+    }
+
+    /// Takes the achievements unlocked since the last call, to be stored in
+    /// the profile.
+    pub fn take_newly_unlocked(&mut self) -> Vec<Achievement> {
+        std::mem::take(&mut self.newly_unlocked)
+    }
+
+    /// Advances the survival clock and the toasts.
+    pub fn update(&mut self, delta_time: f64, game: &Game) {
+        if let Some((_, timer)) = self.toasts.front_mut() {
+            *timer -= delta_time;
+            if *timer <= 0.0 {
+                self.toasts.pop_front();
+            }
+        }
+
+        if game.is_game_over() || !game.players()[0].is_alive() {
+            self.alive_time = 0.0;
+            return;
+        }
+        self.alive_time += delta_time;
+        if self.alive_time >= SURVIVAL_TARGET {
+            self.unlock(Achievement::Survive5Minutes);
+        }
+    }
+
+    fn unlock(&mut self, achievement: Achievement) {
+        if !self.is_unlocked(achievement) {
+            self.unlocked.push(achievement);
+            self.newly_unlocked.push(achievement);
+            self.toasts.push_back((achievement, TOAST_DURATION));
+        }
+    }
+
+    pub fn draw(&self, width: i32, height: i32, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        if let Some((achievement, _)) = self.toasts.front() {
+            let x = to_gui_coord(width) - TOAST_WIDTH - 30.0;
+            let y = 30.0;
+            rectangle(
+                TOAST_BACKGROUND,
+                [x, y, TOAST_WIDTH, TOAST_HEIGHT],
+                con.transform,
+                g,
+            );
+            for (color, size, line, offset_y) in [
+                (
+                    TOAST_TITLE_COLOR,
+                    16,
+                    format!("Unlocked: {}", achievement.title()),
+                    20.0,
+                ),
+                (TOAST_COLOR, 12, achievement.description().to_string(), 40.0),
+            ] {
+                text::Text::new_color(color, size)
+                    .draw(
+                        &line,
+                        glyphs,
+                        &con.draw_state,
+                        con.transform.trans(x + 10.0, y + offset_y),
+                        g,
+                    )
+                    .unwrap();
+            }
+        }
+
+        if self.show_gallery {
+            self.draw_gallery(width, height, con, g, glyphs);
+        }
+    }
+
+    fn draw_gallery(
+        &self,
+        width: i32,
+        height: i32,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        draw_rectangle(GALLERY_BACKGROUND, 0, 0, width, height, con, g);
+        let left = to_gui_coord(width) / 2.0 - 200.0;
+
+        let header = format!(
+            "ACHIEVEMENTS  {}/{}",
+            self.unlocked.len(),
+            Achievement::ALL.len()
+        );
+        text::Text::new_color(UNLOCKED_COLOR, 28)
+            .draw(
+                &header,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(left, 70.0),
+                g,
+            )
+            .unwrap();
+
+        let mut y = 120.0;
+        for achievement in Achievement::ALL {
+            let color = if self.is_unlocked(achievement) {
+                UNLOCKED_COLOR
+            } else {
+                LOCKED_COLOR
+            };
+            for (size, line, offset_y) in [
+                (18, achievement.title(), 0.0),
+                (14, achievement.description(), 20.0),
+            ] {
+                text::Text::new_color(color, size)
+                    .draw(
+                        line,
+                        glyphs,
+                        &con.draw_state,
+                        con.transform.trans(left, y + offset_y),
+                        g,
+                    )
+                    .unwrap();
+            }
+            y += 46.0;
+        }
+
+        text::Text::new_color(TOAST_COLOR, 16)
+            .draw(
+                "Press G to close",
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(left, to_gui_coord(height) - 40.0),
+                g,
+            )
+            .unwrap();
+    }
+}
+
+impl EventSubscriber for Achievements {
+    /// Unlocks whatever the event earned the first player.
+    fn on_event(&mut self, event: &GameEvent, game: &Game) {
+        let player = &game.players()[0];
+        let mut earned = Vec::new();
+        match *event {
+            GameEvent::FoodEaten { player: 0, .. } => {
+                earned.push(Achievement::FirstFood);
+                if player.combo_count() >= COMBO_TARGET {
+                    earned.push(Achievement::Combo10);
+                }
+                let length = player.snake().length() + player.snake().pending_growth();
+                if length >= LENGTH_TARGET {
+                    earned.push(Achievement::Length50);
+                }
+                let cells = ((game.width() - 2) * (game.height() - 2)) as usize;
+                if length >= cells {
+                    earned.push(Achievement::FullBoard);
+                }
+            }
+            GameEvent::PowerUpCollected { player: 0, .. }
+            | GameEvent::PowerUpActivated { player: 0, .. }
+                if player.has_effect(PowerUpType::Speed) && player.is_shielded() =>
+            {
+                earned.push(Achievement::DoublePowered);
+            }
+            GameEvent::ShieldSaved { player: 0 } => earned.push(Achievement::ShieldSave),
+            GameEvent::ScoreChanged { player: 0, score } if score >= SCORE_TARGET => {
+                earned.push(Achievement::Score1000);
+            }
+            _ => {}
+        }
+        for achievement in earned {
+            self.unlock(achievement);
+        }
+    }
+}
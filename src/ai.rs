@@ -0,0 +1,69 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::game::Game;
+use crate::snake::Direction;
+
+const FREE_SPACE_MARGIN: usize = 4;
+
+pub fn choose_direction(game: &Game, index: usize) -> Option<Direction> {
+    let snake = game.players()[index].snake();
+    let current = snake.head_direction();
+    let length = snake.length();
+
+    let mut best: Option<(i64, Direction)> = None;
+    for dir in [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ] {
+        if dir == current.opposite() {
+            continue;
+        }
+
+        let (x, y) = snake.next_head_position(Some(dir));
+        if !game.is_cell_free(x, y) {
+            continue;
+        }
+
+        let space = free_space(game, (x, y), length + FREE_SPACE_MARGIN);
+        let distance = match game.nearest_food(x, y) {
+            Some(food) => ((food.x - x).abs() + (food.y - y).abs()) as i64,
+            None => 0,
+        };
+
+        let mut score = -distance;
+        if space < length + FREE_SPACE_MARGIN {
+            score -= 1000 - space as i64;
+        }
+        if dir == current {
+            score += 1;
+        }
+
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, dir));
+        }
+    }
+
+    best.map(|(_, dir)| dir)
+}
+
+fn free_space(game: &Game, start: (i32, i32), limit: usize) -> usize {
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(start);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        if visited.len() >= limit {
+            break;
+        }
+        for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+            if game.is_cell_free(next.0, next.1) && visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    visited.len()
+}
@@ -0,0 +1,589 @@
+use std::io;
+use std::thread;
+
+use crate::env::{
+    check_board, Action, ObservationKind, RewardConfig, FEATURE_COUNT, GRID_CHANNELS,
+};
+use crate::game::{
+    COMBO_WINDOW, FOOD_SCORE, MOVING_PERIOD, POWERUP_LIFETIME, POWERUP_SCORE, POWERUP_SPAWN_CHANCE,
+    SHIELD_DURATION, SPEED_BOOST_DURATION, SPEED_BOOST_PERIOD_SCALE,
+};
+use crate::powerup::PowerUpType;
+use crate::snake::Direction;
+
+pub const MAX_POWERUPS: usize = 8;
+
+const NO_CELL: u16 = u16::MAX;
+/// Fewest games given to each thread. Threads are spawned on every step, so
+/// smaller shares cost more to start than they save.
+const MIN_ENVS_PER_THREAD: usize = 1024;
+/// Random cells tried before spawning falls back to scanning the board.
+const SPAWN_ATTEMPTS: u32 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchConfig {
+    pub num_envs: usize,
+    pub width: i32,
+    pub height: i32,
+    pub observation: ObservationKind,
+    pub reward: RewardConfig,
+    pub max_steps: Option<u32>,
+    pub auto_reset: bool,
+    pub threads: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> BatchConfig {
+        BatchConfig {
+            num_envs: 1024,
+            width: 27,
+            height: 22,
+            observation: ObservationKind::Features,
+            reward: RewardConfig::default(),
+            max_steps: None,
+            auto_reset: true,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PowerUpSlot {
+    cell: u16,
+    powerup_type: PowerUpType,
+    timer: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lane {
+    rng: u64,
+    direction: Direction,
+    head: u32,
+    length: u32,
+    pending_growth: u32,
+    food: u16,
+    powerups: [Option<PowerUpSlot>; MAX_POWERUPS],
+    speed_boost_timer: f64,
+    shield_timer: f64,
+    combo_count: u32,
+    combo_timer: f64,
+    score: u32,
+    steps: u32,
+    done: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Board {
+    width: i32,
+    height: i32,
+    cells: usize,
+    max_length: usize,
+}
+
+/// Steps many independent games in lockstep. Per-game state lives in flat
+/// buffers (an occupancy grid and a ring buffer of body cells per game) so a
+/// step allocates nothing.
+///
+/// Movement, collisions, eating, scoring, rewards and observations follow
+/// `Env` and are checked against it in `tests/batch.rs`. The rest is a
+/// deliberately smaller game: its own RNG stream, so seeds do not reproduce
+/// `Env` runs; a single ordinary food; only speed and shield power-ups; and
+/// a shielded snake that hits a wall holds its position, since the grid has
+/// no cells beyond the border.
+pub struct BatchEnv {
+    config: BatchConfig,
+    board: Board,
+    lanes: Vec<Lane>,
+    occupancy: Vec<u8>,
+    bodies: Vec<u16>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl BatchEnv {
+    /// Fails when the board is too small for the starting snake and food,
+    /// or has too many cells to index with a `u16`.
+    pub fn new(config: BatchConfig) -> io::Result<BatchEnv> {
+        check_board(config.width, config.height)?;
+        let cells = config.width as usize * config.height as usize;
+        let max_length = ((config.width - 2) * (config.height - 2)) as usize;
+
+        let mut batch = BatchEnv {
+            board: Board {
+                width: config.width,
+                height: config.height,
+                cells,
+                max_length,
+            },
+            lanes: vec![Lane::empty(); config.num_envs],
+            occupancy: vec![0; config.num_envs * cells],
+            bodies: vec![0; config.num_envs * max_length],
+            rewards: vec![0.0; config.num_envs],
+            dones: vec![false; config.num_envs],
+            config,
+        };
+        batch.reset(0);
+        Ok(batch)
+    }
+
+    pub fn num_envs(&self) -> usize {
+        self.config.num_envs
+    }
+
+    pub fn config(&self) -> &BatchConfig {
+        &self.config
+    }
+
+    pub fn observation_size(&self) -> usize {
+        match self.config.observation {
+            ObservationKind::Grid => GRID_CHANNELS * self.board.cells,
+            ObservationKind::Features => FEATURE_COUNT,
+        }
+    }
+
+    pub fn rewards(&self) -> &[f32] {
+        &self.rewards
+    }
+
+    pub fn dones(&self) -> &[bool] {
+        &self.dones
+    }
+
+    pub fn scores(&self) -> impl Iterator<Item = u32> + '_ {
+        self.lanes.iter().map(|lane| lane.score)
+    }
+
+    pub fn lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lanes.iter().map(|lane| lane.length as usize)
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        let board = self.board;
+        for (i, ((lane, occupancy), body)) in self
+            .lanes
+            .iter_mut()
+            .zip(self.occupancy.chunks_mut(board.cells))
+            .zip(self.bodies.chunks_mut(board.max_length))
+            .enumerate()
+        {
+            lane.reset(&board, seed.wrapping_add(i as u64), occupancy, body);
+        }
+        self.rewards.iter_mut().for_each(|r| *r = 0.0);
+        self.dones.iter_mut().for_each(|d| *d = false);
+    }
+
+    pub fn step(&mut self, actions: &[Action]) {
+        assert_eq!(actions.len(), self.config.num_envs);
+
+        let board = self.board;
+        let config = self.config;
+        let threads = config
+            .threads
+            .min(config.num_envs / MIN_ENVS_PER_THREAD)
+            .max(1);
+        let per_thread = config.num_envs.div_ceil(threads).max(1);
+
+        let chunks = self
+            .lanes
+            .chunks_mut(per_thread)
+            .zip(self.occupancy.chunks_mut(per_thread * board.cells))
+            .zip(self.bodies.chunks_mut(per_thread * board.max_length))
+            .zip(actions.chunks(per_thread))
+            .zip(self.rewards.chunks_mut(per_thread))
+            .zip(self.dones.chunks_mut(per_thread));
+
+        thread::scope(|scope| {
+            for (((((lanes, occupancy), bodies), actions), rewards), dones) in chunks {
+                let mut work = move || {
+                    for (i, lane) in lanes.iter_mut().enumerate() {
+                        let occupancy = &mut occupancy[i * board.cells..(i + 1) * board.cells];
+                        let body = &mut bodies[i * board.max_length..(i + 1) * board.max_length];
+                        let (reward, done) =
+                            lane.step(&board, &config, actions[i], occupancy, body);
+                        rewards[i] = reward;
+                        dones[i] = done;
+                    }
+                };
+
+                if threads > 1 {
+                    scope.spawn(work);
+                } else {
+                    work();
+                }
+            }
+        });
+    }
+
+    pub fn observe(&self, out: &mut [f32]) {
+        let size = self.observation_size();
+        assert_eq!(out.len(), size * self.config.num_envs);
+
+        for (i, (lane, out)) in self.lanes.iter().zip(out.chunks_mut(size)).enumerate() {
+            let occupancy = &self.occupancy[i * self.board.cells..(i + 1) * self.board.cells];
+            let body = &self.bodies[i * self.board.max_length..(i + 1) * self.board.max_length];
+            match self.config.observation {
+                ObservationKind::Grid => lane.grid_observation(&self.board, body, out),
+                ObservationKind::Features => {
+                    lane.feature_observation(&self.board, occupancy, body, out)
+                }
+            }
+        }
+    }
+}
+
+impl Lane {
+    fn empty() -> Lane {
+        Lane {
+            rng: 0,
+            direction: Direction::Right,
+            head: 0,
+            length: 0,
+            pending_growth: 0,
+            food: NO_CELL,
+            powerups: [None; MAX_POWERUPS],
+            speed_boost_timer: 0.0,
+            shield_timer: 0.0,
+            combo_count: 0,
+            combo_timer: 0.0,
+            score: 0,
+            steps: 0,
+            done: false,
+        }
+    }
+
+    fn reset(&mut self, board: &Board, seed: u64, occupancy: &mut [u8], body: &mut [u16]) {
+        *self = Lane::empty();
+        self.rng = seed;
+        occupancy.iter_mut().for_each(|c| *c = 0);
+
+        for (i, x) in [2, 3, 4].iter().enumerate() {
+            let cell = board.cell(*x, 2);
+            body[i] = cell;
+            occupancy[cell as usize] += 1;
+        }
+        self.head = 2;
+        self.length = 3;
+        self.food = board.cell(5, 3);
+    }
+
+    fn step(
+        &mut self,
+        board: &Board,
+        config: &BatchConfig,
+        action: Action,
+        occupancy: &mut [u8],
+        body: &mut [u16],
+    ) -> (f32, bool) {
+        if self.done {
+            if !config.auto_reset {
+                return (0.0, true);
+            }
+            let seed = self.next_u64();
+            self.reset(board, seed, occupancy, body);
+        }
+
+        let dir = action
+            .direction()
+            .filter(|d| *d != self.direction.opposite());
+
+        let period = if self.speed_boost_timer > 0.0 {
+            MOVING_PERIOD * SPEED_BOOST_PERIOD_SCALE
+        } else {
+            MOVING_PERIOD
+        };
+        self.update_timers(period);
+        self.update_pickups(board, period, occupancy, body);
+
+        let score_before = self.score;
+        let (alive, ate_food) = self.move_snake(board, dir, occupancy, body);
+        self.steps += 1;
+
+        let reward_config = &config.reward;
+        let mut reward = reward_config.step;
+        if ate_food {
+            reward += reward_config.food;
+        } else if self.score > score_before {
+            reward += reward_config.powerup;
+        }
+        if !alive {
+            reward += reward_config.death;
+        }
+
+        let truncated = config.max_steps.is_some_and(|max| self.steps >= max);
+        self.done = !alive || truncated;
+        (reward, self.done)
+    }
+
+    fn update_timers(&mut self, delta_time: f64) {
+        self.speed_boost_timer = (self.speed_boost_timer - delta_time).max(0.0);
+        self.shield_timer = (self.shield_timer - delta_time).max(0.0);
+
+        if self.combo_timer > 0.0 {
+            self.combo_timer -= delta_time;
+            if self.combo_timer <= 0.0 {
+                self.combo_count = 0;
+            }
+        }
+    }
+
+    fn update_pickups(&mut self, board: &Board, delta_time: f64, occupancy: &[u8], body: &[u16]) {
+        for slot in self.powerups.iter_mut() {
+            if let Some(powerup) = slot {
+                powerup.timer += delta_time;
+                if powerup.timer >= POWERUP_LIFETIME {
+                    *slot = None;
+                }
+            }
+        }
+
+        if self.random_f64() < POWERUP_SPAWN_CHANCE {
+            let index = self.powerups.iter().position(|p| p.is_none());
+            if let Some((index, cell)) = index.zip(self.free_cell(board, occupancy, body)) {
+                let powerup_type = if self.random_f64() < 0.5 {
+                    PowerUpType::Speed
+                } else {
+                    PowerUpType::Shield
+                };
+                self.powerups[index] = Some(PowerUpSlot {
+                    cell,
+                    powerup_type,
+                    timer: 0.0,
+                });
+            }
+        }
+
+        if self.food == NO_CELL {
+            self.food = self.free_cell(board, occupancy, body).unwrap_or(NO_CELL);
+        }
+    }
+
+    fn move_snake(
+        &mut self,
+        board: &Board,
+        dir: Option<Direction>,
+        occupancy: &mut [u8],
+        body: &mut [u16],
+    ) -> (bool, bool) {
+        let direction = dir.unwrap_or(self.direction);
+        let (head_x, head_y) = board.position(body[self.head as usize]);
+        let (next_x, next_y) = match direction {
+            Direction::Up => (head_x, head_y - 1),
+            Direction::Down => (head_x, head_y + 1),
+            Direction::Left => (head_x - 1, head_y),
+            Direction::Right => (head_x + 1, head_y),
+        };
+        let shielded = self.shield_timer > 0.0;
+
+        if board.is_wall(next_x, next_y) {
+            return (shielded, false);
+        }
+
+        let next = board.cell(next_x, next_y);
+        if occupancy[next as usize] > 0 && Some(next) != self.leaving_tail(board, body) && !shielded
+        {
+            return (false, false);
+        }
+
+        let tail = body[self.tail_index(board)];
+        self.direction = direction;
+        self.head = ((self.head as usize + 1) % board.max_length) as u32;
+        body[self.head as usize] = next;
+        occupancy[next as usize] += 1;
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            self.length += 1;
+        } else {
+            occupancy[tail as usize] -= 1;
+        }
+
+        let ate_food = next == self.food;
+        if ate_food {
+            self.food = NO_CELL;
+            self.pending_growth += 1;
+            self.combo_count += 1;
+            self.combo_timer = COMBO_WINDOW;
+            let combo_bonus = if self.combo_count > 1 {
+                self.combo_count * 2
+            } else {
+                0
+            };
+            self.score += FOOD_SCORE + combo_bonus;
+        }
+
+        if let Some(slot) = self
+            .powerups
+            .iter_mut()
+            .find(|p| p.is_some_and(|p| p.cell == next))
+        {
+            match slot.take().map(|p| p.powerup_type) {
+                Some(PowerUpType::Speed) => self.speed_boost_timer = SPEED_BOOST_DURATION,
+                Some(PowerUpType::Shield) => self.shield_timer = SHIELD_DURATION,
+                _ => {}
+            }
+            self.score += POWERUP_SCORE;
+        }
+
+        (true, ate_food)
+    }
+
+    fn tail_index(&self, board: &Board) -> usize {
+        (self.head as usize + board.max_length + 1 - self.length as usize) % board.max_length
+    }
+
+    /// The tail cell the next move frees, unless the snake is still growing
+    /// and keeps it, as `Snake` does.
+    fn leaving_tail(&self, board: &Board, body: &[u16]) -> Option<u16> {
+        Some(body[self.tail_index(board)]).filter(|_| self.pending_growth == 0)
+    }
+
+    /// A random empty cell inside the walls, or `None` when there is none.
+    fn free_cell(&mut self, board: &Board, occupancy: &[u8], body: &[u16]) -> Option<u16> {
+        let tail = self.leaving_tail(board, body);
+        let is_free = |lane: &Lane, cell: u16| {
+            let (x, y) = board.position(cell);
+            !board.is_wall(x, y)
+                && (occupancy[cell as usize] == 0 || Some(cell) == tail)
+                && cell != lane.food
+                && !lane
+                    .powerups
+                    .iter()
+                    .any(|p| p.is_some_and(|p| p.cell == cell))
+        };
+
+        for _ in 0..SPAWN_ATTEMPTS {
+            let x = 1 + self.random_below((board.width - 2) as u64) as i32;
+            let y = 1 + self.random_below((board.height - 2) as u64) as i32;
+            let cell = board.cell(x, y);
+            if is_free(self, cell) {
+                return Some(cell);
+            }
+        }
+
+        let cells = 0..board.cells as u16;
+        let free = cells.clone().filter(|&cell| is_free(self, cell)).count();
+        if free == 0 {
+            return None;
+        }
+        let nth = self.random_below(free as u64) as usize;
+        cells.filter(|&cell| is_free(self, cell)).nth(nth)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn random_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn random_below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    fn grid_observation(&self, board: &Board, body: &[u16], out: &mut [f32]) {
+        out.iter_mut().for_each(|v| *v = 0.0);
+        let plane = board.cells;
+
+        for i in 1..self.length as usize {
+            let index = (self.head as usize + board.max_length - i) % board.max_length;
+            out[body[index] as usize] = 1.0;
+        }
+        out[plane + body[self.head as usize] as usize] = 1.0;
+        if self.food != NO_CELL {
+            out[2 * plane + self.food as usize] = 1.0;
+        }
+        for powerup in self.powerups.iter().flatten() {
+            out[3 * plane + powerup.cell as usize] = 1.0;
+        }
+        for cell in 0..plane {
+            let (x, y) = board.position(cell as u16);
+            if board.is_wall(x, y) {
+                out[4 * plane + cell] = 1.0;
+            }
+        }
+    }
+
+    fn feature_observation(&self, board: &Board, occupancy: &[u8], body: &[u16], out: &mut [f32]) {
+        let (head_x, head_y) = board.position(body[self.head as usize]);
+        let tail = self.leaving_tail(board, body);
+        let dir = self.direction;
+        let (left, right) = match dir {
+            Direction::Up => (Direction::Left, Direction::Right),
+            Direction::Down => (Direction::Right, Direction::Left),
+            Direction::Left => (Direction::Down, Direction::Up),
+            Direction::Right => (Direction::Up, Direction::Down),
+        };
+
+        for (i, d) in [dir, left, right].iter().enumerate() {
+            let (x, y) = match *d {
+                Direction::Up => (head_x, head_y - 1),
+                Direction::Down => (head_x, head_y + 1),
+                Direction::Left => (head_x - 1, head_y),
+                Direction::Right => (head_x + 1, head_y),
+            };
+            let danger = board.is_wall(x, y) || {
+                let cell = board.cell(x, y);
+                occupancy[cell as usize] > 0 && Some(cell) != tail
+            };
+            out[i] = danger as u8 as f32;
+        }
+        for (i, d) in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .iter()
+        .enumerate()
+        {
+            out[3 + i] = (dir == *d) as u8 as f32;
+        }
+
+        let mut target_features = |offset: usize, cell: Option<u16>| {
+            let values = match cell {
+                Some(cell) => {
+                    let (x, y) = board.position(cell);
+                    [
+                        (x - head_x).signum() as f32,
+                        (y - head_y).signum() as f32,
+                        1.0,
+                    ]
+                }
+                None => [0.0, 0.0, 0.0],
+            };
+            out[offset..offset + 3].copy_from_slice(&values);
+        };
+
+        let nearest_powerup = self
+            .powerups
+            .iter()
+            .flatten()
+            .map(|p| p.cell)
+            .min_by_key(|cell| {
+                let (x, y) = board.position(*cell);
+                (x - head_x).abs() + (y - head_y).abs()
+            });
+
+        target_features(7, Some(self.food).filter(|c| *c != NO_CELL));
+        target_features(10, nearest_powerup);
+        out[13] = self.length as f32 / board.max_length as f32;
+    }
+}
+
+impl Board {
+    fn cell(&self, x: i32, y: i32) -> u16 {
+        (y * self.width + x) as u16
+    }
+
+    fn position(&self, cell: u16) -> (i32, i32) {
+        (cell as i32 % self.width, cell as i32 / self.width)
+    }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        x <= 0 || y <= 0 || x >= self.width - 1 || y >= self.height - 1
+    }
+}
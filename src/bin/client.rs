@@ -0,0 +1,118 @@
+extern crate rust_snakegame;
+
+use std::thread;
+use std::time::Duration;
+
+use rust_snakegame::client::NetClient;
+use rust_snakegame::net::NetState;
+use rust_snakegame::snake::Direction;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let address = flag_value("--address").map_or("127.0.0.1:7878", |v| v.as_str());
+    let name = flag_value("--name").map_or("Bot", |v| v.as_str());
+    let max_ticks: u32 = flag_value("--ticks")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(600);
+    let drop_after: Option<u32> = flag_value("--drop-after").and_then(|v| v.parse().ok());
+
+    let mut client = match NetClient::connect(address, name) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Could not join {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Joined {} as player {} (token {:x})",
+        address,
+        client.player_index() + 1,
+        client.token()
+    );
+
+    let mut dropped = false;
+    let mut last_tick = 0;
+    while client.state().tick < max_ticks {
+        if let Err(e) = client.poll() {
+            eprintln!("Connection lost ({}), resuming", e);
+            if let Err(e) = client.reconnect() {
+                eprintln!("Could not resume: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        let tick = client.state().tick;
+        if tick != last_tick {
+            last_tick = tick;
+            let bounds = (client.width(), client.height());
+            if let Some(dir) = steer(client.state(), client.player_index(), bounds) {
+                let _ = client.send_direction(dir);
+            }
+        }
+
+        if !dropped && drop_after.is_some_and(|after| tick >= after) {
+            dropped = true;
+            println!("Dropping connection at tick {}", tick);
+            if let Err(e) = client.reconnect() {
+                eprintln!("Could not resume: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    let state = client.state();
+    for player in &state.players {
+        println!("{}: {}", player.name, player.score);
+    }
+}
+
+fn steer(state: &NetState, index: usize, (width, height): (i32, i32)) -> Option<Direction> {
+    let player = state.players.get(index)?;
+    let &(head_x, head_y) = player.body.first()?;
+    let (food_x, food_y) = state
+        .foods
+        .iter()
+        .filter(|food| !food.food_type.is_rotten())
+        .min_by_key(|food| (food.x - head_x).abs() + (food.y - head_y).abs())
+        .map_or((head_x, head_y), |food| (food.x, food.y));
+
+    let blocked = |x: i32, y: i32| {
+        x <= 0
+            || y <= 0
+            || x >= width - 1
+            || y >= height - 1
+            || state
+                .players
+                .iter()
+                .any(|p| p.alive && p.body.contains(&(x, y)))
+    };
+
+    [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ]
+    .iter()
+    .filter(|d| **d != player.direction.opposite())
+    .map(|d| {
+        let (x, y) = match d {
+            Direction::Up => (head_x, head_y - 1),
+            Direction::Down => (head_x, head_y + 1),
+            Direction::Left => (head_x - 1, head_y),
+            Direction::Right => (head_x + 1, head_y),
+        };
+        (*d, x, y)
+    })
+    .filter(|(_, x, y)| !blocked(*x, *y))
+    .min_by_key(|(_, x, y)| (x - food_x).abs() + (y - food_y).abs())
+    .map(|(d, _, _)| d)
+}
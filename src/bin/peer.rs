@@ -0,0 +1,96 @@
+extern crate rust_snakegame;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snakegame::ai;
+use rust_snakegame::lockstep::{Lockstep, LockstepConfig};
+
+const PEER_LEFT_GRACE: Duration = Duration::from_secs(2);
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let defaults = LockstepConfig::default();
+    let config = LockstepConfig {
+        player: flag_value("--player")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.player),
+        peers: flag_value("--peers")
+            .map(|v| v.split(',').map(|s| s.to_string()).collect())
+            .unwrap_or_else(|| vec!["127.0.0.1:7879".to_string(), "127.0.0.1:7880".to_string()]),
+        name: flag_value("--name").cloned().unwrap_or(defaults.name),
+        tick_rate: flag_value("--tick-rate")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.tick_rate),
+        input_delay: flag_value("--input-delay")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.input_delay),
+        seed: flag_value("--seed").and_then(|v| v.parse().ok()),
+        ..defaults
+    };
+    let max_ticks: u32 = flag_value("--ticks")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(600);
+
+    let mut session = match Lockstep::connect(config.clone()) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Could not connect to peers: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Connected as player {} of {}",
+        config.player + 1,
+        config.peers.len()
+    );
+
+    let mut last_update = Instant::now();
+    let mut stalled_since = None;
+    while session.verified_tick() < max_ticks && session.desync().is_none() {
+        if let Some(dir) = ai::choose_direction(session.game(), session.player_index()) {
+            session.set_direction(dir);
+        }
+
+        let now = Instant::now();
+        if let Err(e) = session.update((now - last_update).as_secs_f64()) {
+            eprintln!("Session ended: {}", e);
+            std::process::exit(1);
+        }
+        last_update = now;
+
+        let peer_left = (0..config.peers.len()).any(|peer| !session.is_connected(peer));
+        if !peer_left {
+            stalled_since = None;
+        } else if stalled_since.get_or_insert(now).elapsed() > PEER_LEFT_GRACE {
+            eprintln!("A peer left before tick {}", max_ticks);
+            std::process::exit(1);
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    for player in session.game().players() {
+        println!("{}: {}", player.name(), player.score());
+    }
+    println!(
+        "Verified {} ticks with {} rollbacks",
+        session.verified_tick(),
+        session.rollbacks()
+    );
+
+    if let Some(desync) = session.desync() {
+        eprintln!(
+            "Desync with peer {} at tick {}: {} differs",
+            desync.peer + 1,
+            desync.tick,
+            desync.field
+        );
+        std::process::exit(1);
+    }
+}
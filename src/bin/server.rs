@@ -0,0 +1,53 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::server::{Server, ServerConfig};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let defaults = ServerConfig::default();
+    let config = ServerConfig {
+        address: flag_value("--address").cloned().unwrap_or(defaults.address),
+        players: flag_value("--players")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.players),
+        tick_rate: flag_value("--tick-rate")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.tick_rate),
+        input_delay: flag_value("--input-delay")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(defaults.input_delay),
+        seed: flag_value("--seed").and_then(|v| v.parse().ok()),
+        broadcast: flag_value("--broadcast").cloned(),
+        ..defaults
+    };
+    let max_ticks = flag_value("--ticks").and_then(|v| v.parse().ok());
+
+    let mut server = match Server::bind(config.clone()) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Could not listen on {}: {}", config.address, e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Listening on {} for {} players",
+        server.local_addr().unwrap(),
+        config.players
+    );
+
+    if let Err(e) = server.run(max_ticks) {
+        eprintln!("Server stopped: {}", e);
+        std::process::exit(1);
+    }
+
+    for player in server.game().players() {
+        println!("{}: {}", player.name(), player.score());
+    }
+}
@@ -0,0 +1,103 @@
+extern crate rust_snakegame;
+
+use std::fs;
+use std::path::Path;
+
+use rust_snakegame::daily::Date;
+use rust_snakegame::replay::Replay;
+use rust_snakegame::submission::{ScoreRecord, ScoreServer, SCORE_KEY_ENV};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    let Some(key) = flag_value("--key")
+        .cloned()
+        .or_else(|| std::env::var(SCORE_KEY_ENV).ok())
+    else {
+        eprintln!(
+            "A signing key is required: pass --key or set {}",
+            SCORE_KEY_ENV
+        );
+        std::process::exit(2);
+    };
+
+    if let Some(replay_path) = flag_value("--sign") {
+        let name = flag_value("--name").map_or("Player", |name| name.as_str());
+        let out = flag_value("--out").map_or("score.record", |out| out.as_str());
+        let replay = match Replay::load(Path::new(replay_path)) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Could not load replay {}: {}", replay_path, e);
+                std::process::exit(1);
+            }
+        };
+        let record = ScoreRecord::from_replay(name, Date::today(), replay);
+        if let Err(e) = fs::write(out, record.sign(key.as_bytes())) {
+            eprintln!("Could not write {}: {}", out, e);
+            std::process::exit(1);
+        }
+        println!("Signed score {} for {} into {}", record.score, name, out);
+        return;
+    }
+
+    let server =
+        flag_value("--server").map(
+            |dir| match ScoreServer::open(Path::new(dir), key.as_bytes()) {
+                Ok(server) => server,
+                Err(e) => {
+                    eprintln!("Could not open server directory {}: {}", dir, e);
+                    std::process::exit(1);
+                }
+            },
+        );
+
+    let files: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, arg)| !arg.starts_with("--") && !args[i - 1].starts_with("--"))
+        .map(|(_, arg)| arg)
+        .collect();
+    if files.is_empty() {
+        eprintln!("Usage: verify --key KEY [--server DIR] RECORD...");
+        eprintln!("       verify --key KEY --sign REPLAY [--name NAME] [--out RECORD]");
+        std::process::exit(2);
+    }
+
+    let mut rejected = 0;
+    for file in files {
+        let signed = match fs::read(file) {
+            Ok(signed) => signed,
+            Err(e) => {
+                println!("{}: REJECTED ({})", file, e);
+                rejected += 1;
+                continue;
+            }
+        };
+
+        let result = match &server {
+            Some(server) => server.submit(&signed).map(|rank| match rank {
+                Some(rank) => format!("accepted at rank {}", rank + 1),
+                None => "accepted, not in the top scores".to_string(),
+            }),
+            None => ScoreRecord::verify(&signed, key.as_bytes())
+                .map(|record| format!("{} scored {}", record.name, record.score)),
+        };
+        match result {
+            Ok(outcome) => println!("{}: OK ({})", file, outcome),
+            Err(e) => {
+                println!("{}: REJECTED ({})", file, e);
+                rejected += 1;
+            }
+        }
+    }
+
+    if rejected > 0 {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,258 @@
+use std::io;
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use piston_window::*;
+
+use crate::drawing::{draw_block, draw_eyes, draw_rectangle, to_gui_coord, BLOCK_SIZE};
+use crate::game::BORDER_COLOR;
+use crate::net::{invalid, ClientMessage, Connection, NetState, ServerMessage, PROTOCOL_VERSION};
+use crate::player::PLAYER_COLORS;
+use crate::snake::Direction;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct NetClient {
+    address: String,
+    name: String,
+    connection: Connection<TcpStream>,
+    player: usize,
+    token: u64,
+    input_delay: u32,
+    width: i32,
+    height: i32,
+    state: NetState,
+}
+
+impl NetClient {
+    pub fn connect(address: &str, name: &str) -> io::Result<NetClient> {
+        NetClient::handshake(address, name, None)
+    }
+
+    pub fn resume(address: &str, name: &str, token: u64) -> io::Result<NetClient> {
+        NetClient::handshake(address, name, Some(token))
+    }
+
+    fn handshake(address: &str, name: &str, resume_token: Option<u64>) -> io::Result<NetClient> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        let mut connection = Connection::new(stream);
+
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+            resume_token,
+        };
+        connection.send(&hello.encode());
+        connection.flush()?;
+
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while Instant::now() < deadline {
+            let frames = connection.receive()?;
+            if let Some(frame) = frames.first() {
+                return match ServerMessage::decode(frame)? {
+                    ServerMessage::Welcome {
+                        player,
+                        token,
+                        input_delay,
+                        width,
+                        height,
+                        ..
+                    } => {
+                        let mut client = NetClient {
+                            address: address.to_string(),
+                            name: name.to_string(),
+                            connection,
+                            player: player as usize,
+                            token,
+                            input_delay,
+                            width,
+                            height,
+                            state: NetState::empty(),
+                        };
+                        for frame in &frames[1..] {
+                            client.handle(frame)?;
+                        }
+                        Ok(client)
+                    }
+                    ServerMessage::Rejected(reason) => {
+                        Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason))
+                    }
+                    _ => Err(invalid("expected welcome".to_string())),
+                };
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        Err(io::ErrorKind::TimedOut.into())
+    }
+
+    /// Opens a new connection that takes over this client's slot.
+    pub fn reconnect(&mut self) -> io::Result<()> {
+        *self = NetClient::resume(&self.address, &self.name, self.token)?;
+        Ok(())
+    }
+
+    pub fn player_index(&self) -> usize {
+        self.player
+    }
+
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn state(&self) -> &NetState {
+        &self.state
+    }
+
+    /// Processes everything the server has sent so far without blocking.
+    /// Returns whether the state changed.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let frames = self.connection.receive()?;
+        for frame in &frames {
+            self.handle(frame)?;
+        }
+        Ok(!frames.is_empty())
+    }
+
+    fn handle(&mut self, frame: &[u8]) -> io::Result<()> {
+        match ServerMessage::decode(frame)? {
+            ServerMessage::Snapshot(state) => self.state = state,
+            ServerMessage::Delta(delta) => self.state.apply(delta),
+            ServerMessage::Rejected(reason) => {
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, reason))
+            }
+            ServerMessage::Welcome { .. } | ServerMessage::Spectate { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Schedules a turn for `input_delay` ticks after the latest state seen.
+    pub fn send_direction(&mut self, direction: Direction) -> io::Result<()> {
+        let input = ClientMessage::Input {
+            tick: self.state.tick + self.input_delay,
+            direction,
+        };
+        self.connection.send(&input.encode());
+        self.connection.flush()
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        draw_state(
+            &self.state,
+            self.width,
+            self.height,
+            self.player,
+            con,
+            g,
+            glyphs,
+        );
+    }
+}
+
+/// Draws a networked game state, marking `highlight` in the score list.
+pub fn draw_state(
+    state: &NetState,
+    width: i32,
+    height: i32,
+    highlight: usize,
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+) {
+    for (i, player) in state.players.iter().enumerate() {
+        let (mut color, mut head_color) = PLAYER_COLORS[i % PLAYER_COLORS.len()];
+        if !player.alive {
+            color[3] = 0.35;
+            head_color[3] = 0.35;
+        }
+        for (n, &(x, y)) in player.body.iter().enumerate() {
+            if n == 0 {
+                draw_block(head_color, x, y, con, g);
+                draw_eyes(player.direction, x, y, con, g);
+            } else {
+                draw_block(color, x, y, con, g);
+            }
+        }
+    }
+
+    for food in &state.foods {
+        draw_rectangle(food.food_type.color(), food.x, food.y, 1, 1, con, g);
+    }
+
+    for powerup in &state.powerups {
+        let size = BLOCK_SIZE * 0.8;
+        let offset = (BLOCK_SIZE - size) / 2.0;
+        rectangle(
+            powerup.powerup_type.effect().color(),
+            [
+                to_gui_coord(powerup.x) + offset,
+                to_gui_coord(powerup.y) + offset,
+                size,
+                size,
+            ],
+            con.transform,
+            g,
+        );
+    }
+
+    draw_rectangle(BORDER_COLOR, 0, 0, width, 1, con, g);
+    draw_rectangle(BORDER_COLOR, 0, height - 1, width, 1, con, g);
+    draw_rectangle(BORDER_COLOR, 0, 0, 1, height, con, g);
+    draw_rectangle(BORDER_COLOR, width - 1, 0, 1, height, con, g);
+
+    let mut hud_y = 30.0;
+    for (i, player) in state.players.iter().enumerate() {
+        let marker = if i == highlight { ">" } else { " " };
+        let status = if !player.connected {
+            " (offline)"
+        } else if !player.alive {
+            " (dead)"
+        } else {
+            ""
+        };
+        let line = format!("{} {}: {}{}", marker, player.name, player.score, status);
+        text::Text::new_color(PLAYER_COLORS[i % PLAYER_COLORS.len()].0, 16)
+            .draw(
+                &line,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(10.0, hud_y),
+                g,
+            )
+            .unwrap();
+        hud_y += 20.0;
+    }
+
+    let banner = if !state.started {
+        Some("Waiting for players...")
+    } else if state.game_over {
+        Some("Round over")
+    } else {
+        None
+    };
+    if let Some(banner) = banner {
+        text::Text::new_color([1.0, 1.0, 1.0, 1.0], 28)
+            .draw(
+                banner,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(
+                    to_gui_coord(width) / 2.0 - 120.0,
+                    to_gui_coord(height) / 2.0,
+                ),
+                g,
+            )
+            .unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use piston_window::types::Color;
+
+use crate::drawing::to_gui_coord;
+use crate::game::Game;
+use crate::raster::render_game;
+
+pub const CLIP_FPS: u16 = 20;
+pub const DEFAULT_CLIP_SECONDS: f64 = 10.0;
+
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipFormat {
+    Gif,
+    Apng,
+}
+
+impl ClipFormat {
+    /// Picks the format from a file extension, defaulting to GIF.
+    pub fn from_path(path: &Path) -> ClipFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") | Some("apng") => ClipFormat::Apng,
+            _ => ClipFormat::Gif,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Apng => "png",
+        }
+    }
+}
+
+/// Keeps the last few seconds of play as game states, sampled at `CLIP_FPS`.
+pub struct ClipBuffer {
+    frames: VecDeque<Game>,
+    capacity: usize,
+    elapsed: f64,
+}
+
+impl ClipBuffer {
+    pub fn new(seconds: f64) -> ClipBuffer {
+        ClipBuffer {
+            frames: VecDeque::new(),
+            capacity: ((seconds * CLIP_FPS as f64).ceil() as usize).max(1),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn update(&mut self, delta_time: f64, game: &Game) {
+        self.elapsed += delta_time;
+        if self.elapsed < 1.0 / CLIP_FPS as f64 && !self.frames.is_empty() {
+            return;
+        }
+        self.elapsed = 0.0;
+
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(game.clone());
+    }
+
+    pub fn frames(&self) -> Vec<Game> {
+        self.frames.iter().cloned().collect()
+    }
+}
+
+/// Renders `frames` off-screen and writes them as an animated image.
+pub fn export_clip(
+    frames: &[Game],
+    background: Color,
+    format: ClipFormat,
+    path: &Path,
+) -> io::Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no frames to export",
+        ));
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let width = to_gui_coord(first.width()) as u32;
+    let height = to_gui_coord(first.height()) as u32;
+
+    match format {
+        ClipFormat::Gif => {
+            let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])
+                .map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            for game in frames {
+                let mut pixels = render_game(game, background).into_pixels();
+                let mut frame = gif::Frame::from_rgba_speed(
+                    width as u16,
+                    height as u16,
+                    &mut pixels,
+                    GIF_QUANTIZE_SPEED,
+                );
+                frame.delay = 100 / CLIP_FPS;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+        }
+        ClipFormat::Apng => {
+            let mut encoder = png::Encoder::new(file, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .set_animated(frames.len() as u32, 0)
+                .map_err(io::Error::other)?;
+            encoder
+                .set_frame_delay(1, CLIP_FPS)
+                .map_err(io::Error::other)?;
+            let mut writer = encoder.write_header().map_err(io::Error::other)?;
+            for game in frames {
+                writer
+                    .write_image_data(render_game(game, background).pixels())
+                    .map_err(io::Error::other)?;
+            }
+            writer.finish().map_err(io::Error::other)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::drawing::{draw_rectangle, to_gui_coord};
+use crate::net::{invalid, Reader, Writer};
+
+pub const DAILY_HISTORY_PATH: &str = "daily/history";
+pub const DAILY_HISTORY_VERSION: u16 = 1;
+
+const HISTORY_MAGIC: u32 = u32::from_le_bytes(*b"SNKD");
+const HISTORY_ROWS: usize = 10;
+const HUD_COLOR: Color = [0.96, 0.71, 0.20, 1.0];
+const HISTORY_BACKGROUND: Color = [0.0, 0.0, 0.0, 0.8];
+const HISTORY_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+/// A UTC calendar date, worked out from the local clock only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Date::from_days((seconds / 86_400) as i64)
+    }
+
+    /// Converts days since 1970-01-01 to a date in the proleptic Gregorian
+    /// calendar.
+    pub fn from_days(days: i64) -> Date {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// The game seed for this date; the same on every machine.
+    pub fn seed(&self) -> u64 {
+        let mut z = (self.year as u64) * 10_000 + self.month as u64 * 100 + self.day as u64;
+        z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyResult {
+    pub date: Date,
+    pub score: u32,
+    pub length: u32,
+    pub duration: f64,
+}
+
+/// Scored daily attempts, oldest first, with at most one per date.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DailyHistory {
+    results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+    pub fn results(&self) -> &[DailyResult] {
+        &self.results
+    }
+
+    pub fn result(&self, date: Date) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.date == date)
+    }
+
+    /// Adds `result` unless its date already has one. Returns whether it
+    /// was added.
+    pub fn record(&mut self, result: DailyResult) -> bool {
+        if self.result(result.date).is_some() {
+            return false;
+        }
+        let index = self.results.partition_point(|r| r.date < result.date);
+        self.results.insert(index, result);
+        true
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.u32(HISTORY_MAGIC);
+        w.u16(DAILY_HISTORY_VERSION);
+        w.list(&self.results, |w, result| {
+            w.i32(result.date.year);
+            w.u8(result.date.month as u8);
+            w.u8(result.date.day as u8);
+            w.u32(result.score);
+            w.u32(result.length);
+            w.f64(result.duration);
+        });
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<DailyHistory> {
+        let mut r = Reader::new(payload);
+        if r.u32()? != HISTORY_MAGIC {
+            return Err(invalid("not a daily history file".to_string()));
+        }
+        let version = r.u16()?;
+        if version != DAILY_HISTORY_VERSION {
+            return Err(invalid(format!(
+                "daily history version {} is not supported, expected {}",
+                version, DAILY_HISTORY_VERSION
+            )));
+        }
+
+        let mut history = DailyHistory::default();
+        for result in r.list(|r| {
+            Ok(DailyResult {
+                date: Date {
+                    year: r.i32()?,
+                    month: r.u8()? as u32,
+                    day: r.u8()? as u32,
+                },
+                score: r.u32()?,
+                length: r.u32()?,
+                duration: r.f64()?,
+            })
+        })? {
+            history.record(result);
+        }
+        Ok(history)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    /// Loads the history at `path`, or an empty one if there is none yet.
+    pub fn load(path: &Path) -> io::Result<DailyHistory> {
+        match fs::read(path) {
+            Ok(payload) => DailyHistory::decode(&payload),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(DailyHistory::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Today's challenge. Only the first attempt of the day is scored; later
+/// ones on the same seed are practice.
+pub struct Daily {
+    date: Date,
+    history: DailyHistory,
+    path: PathBuf,
+    show_history: bool,
+}
+
+impl Daily {
+    pub fn new(date: Date, history: DailyHistory, path: &Path) -> Daily {
+        Daily {
+            date,
+            history,
+            path: path.to_path_buf(),
+            show_history: false,
+        }
+    }
+
+    pub fn load(date: Date, path: &Path) -> io::Result<Daily> {
+        Ok(Daily::new(date, DailyHistory::load(path)?, path))
+    }
+
+    pub fn date(&self) -> Date {
+        self.date
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.date.seed()
+    }
+
+    pub fn history(&self) -> &DailyHistory {
+        &self.history
+    }
+
+    pub fn is_scored(&self) -> bool {
+        self.history.result(self.date).is_some()
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+    }
+
+    /// Records the outcome of an attempt if it is today's first. Returns
+    /// whether it counted.
+    pub fn finish(&mut self, score: u32, length: usize, duration: f64) -> io::Result<bool> {
+        let recorded = self.history.record(DailyResult {
+            date: self.date,
+            score,
+            length: length as u32,
+            duration,
+        });
+        if recorded {
+            self.history.save(&self.path)?;
+        }
+        Ok(recorded)
+    }
+
+    pub fn draw(&self, width: i32, height: i32, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let status = match self.history.result(self.date) {
+            Some(result) => format!("Daily {} - practice (scored {})", self.date, result.score),
+            None => format!("Daily {}", self.date),
+        };
+        text::Text::new_color(HUD_COLOR, 16)
+            .draw(
+                &status,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(
+                    to_gui_coord(width) / 2.0 - 80.0,
+                    to_gui_coord(height) - 35.0,
+                ),
+                g,
+            )
+            .unwrap();
+
+        if self.show_history {
+            self.draw_history(width, height, con, g, glyphs);
+        }
+    }
+
+    fn draw_history(
+        &self,
+        width: i32,
+        height: i32,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        draw_rectangle(HISTORY_BACKGROUND, 0, 0, width, height, con, g);
+
+        let mut lines = vec![
+            "DAILY HISTORY".to_string(),
+            "Date          Score   Length   Time".to_string(),
+        ];
+        lines.extend(
+            self.history
+                .results()
+                .iter()
+                .rev()
+                .take(HISTORY_ROWS)
+                .map(|r| {
+                    format!(
+                        "{}    {:>5}    {:>5}   {:>4.0}s",
+                        r.date, r.score, r.length, r.duration
+                    )
+                }),
+        );
+        if self.history.results().is_empty() {
+            lines.push("No daily results yet".to_string());
+        }
+        lines.push("Press H to close".to_string());
+
+        let mut y = 80.0;
+        for (i, line) in lines.iter().enumerate() {
+            let size = if i == 0 { 28 } else { 18 };
+            text::Text::new_color(HISTORY_COLOR, size)
+                .draw(
+                    line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(to_gui_coord(width) / 2.0 - 170.0, y),
+                    g,
+                )
+                .unwrap();
+            y += if i == 0 { 40.0 } else { 28.0 };
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::io;
+
+use crate::net::{Reader, Writer};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Per-field hashes of a game state, in a fixed order, so two peers can
+/// compare a single checksum every tick and, when it differs, find the
+/// first field that diverged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateHashes {
+    fields: Vec<(String, u64)>,
+}
+
+impl StateHashes {
+    pub(crate) fn new() -> StateHashes {
+        StateHashes { fields: Vec::new() }
+    }
+
+    pub(crate) fn field(&mut self, name: impl Into<String>, write: impl FnOnce(&mut Writer)) {
+        let mut w = Writer::new();
+        write(&mut w);
+        self.fields
+            .push((name.into(), fnv1a(FNV_OFFSET, &w.finish())));
+    }
+
+    pub fn fields(&self) -> &[(String, u64)] {
+        &self.fields
+    }
+
+    pub fn checksum(&self) -> u64 {
+        self.fields.iter().fold(FNV_OFFSET, |hash, (_, field)| {
+            fnv1a(hash, &field.to_le_bytes())
+        })
+    }
+
+    /// Name of the first field whose hash differs from `other`, or `None`
+    /// when both states are identical.
+    pub fn first_divergence(&self, other: &StateHashes) -> Option<String> {
+        for (ours, theirs) in self.fields.iter().zip(&other.fields) {
+            if ours != theirs {
+                return Some(ours.0.clone());
+            }
+        }
+
+        let longer = if self.fields.len() > other.fields.len() {
+            self
+        } else {
+            other
+        };
+        longer
+            .fields
+            .get(self.fields.len().min(other.fields.len()))
+            .map(|(name, _)| name.clone())
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer) {
+        w.list(&self.fields, |w, (name, hash)| {
+            w.string(name);
+            w.u64(*hash);
+        });
+    }
+
+    pub(crate) fn read(r: &mut Reader) -> io::Result<StateHashes> {
+        Ok(StateHashes {
+            fields: r.list(|r| Ok((r.string()?, r.u64()?)))?,
+        })
+    }
+}
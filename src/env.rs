@@ -0,0 +1,318 @@
+use std::io;
+
+use crate::events::GameEvent;
+use crate::game::Game;
+use crate::snake::Direction;
+
+pub const GRID_CHANNELS: usize = 5;
+pub const FEATURE_COUNT: usize = 14;
+
+/// Smallest board that fits the starting snake and food inside the walls.
+const MIN_WIDTH: i32 = 7;
+const MIN_HEIGHT: i32 = 5;
+/// One past the last cell index a `u16` can hold, which `BatchEnv` reserves.
+const MAX_CELLS: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Keep,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Action {
+    pub fn direction(&self) -> Option<Direction> {
+        match *self {
+            Action::Keep => None,
+            Action::Up => Some(Direction::Up),
+            Action::Down => Some(Direction::Down),
+            Action::Left => Some(Direction::Left),
+            Action::Right => Some(Direction::Right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObservationKind {
+    /// `GRID_CHANNELS x height x width`, channels are body, head, food, powerups, walls.
+    Grid,
+    /// `FEATURE_COUNT` values: danger ahead/left/right, direction one-hot,
+    /// food direction, powerup direction and normalised length.
+    Features,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RewardConfig {
+    pub food: f32,
+    pub powerup: f32,
+    pub death: f32,
+    pub step: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> RewardConfig {
+        RewardConfig {
+            food: 1.0,
+            powerup: 0.5,
+            death: -1.0,
+            step: -0.01,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnvConfig {
+    pub width: i32,
+    pub height: i32,
+    pub observation: ObservationKind,
+    pub reward: RewardConfig,
+    pub max_steps: Option<u32>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            width: 27,
+            height: 22,
+            observation: ObservationKind::Grid,
+            reward: RewardConfig::default(),
+            max_steps: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepInfo {
+    pub score: u32,
+    pub length: usize,
+    pub steps: u32,
+    pub ate_food: bool,
+    pub collected_powerup: bool,
+    pub truncated: bool,
+}
+
+/// Fails when the board is too small for the starting snake and food, or
+/// has too many cells to index with a `u16`.
+pub(crate) fn check_board(width: i32, height: i32) -> io::Result<()> {
+    if width < MIN_WIDTH || height < MIN_HEIGHT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "board of {}x{} is smaller than {}x{}",
+                width, height, MIN_WIDTH, MIN_HEIGHT
+            ),
+        ));
+    }
+    if width as usize * height as usize >= MAX_CELLS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "board of {}x{} has more than {} cells",
+                width, height, MAX_CELLS
+            ),
+        ));
+    }
+    Ok(())
+}
+
+pub struct Env {
+    config: EnvConfig,
+    game: Game,
+    steps: u32,
+}
+
+impl Env {
+    /// Fails on the same boards as `BatchEnv::new`.
+    pub fn new(config: EnvConfig) -> io::Result<Env> {
+        check_board(config.width, config.height)?;
+        Ok(Env {
+            game: Game::with_seed(config.width, config.height, 0),
+            config,
+            steps: 0,
+        })
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn observation_shape(&self) -> Vec<usize> {
+        match self.config.observation {
+            ObservationKind::Grid => vec![
+                GRID_CHANNELS,
+                self.config.height as usize,
+                self.config.width as usize,
+            ],
+            ObservationKind::Features => vec![FEATURE_COUNT],
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game.reset(seed);
+        self.steps = 0;
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        self.game.step(action.direction());
+        self.steps += 1;
+
+        let events = self.game.take_events();
+        let ate_food = events
+            .iter()
+            .any(|e| matches!(e, GameEvent::FoodEaten { player: 0, .. }));
+        let collected_powerup = events.iter().any(|e| {
+            matches!(e, GameEvent::PowerUpCollected { player: 0, powerup_type }
+                if !powerup_type.effect().is_cursed())
+        });
+        let died = self.game.is_game_over();
+        let truncated = !died && self.config.max_steps.is_some_and(|max| self.steps >= max);
+
+        let reward_config = &self.config.reward;
+        let mut reward = reward_config.step;
+        if ate_food {
+            reward += reward_config.food;
+        }
+        if collected_powerup {
+            reward += reward_config.powerup;
+        }
+        if died {
+            reward += reward_config.death;
+        }
+
+        let info = StepInfo {
+            score: self.game.score(),
+            length: self.game.snake().length(),
+            steps: self.steps,
+            ate_food,
+            collected_powerup,
+            truncated,
+        };
+
+        (self.observe(), reward, died || truncated, info)
+    }
+
+    pub fn observe(&self) -> Observation {
+        let data = match self.config.observation {
+            ObservationKind::Grid => self.grid_observation(),
+            ObservationKind::Features => self.feature_observation(),
+        };
+
+        Observation {
+            shape: self.observation_shape(),
+            data,
+        }
+    }
+
+    fn grid_observation(&self) -> Vec<f32> {
+        let width = self.config.width;
+        let height = self.config.height;
+        let plane = (width * height) as usize;
+        let mut data = vec![0.0; GRID_CHANNELS * plane];
+
+        let mut set = |channel: usize, x: i32, y: i32| {
+            if x >= 0 && y >= 0 && x < width && y < height {
+                data[channel * plane + (y * width + x) as usize] = 1.0;
+            }
+        };
+
+        let (head_x, head_y) = self.game.snake().head_position();
+        for (x, y) in self.game.snake().body_positions().skip(1) {
+            set(0, x, y);
+        }
+        set(1, head_x, head_y);
+
+        for food in self.game.foods() {
+            set(2, food.x, food.y);
+        }
+
+        for powerup in self.game.powerups() {
+            set(3, powerup.x, powerup.y);
+        }
+
+        for x in 0..width {
+            set(4, x, 0);
+            set(4, x, height - 1);
+        }
+        for y in 0..height {
+            set(4, 0, y);
+            set(4, width - 1, y);
+        }
+
+        data
+    }
+
+    fn feature_observation(&self) -> Vec<f32> {
+        let snake = self.game.snake();
+        let (head_x, head_y) = snake.head_position();
+        let dir = snake.head_direction();
+        let (left, right) = match dir {
+            Direction::Up => (Direction::Left, Direction::Right),
+            Direction::Down => (Direction::Right, Direction::Left),
+            Direction::Left => (Direction::Down, Direction::Up),
+            Direction::Right => (Direction::Up, Direction::Down),
+        };
+
+        let mut data = Vec::with_capacity(FEATURE_COUNT);
+        for d in [dir, left, right] {
+            data.push(self.is_danger(snake.next_head_position(Some(d))) as u8 as f32);
+        }
+        for d in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            data.push((dir == d) as u8 as f32);
+        }
+
+        let target_features = |target: Option<(i32, i32)>| match target {
+            Some((x, y)) => [
+                (x - head_x).signum() as f32,
+                (y - head_y).signum() as f32,
+                1.0,
+            ],
+            None => [0.0, 0.0, 0.0],
+        };
+
+        let nearest_powerup = self
+            .game
+            .powerups()
+            .iter()
+            .min_by_key(|p| (p.x - head_x).abs() + (p.y - head_y).abs())
+            .map(|p| (p.x, p.y));
+
+        let nearest_food = self
+            .game
+            .nearest_food(head_x, head_y)
+            .map(|food| (food.x, food.y));
+
+        data.extend_from_slice(&target_features(nearest_food));
+        data.extend_from_slice(&target_features(nearest_powerup));
+
+        let cells = ((self.config.width - 2) * (self.config.height - 2)) as f32;
+        data.push(snake.length() as f32 / cells);
+
+        data
+    }
+
+    fn is_danger(&self, (x, y): (i32, i32)) -> bool {
+        x <= 0
+            || y <= 0
+            || x >= self.config.width - 1
+            || y >= self.config.height - 1
+            || self.game.snake().is_overlap_except_tail(x, y)
+    }
+}
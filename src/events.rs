@@ -0,0 +1,61 @@
+use crate::food::FoodType;
+use crate::game::Game;
+use crate::powerup::PowerUpType;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeathCause {
+    Wall,
+    Body,
+    HeadOn { other: usize },
+}
+
+/// Something that happened in the simulation. The game raises these as it
+/// runs, and subscribers such as achievements, audio or stats react to them
+/// without the game knowing about them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    FoodEaten {
+        player: usize,
+        food_type: FoodType,
+    },
+    PowerUpCollected {
+        player: usize,
+        powerup_type: PowerUpType,
+    },
+    /// A stored power-up was taken out of the inventory and started.
+    PowerUpActivated {
+        player: usize,
+        powerup_type: PowerUpType,
+    },
+    PowerUpExpired {
+        player: usize,
+        powerup_type: PowerUpType,
+    },
+    ComboChanged {
+        player: usize,
+        combo: u32,
+    },
+    ScoreChanged {
+        player: usize,
+        score: u32,
+    },
+    /// The player's speed level went up.
+    LevelUp {
+        player: usize,
+        level: u32,
+    },
+    ShieldSaved {
+        player: usize,
+    },
+    Died {
+        player: usize,
+        cause: DeathCause,
+    },
+    Restarted,
+}
+
+pub trait EventSubscriber {
+    /// Called once per event, with the game as it is after the update that
+    /// raised it.
+    fn on_event(&mut self, event: &GameEvent, game: &Game);
+}
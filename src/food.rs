@@ -0,0 +1,198 @@
+use piston_window::types::Color;
+
+use crate::game::FOOD_SCORE;
+
+const GOLDEN_LIFETIME: f64 = 6.0;
+const ROTTEN_LIFETIME: f64 = 8.0;
+const LARGE_GROWTH: usize = 4;
+
+/// How long ordinary food lies uneaten before it rots.
+pub const ROT_TIME: f64 = 15.0;
+/// Fleeing food steps away once a head comes this close.
+pub const FLEE_RADIUS: i32 = 4;
+pub const FLEE_PERIOD: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoodType {
+    Normal,
+    Golden,
+    Large,
+    Fleeing,
+    Rotten,
+}
+
+impl FoodType {
+    /// Every food type, in wire order.
+    pub const ALL: [FoodType; 5] = [
+        FoodType::Normal,
+        FoodType::Golden,
+        FoodType::Large,
+        FoodType::Fleeing,
+        FoodType::Rotten,
+    ];
+
+    pub(crate) fn id(self) -> u8 {
+        FoodType::ALL.iter().position(|t| *t == self).unwrap() as u8
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<FoodType> {
+        FoodType::ALL.get(id as usize).copied()
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            FoodType::Normal => [0.96, 0.71, 0.20, 1.0],
+            FoodType::Golden => [1.0, 0.92, 0.35, 1.0],
+            FoodType::Large => [0.95, 0.45, 0.15, 1.0],
+            FoodType::Fleeing => [0.40, 0.90, 0.85, 1.0],
+            FoodType::Rotten => [0.45, 0.50, 0.20, 1.0],
+        }
+    }
+
+    /// Points before the combo bonus and score multipliers.
+    pub fn score(self) -> u32 {
+        match self {
+            FoodType::Normal => FOOD_SCORE,
+            FoodType::Golden => FOOD_SCORE * 5,
+            FoodType::Large => FOOD_SCORE * 2,
+            FoodType::Fleeing => FOOD_SCORE * 3,
+            FoodType::Rotten => 0,
+        }
+    }
+
+    /// Segments the snake gains from eating it.
+    pub fn growth(self) -> usize {
+        match self {
+            FoodType::Large => LARGE_GROWTH,
+            FoodType::Rotten => 0,
+            _ => 1,
+        }
+    }
+
+    /// Seconds before uneaten food disappears, if it ever does.
+    pub fn lifetime(self) -> Option<f64> {
+        match self {
+            FoodType::Golden => Some(GOLDEN_LIFETIME),
+            FoodType::Rotten => Some(ROTTEN_LIFETIME),
+            _ => None,
+        }
+    }
+
+    /// Relative chance of spawning. Rotten food only comes from ordinary food
+    /// left too long.
+    pub fn spawn_weight(self) -> f64 {
+        match self {
+            FoodType::Normal => 70.0,
+            FoodType::Golden => 10.0,
+            FoodType::Large => 12.0,
+            FoodType::Fleeing => 8.0,
+            FoodType::Rotten => 0.0,
+        }
+    }
+
+    pub fn flees(self) -> bool {
+        self == FoodType::Fleeing
+    }
+
+    /// Rotten food scores nothing and breaks the combo.
+    pub fn is_rotten(self) -> bool {
+        self == FoodType::Rotten
+    }
+
+    /// Size of the drawn square relative to a block, before the pulse.
+    pub fn scale(self) -> f64 {
+        match self {
+            FoodType::Large => 1.0,
+            FoodType::Rotten => 0.7,
+            _ => 0.85,
+        }
+    }
+}
+
+/// How many food items the board holds at once. The target grows with the
+/// board and with the number of snakes so no one is starved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoodRules {
+    /// Food on a small board with one snake.
+    pub base: u32,
+    /// One more food for every this many cells inside the walls, whether
+    /// or not they are occupied; 0 turns it off.
+    pub cells_per_food: u32,
+    /// More food for each snake after the first.
+    pub per_extra_player: u32,
+}
+
+impl FoodRules {
+    pub fn target(&self, cells: usize, players: usize) -> usize {
+        let from_size = cells.checked_div(self.cells_per_food as usize).unwrap_or(0);
+        let from_players = players.saturating_sub(1) * self.per_extra_player as usize;
+        (self.base as usize + from_size + from_players).max(1)
+    }
+}
+
+impl Default for FoodRules {
+    /// One food on the standard board, plus one for each extra snake.
+    fn default() -> FoodRules {
+        FoodRules {
+            base: 1,
+            cells_per_food: 600,
+            per_extra_player: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Food {
+    pub x: i32,
+    pub y: i32,
+    pub food_type: FoodType,
+    /// Seconds since it appeared or last changed type.
+    pub timer: f64,
+    pub(crate) flee_timer: f64,
+}
+
+impl Food {
+    pub fn new(x: i32, y: i32, food_type: FoodType) -> Food {
+        Food {
+            x,
+            y,
+            food_type,
+            timer: 0.0,
+            flee_timer: 0.0,
+        }
+    }
+
+    /// Opacity for drawing: a slow pulse, turning into a fast blink during
+    /// the last two seconds of a limited lifetime.
+    pub fn alpha(&self) -> f64 {
+        match self.food_type.lifetime() {
+            Some(lifetime) if lifetime - self.timer < 2.0 => {
+                if (self.timer * 8.0).sin() > 0.0 {
+                    1.0
+                } else {
+                    0.3
+                }
+            }
+            _ => 0.7 + 0.3 * (self.timer * 6.0).sin().abs(),
+        }
+    }
+
+    /// Drawn size relative to a block.
+    pub fn scale(&self) -> f64 {
+        self.food_type.scale() + 0.1 * (self.timer * 4.0).sin().abs()
+    }
+}
+
+/// Picks a food type by spawn weight from a roll in `0.0..1.0`.
+pub(crate) fn choose(roll: f64) -> FoodType {
+    let total: f64 = FoodType::ALL.iter().map(|t| t.spawn_weight()).sum();
+    let mut target = roll * total;
+    for food_type in FoodType::ALL {
+        let weight = food_type.spawn_weight();
+        if target < weight {
+            return food_type;
+        }
+        target -= weight;
+    }
+    FoodType::Normal
+}
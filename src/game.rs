@@ -1,32 +1,53 @@
+use std::io;
+
 use piston_window::types::Color;
 use piston_window::*;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 
+use crate::ai;
+use crate::desync::StateHashes;
 use crate::drawing::{draw_rectangle, to_gui_coord, BLOCK_SIZE};
-use crate::snake::{Direction, Snake};
-
-const BORDER_COLOR: Color = [0.35, 0.35, 0.35, 1.0];
-const FOOD_COLOR: Color = [0.96, 0.71, 0.20, 1.0];
-const GAMEOVER_COLOR: Color = [0.91, 0.30, 0.24, 0.8];
+use crate::events::{DeathCause, EventSubscriber, GameEvent};
+use crate::food::{self, Food, FoodRules, FoodType, FLEE_PERIOD, FLEE_RADIUS, ROT_TIME};
+use crate::net::{invalid, Reader, Writer};
+use crate::player::{Controller, KeyScheme, Player, MAX_PLAYERS, PLAYER_COLORS};
+use crate::powerup::{self, InventoryRules, PowerUpType};
+use crate::snake::{Direction, Snake, MIN_LENGTH};
+
+pub(crate) const BORDER_COLOR: Color = [0.35, 0.35, 0.35, 1.0];
+pub(crate) const GAMEOVER_COLOR: Color = [0.91, 0.30, 0.24, 0.8];
 const SCORE_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
 
-const SPEED_POWERUP_COLOR: Color = [0.0, 0.8, 1.0, 1.0];
-const SHIELD_POWERUP_COLOR: Color = [0.8, 0.0, 1.0, 1.0];
-const SHIELD_EFFECT_COLOR: Color = [0.8, 0.0, 1.0, 0.3];
-
-const MOVING_PERIOD: f64 = 0.2;
-const SPEED_BOOST_PERIOD: f64 = 0.1;
-const SPEED_BOOST_DURATION: f64 = 5.0;
-const SHIELD_DURATION: f64 = 10.0;
-const POWERUP_SPAWN_CHANCE: f64 = 0.02;
-
-#[derive(Clone, Copy, PartialEq)]
-pub enum PowerUpType {
-    Speed,
-    Shield,
-}
-
+pub(crate) const SPEED_POWERUP_COLOR: Color = [0.0, 0.8, 1.0, 1.0];
+pub(crate) const SHIELD_POWERUP_COLOR: Color = [0.8, 0.0, 1.0, 1.0];
+pub(crate) const SHIELD_EFFECT_COLOR: Color = [0.8, 0.0, 1.0, 0.3];
+const POWERUP_ICON_COLOR: Color = [0.05, 0.05, 0.05, 0.9];
+const INVENTORY_SLOT_COLOR: Color = [1.0, 1.0, 1.0, 0.15];
+const INVENTORY_SLOT_SIZE: f64 = 24.0;
+pub(crate) const CURSED_WARNING_COLOR: Color = [0.9, 0.1, 0.1, 1.0];
+pub(crate) const FOG_COLOR: Color = [0.05, 0.05, 0.07, 0.97];
+
+pub(crate) const DEAD_SNAKE_ALPHA: f32 = 0.35;
+
+pub(crate) const MOVING_PERIOD: f64 = 0.2;
+pub(crate) const SPEED_BOOST_PERIOD_SCALE: f64 = 0.5;
+pub(crate) const SPEED_BOOST_DURATION: f64 = 5.0;
+pub(crate) const SHIELD_DURATION: f64 = 10.0;
+pub(crate) const POWERUP_SPAWN_CHANCE: f64 = 0.02;
+pub(crate) const POWERUP_LIFETIME: f64 = 10.0;
+pub(crate) const COMBO_WINDOW: f64 = 3.0;
+pub(crate) const FOOD_SCORE: u32 = 10;
+pub(crate) const POWERUP_SCORE: u32 = 50;
+pub(crate) const LEVEL_UP_FLASH: f64 = 1.5;
+const LINEAR_RAMP_STEP: f64 = 0.05;
+const EXPONENTIAL_RAMP_FACTOR: f64 = 0.92;
+const LEVEL_UP_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+/// Random cells tried before spawning falls back to listing the free ones.
+const SPAWN_ATTEMPTS: u32 = 100;
+
+#[derive(Clone)]
 pub struct PowerUp {
     pub x: i32,
     pub y: i32,
@@ -34,86 +55,556 @@ pub struct PowerUp {
     pub timer: f64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    /// Multiplier applied to every movement period.
+    pub fn period_scale(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 1.25,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 0.7,
+        }
+    }
+}
+
+/// What raises the speed level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RampMetric {
+    Score,
+    Length,
+}
+
+/// How the movement period falls as the level rises.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedCurve {
+    /// A fixed slice of the base period per level.
+    Linear,
+    /// A fixed fraction of the current period per level.
+    Exponential,
+}
+
+impl SpeedCurve {
+    pub fn from_name(name: &str) -> Option<SpeedCurve> {
+        match name {
+            "linear" => Some(SpeedCurve::Linear),
+            "exponential" => Some(SpeedCurve::Exponential),
+            _ => None,
+        }
+    }
+}
+
+/// Speeds the snakes up as they score or grow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedRamp {
+    pub metric: RampMetric,
+    pub curve: SpeedCurve,
+    /// Points, or segments beyond the starting length, per level.
+    pub per_level: u32,
+    /// The ramped period never drops below this, before power-ups and
+    /// difficulty are applied.
+    pub min_period: f64,
+}
+
+impl SpeedRamp {
+    pub fn new(metric: RampMetric) -> SpeedRamp {
+        SpeedRamp {
+            metric,
+            curve: SpeedCurve::Linear,
+            per_level: match metric {
+                RampMetric::Score => 100,
+                RampMetric::Length => 5,
+            },
+            min_period: 0.08,
+        }
+    }
+
+    pub fn level(&self, score: u32, length: usize) -> u32 {
+        let progress = match self.metric {
+            RampMetric::Score => score,
+            RampMetric::Length => length.saturating_sub(MIN_LENGTH) as u32,
+        };
+        progress / self.per_level.max(1)
+    }
+
+    /// `base` shortened for `level`, capped at `min_period`.
+    pub fn period(&self, base: f64, level: u32) -> f64 {
+        let period = match self.curve {
+            SpeedCurve::Linear => base * (1.0 - LINEAR_RAMP_STEP * level as f64),
+            SpeedCurve::Exponential => base * EXPONENTIAL_RAMP_FACTOR.powi(level as i32),
+        };
+        period.max(self.min_period)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    Single,
+    Versus,
+    TwoPlayer,
+    Online { players: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeadOnRule {
+    BothDie,
+    ShorterDies,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionRules {
+    pub snakes_collide: bool,
+    pub head_on: HeadOnRule,
+}
+
+impl Default for CollisionRules {
+    fn default() -> CollisionRules {
+        CollisionRules {
+            snakes_collide: true,
+            head_on: HeadOnRule::ShorterDies,
+        }
+    }
+}
+
+enum Collision {
+    Wall,
+    Body,
+    Head(usize),
+}
+
+#[derive(Clone)]
 pub struct Game {
-    snake: Snake,
+    mode: GameMode,
+    players: Vec<Player>,
+    rules: CollisionRules,
+    difficulty: Difficulty,
+    inventory: Option<InventoryRules>,
+    speed_ramp: Option<SpeedRamp>,
 
-    food_exists: bool,
-    food_x: i32,
-    food_y: i32,
+    best_of: u32,
+    round: u32,
+    round_wins: Vec<u32>,
+    match_scores: Vec<u32>,
+
+    foods: Vec<Food>,
+    food_rules: FoodRules,
 
     width: i32,
     height: i32,
 
     is_game_over: bool,
-    waiting_time: f64,
-    food_timer: f64,
-    score: u32,
 
     powerups: Vec<PowerUp>,
-    speed_boost_timer: f64,
-    shield_timer: f64,
-    is_speed_boosted: bool,
-    is_shielded: bool,
 
     screen_shake_timer: f64,
     screen_shake_intensity: f64,
 
-    combo_count: u32,
-    combo_timer: f64,
-
     game_over_animation: f64,
 
     high_score: u32,
+
+    rng: ChaCha12Rng,
+
+    events: Vec<GameEvent>,
 }
 
 impl Game {
     pub fn new(width: i32, height: i32) -> Game {
+        Game::with_mode(width, height, GameMode::Single)
+    }
+
+    pub fn with_mode(width: i32, height: i32, mode: GameMode) -> Game {
+        let mut game = Game::with_seed(width, height, rand::rng().random());
+        game.mode = mode;
+        game.new_match();
+        game
+    }
+
+    pub fn with_seed(width: i32, height: i32, seed: u64) -> Game {
         Game {
-            snake: Snake::new(2, 2),
-            waiting_time: 0.0,
-            food_exists: true,
-            food_x: 5,
-            food_y: 3,
+            mode: GameMode::Single,
+            players: Game::spawn_players(GameMode::Single, width, height),
+            rules: CollisionRules::default(),
+            difficulty: Difficulty::Normal,
+            inventory: None,
+            speed_ramp: None,
+            best_of: 1,
+            round: 1,
+            round_wins: vec![0],
+            match_scores: vec![0],
+            foods: vec![Food::new(5, 3, FoodType::Normal)],
+            food_rules: FoodRules::default(),
             width,
             height,
             is_game_over: false,
-            food_timer: 0.0,
-            score: 0,
             powerups: Vec::new(),
-            speed_boost_timer: 0.0,
-            shield_timer: 0.0,
-            is_speed_boosted: false,
-            is_shielded: false,
             screen_shake_timer: 0.0,
             screen_shake_intensity: 0.0,
-            combo_count: 0,
-            combo_timer: 0.0,
             game_over_animation: 0.0,
             high_score: 0,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            events: Vec::new(),
+        }
+    }
+
+    fn spawn_players(mode: GameMode, width: i32, height: i32) -> Vec<Player> {
+        let controllers = match mode {
+            GameMode::Single => vec![Controller::Human(KeyScheme::Arrows)],
+            GameMode::Versus => vec![Controller::Human(KeyScheme::Arrows), Controller::Ai],
+            GameMode::TwoPlayer => vec![
+                Controller::Human(KeyScheme::Arrows),
+                Controller::Human(KeyScheme::Wasd),
+            ],
+            GameMode::Online { players } => vec![Controller::Remote; players.clamp(1, MAX_PLAYERS)],
+        };
+
+        let spawns = [
+            (2, 2, Direction::Right),
+            (width - 3, height - 3, Direction::Left),
+            (width - 3, 2, Direction::Down),
+            (2, height - 3, Direction::Up),
+            (width / 2, 2, Direction::Down),
+            (width / 2, height - 3, Direction::Up),
+            (2, height / 2, Direction::Right),
+            (width - 3, height / 2, Direction::Left),
+        ];
+
+        controllers
+            .into_iter()
+            .enumerate()
+            .map(|(i, controller)| {
+                let (x, y, dir) = spawns[i];
+                let mut snake = Snake::with_direction(x, y, dir);
+                snake.set_colors(PLAYER_COLORS[i].0, PLAYER_COLORS[i].1);
+                let name = match controller {
+                    Controller::Ai => "AI".to_string(),
+                    _ => format!("P{}", i + 1),
+                };
+                Player::new(&name, snake, controller)
+            })
+            .collect()
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn set_collision_rules(&mut self, rules: CollisionRules) {
+        self.rules = rules;
+    }
+
+    pub fn inventory_rules(&self) -> Option<InventoryRules> {
+        self.inventory
+    }
+
+    /// Turns the power-up inventory on or off. Without one, every power-up
+    /// takes effect on pickup.
+    pub fn set_inventory_rules(&mut self, rules: Option<InventoryRules>) {
+        self.inventory = rules;
+    }
+
+    pub fn speed_ramp(&self) -> Option<SpeedRamp> {
+        self.speed_ramp
+    }
+
+    /// Turns progressive speed on or off for every snake.
+    pub fn set_speed_ramp(&mut self, ramp: Option<SpeedRamp>) {
+        self.speed_ramp = ramp;
+        for player in self.players.iter_mut() {
+            player.set_speed_ramp(ramp);
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        for player in self.players.iter_mut() {
+            player.set_difficulty(difficulty);
+        }
+    }
+
+    pub fn high_score(&self) -> u32 {
+        self.high_score
+    }
+
+    /// Sets the best score shown in the HUD, e.g. from a stored leaderboard.
+    pub fn set_high_score(&mut self, score: u32) {
+        self.high_score = score;
+    }
+
+    pub fn set_best_of(&mut self, rounds: u32) {
+        self.best_of = rounds.max(1);
+        self.new_match();
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn round_wins(&self) -> &[u32] {
+        &self.round_wins
+    }
+
+    pub fn is_match_over(&self) -> bool {
+        let rounds_to_win = self.best_of / 2 + 1;
+        self.round_wins.iter().any(|wins| *wins >= rounds_to_win)
+            || (self.is_game_over && self.round >= self.best_of)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn score(&self) -> u32 {
+        self.players[0].score()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.is_game_over
+    }
+
+    pub fn snake(&self) -> &Snake {
+        self.players[0].snake()
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn foods(&self) -> &[Food] {
+        &self.foods
+    }
+
+    /// The closest food worth eating to (x, y), skipping rotten food.
+    pub fn nearest_food(&self, x: i32, y: i32) -> Option<&Food> {
+        self.foods
+            .iter()
+            .filter(|food| !food.food_type.is_rotten())
+            .min_by_key(|food| (food.x - x).abs() + (food.y - y).abs())
+    }
+
+    pub fn food_rules(&self) -> FoodRules {
+        self.food_rules
+    }
+
+    pub fn set_food_rules(&mut self, rules: FoodRules) {
+        self.food_rules = rules;
+    }
+
+    /// How many food items the board is kept topped up to.
+    pub fn food_target(&self) -> usize {
+        let cells = ((self.width - 2) * (self.height - 2)) as usize;
+        self.food_rules.target(cells, self.players.len())
+    }
+
+    pub fn powerups(&self) -> &[PowerUp] {
+        &self.powerups
+    }
+
+    /// Cells hidden from the first player by fog, outside the visible
+    /// radius around their head.
+    pub(crate) fn fogged_cells(&self) -> Vec<(i32, i32)> {
+        let player = &self.players[0];
+        let Some(radius) = player.fog_radius() else {
+            return Vec::new();
+        };
+        let (head_x, head_y) = player.snake().head_position();
+        let mut cells = Vec::new();
+        for x in 1..self.width - 1 {
+            for y in 1..self.height - 1 {
+                let (dx, dy) = (x - head_x, y - head_y);
+                if dx * dx + dy * dy > radius * radius {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    pub(crate) fn player_mut(&mut self, index: usize) -> &mut Player {
+        &mut self.players[index]
+    }
+
+    /// Moves each food within `radius` cells of the player's head one cell
+    /// closer, where the way is clear.
+    pub(crate) fn pull_food(&mut self, index: usize, radius: i32) {
+        let (head_x, head_y) = self.players[index].snake().head_position();
+        for food_index in 0..self.foods.len() {
+            let food = &self.foods[food_index];
+            let (dx, dy) = (head_x - food.x, head_y - food.y);
+            let distance = dx.abs() + dy.abs();
+            if distance > radius || distance <= 1 {
+                continue;
+            }
+
+            let (x, y) = if dx.abs() >= dy.abs() {
+                (food.x + dx.signum(), food.y)
+            } else {
+                (food.x, food.y + dy.signum())
+            };
+            self.move_food(food_index, x, y);
+        }
+    }
+
+    /// Steps each fleeing food one cell away from the nearest head within
+    /// `FLEE_RADIUS`, every `FLEE_PERIOD`.
+    fn flee_food(&mut self, delta_time: f64) {
+        for food_index in 0..self.foods.len() {
+            let food = &mut self.foods[food_index];
+            if !food.food_type.flees() {
+                continue;
+            }
+            food.flee_timer += delta_time;
+            if food.flee_timer < FLEE_PERIOD {
+                continue;
+            }
+            food.flee_timer = 0.0;
+
+            let (food_x, food_y) = (food.x, food.y);
+            let nearest = self
+                .players
+                .iter()
+                .filter(|p| p.is_alive())
+                .map(|p| p.snake().head_position())
+                .min_by_key(|(x, y)| (x - food_x).abs() + (y - food_y).abs());
+            let Some((head_x, head_y)) = nearest else {
+                continue;
+            };
+            let (dx, dy) = (food_x - head_x, food_y - head_y);
+            if dx.abs() + dy.abs() > FLEE_RADIUS {
+                continue;
+            }
+
+            let steps = if dx.abs() >= dy.abs() {
+                [(dx.signum(), 0), (0, dy.signum())]
+            } else {
+                [(0, dy.signum()), (dx.signum(), 0)]
+            };
+            for (step_x, step_y) in steps {
+                if (step_x, step_y) != (0, 0)
+                    && self.move_food(food_index, food_x + step_x, food_y + step_y)
+                {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Moves a food to (x, y) if nothing is there.
+    fn move_food(&mut self, food_index: usize, x: i32, y: i32) -> bool {
+        if !self.is_cell_free(x, y)
+            || self.is_food_at(x, y)
+            || self.powerups.iter().any(|p| p.x == x && p.y == y)
+        {
+            return false;
         }
+        let food = &mut self.foods[food_index];
+        food.x = x;
+        food.y = y;
+        true
+    }
+
+    fn is_food_at(&self, x: i32, y: i32) -> bool {
+        self.foods.iter().any(|food| food.x == x && food.y == y)
+    }
+
+    pub fn is_wall(&self, x: i32, y: i32) -> bool {
+        x <= 0 || y <= 0 || x >= self.width - 1 || y >= self.height - 1
+    }
+
+    pub fn is_cell_free(&self, x: i32, y: i32) -> bool {
+        !self.is_wall(x, y) && !self.is_occupied(x, y)
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self.new_match();
     }
 
     pub fn key_pressed(&mut self, key: Key) {
         if self.is_game_over {
             if key == Key::Space {
-                self.restart();
+                self.next_round();
             }
             return;
         }
 
-        let dir = match key {
-            Key::Up => Some(Direction::Up),
-            Key::Down => Some(Direction::Down),
-            Key::Left => Some(Direction::Left),
-            Key::Right => Some(Direction::Right),
-            _ => return,
-        };
+        for index in 0..self.players.len() {
+            let player = &self.players[index];
+            if let Controller::Human(keys) = player.controller() {
+                if let Some(slot) = keys.inventory_slot(key) {
+                    self.activate_powerup(index, slot);
+                    return;
+                }
+            }
+
+            let dir = match player.controller() {
+                Controller::Human(keys) => keys.direction(key),
+                Controller::Ai | Controller::Remote => None,
+            };
+
+            let Some(dir) = dir else {
+                continue;
+            };
+
+            if player.is_alive() {
+                self.players[index].queue_direction(dir);
+            }
+            return;
+        }
+    }
 
-        if dir.unwrap() == self.snake.head_direction().opposite() {
+    /// Starts the power-up held in `slot` of the player's inventory.
+    pub fn activate_powerup(&mut self, index: usize, slot: usize) {
+        if self.is_game_over || !self.players[index].is_alive() {
             return;
         }
+        let Some(powerup_type) = self.players[index].take_stored(slot) else {
+            return;
+        };
 
-        self.update_snake(dir);
+        let score = self.players[index].score();
+        self.players[index].apply_effect(powerup_type);
+        powerup_type.effect().on_pickup(self, index);
+        self.emit(GameEvent::PowerUpActivated {
+            player: index,
+            powerup_type,
+        });
+        if self.players[index].score() != score {
+            self.emit(GameEvent::ScoreChanged {
+                player: index,
+                score: self.players[index].score(),
+            });
+        }
     }
 
     pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
@@ -133,33 +624,34 @@ impl Game {
         let shake_transform = con.transform.trans(shake_x, shake_y);
         let shake_con = con.trans(shake_x, shake_y);
 
-        if self.is_shielded {
-            let pulse = 0.7 + 0.3 * (self.shield_timer * 8.0).sin().abs();
-            let mut shield_color = SHIELD_EFFECT_COLOR;
-            shield_color[3] = pulse as f32 * 0.3;
-
-            draw_rectangle(
-                shield_color,
-                1,
-                1,
-                self.width - 2,
-                self.height - 2,
-                &shake_con,
-                g,
-            );
+        for effect in self.players[0].effects() {
+            if let Some(tint) = effect.powerup_type.effect().board_tint(effect.remaining) {
+                draw_rectangle(tint, 1, 1, self.width - 2, self.height - 2, &shake_con, g);
+            }
+        }
+        if let Some(flash) = self.players[0].level_up_flash() {
+            draw_rectangle(flash, 1, 1, self.width - 2, self.height - 2, &shake_con, g);
         }
 
-        self.snake.draw(&shake_con, g);
+        for player in &self.players {
+            if player.is_alive() {
+                player
+                    .snake()
+                    .draw_with_alpha(player.snake_alpha(), &shake_con, g);
+            } else {
+                player
+                    .snake()
+                    .draw_with_alpha(DEAD_SNAKE_ALPHA, &shake_con, g);
+            }
+        }
 
-        if self.food_exists {
-            let alpha = 0.7 + 0.3 * (self.food_timer * 6.0).sin().abs();
-            let scale = 1.0 + 0.1 * (self.food_timer * 4.0).sin().abs();
-            let mut food_color = FOOD_COLOR;
-            food_color[3] = alpha as f32;
+        for food in &self.foods {
+            let mut food_color = food.food_type.color();
+            food_color[3] = food.alpha() as f32;
 
-            let gui_x = to_gui_coord(self.food_x);
-            let gui_y = to_gui_coord(self.food_y);
-            let size = BLOCK_SIZE * scale;
+            let gui_x = to_gui_coord(food.x);
+            let gui_y = to_gui_coord(food.y);
+            let size = BLOCK_SIZE * food.scale();
             let offset = (BLOCK_SIZE - size) / 2.0;
 
             rectangle(
@@ -171,10 +663,8 @@ impl Game {
         }
 
         for powerup in &self.powerups {
-            let color = match powerup.powerup_type {
-                PowerUpType::Speed => SPEED_POWERUP_COLOR,
-                PowerUpType::Shield => SHIELD_POWERUP_COLOR,
-            };
+            let effect = powerup.powerup_type.effect();
+            let color = effect.color();
 
             let pulse = 0.5 + 0.5 * (powerup.timer * 5.0).sin().abs();
             let mut powerup_color = color;
@@ -182,6 +672,16 @@ impl Game {
 
             let gui_x = to_gui_coord(powerup.x);
             let gui_y = to_gui_coord(powerup.y);
+            if effect.is_cursed() {
+                let mut warning_color = CURSED_WARNING_COLOR;
+                warning_color[3] = (1.0 - pulse * 0.5) as f32;
+                rectangle(
+                    warning_color,
+                    [gui_x, gui_y, BLOCK_SIZE, BLOCK_SIZE],
+                    shake_transform,
+                    g,
+                );
+            }
             let size = BLOCK_SIZE * 0.8;
             let offset = (BLOCK_SIZE - size) / 2.0;
 
@@ -191,6 +691,19 @@ impl Game {
                 shake_transform,
                 g,
             );
+            text::Text::new_color(POWERUP_ICON_COLOR, 14)
+                .draw(
+                    effect.icon(),
+                    glyphs,
+                    &con.draw_state,
+                    shake_transform.trans(gui_x + 8.0, gui_y + 18.0),
+                    g,
+                )
+                .unwrap();
+        }
+
+        for (x, y) in self.fogged_cells() {
+            draw_rectangle(FOG_COLOR, x, y, 1, 1, &shake_con, g);
         }
 
         for i in 0..3 {
@@ -218,77 +731,13 @@ impl Game {
             );
         }
 
-        let score_text = format!("Score: {}", self.score);
-        let font_size = 20;
-        text::Text::new_color(SCORE_COLOR, font_size)
-            .draw(
-                &score_text,
-                glyphs,
-                &con.draw_state,
-                con.transform.trans(10.0, 30.0),
-                g,
-            )
-            .unwrap();
-
-        if self.high_score > 0 {
-            let high_score_text = format!("Best: {}", self.high_score);
-            text::Text::new_color([0.8, 0.8, 0.8, 1.0], 16)
-                .draw(
-                    &high_score_text,
-                    glyphs,
-                    &con.draw_state,
-                    con.transform.trans(10.0, 55.0),
-                    g,
-                )
-                .unwrap();
-        }
-
-        let mut status_y = 80.0;
-        if self.is_speed_boosted {
-            let speed_text = format!("SPEED BOOST: {:.1}s", self.speed_boost_timer);
-            text::Text::new_color(SPEED_POWERUP_COLOR, 14)
-                .draw(
-                    &speed_text,
-                    glyphs,
-                    &con.draw_state,
-                    con.transform.trans(10.0, status_y),
-                    g,
-                )
-                .unwrap();
-            status_y += 20.0;
-        }
-
-        if self.is_shielded {
-            let shield_text = format!("SHIELD: {:.1}s", self.shield_timer);
-            text::Text::new_color(SHIELD_POWERUP_COLOR, 14)
-                .draw(
-                    &shield_text,
-                    glyphs,
-                    &con.draw_state,
-                    con.transform.trans(10.0, status_y),
-                    g,
-                )
-                .unwrap();
-            status_y += 20.0;
-        }
-
-        if self.combo_count > 1 {
-            let combo_text = format!("COMBO x{}", self.combo_count);
-            let combo_color = [
-                1.0,
-                1.0 - (self.combo_count as f32 * 0.1).min(0.5),
-                0.0,
-                1.0,
-            ];
-            text::Text::new_color(combo_color, 16)
-                .draw(
-                    &combo_text,
-                    glyphs,
-                    &con.draw_state,
-                    con.transform.trans(10.0, status_y),
-                    g,
-                )
-                .unwrap();
+        for (i, player) in self.players.iter().enumerate() {
+            let hud_x = if i == 0 {
+                10.0
+            } else {
+                to_gui_coord(self.width) - 150.0
+            };
+            self.draw_player_hud(i, player, hud_x, con, g, glyphs);
         }
 
         if self.is_game_over {
@@ -297,6 +746,11 @@ impl Game {
             let center_x = to_gui_coord(self.width) / 2.0;
             let center_y = to_gui_coord(self.height) / 2.0;
 
+            if self.players.len() > 1 {
+                self.draw_match_results(shake_transform, center_x, center_y, con, g, glyphs);
+                return;
+            }
+
             text::Text::new_color([1.0, 1.0, 1.0, 1.0], 36)
                 .draw(
                     "GAME OVER",
@@ -307,7 +761,7 @@ impl Game {
                 )
                 .unwrap();
 
-            let score_text = format!("Score: {}", self.score);
+            let score_text = format!("Score: {}", self.score());
             text::Text::new_color([1.0, 1.0, 1.0, 1.0], 28)
                 .draw(
                     &score_text,
@@ -330,7 +784,7 @@ impl Game {
                     )
                     .unwrap();
 
-                if self.score == self.high_score {
+                if self.score() == self.high_score {
                     text::Text::new_color([1.0, 0.84, 0.0, 1.0], 20)
                         .draw(
                             "NEW RECORD!",
@@ -356,86 +810,671 @@ impl Game {
         }
     }
 
-    pub fn update(&mut self, delta_time: f64) {
-        self.waiting_time += delta_time;
-        self.food_timer += delta_time;
-        self.snake.update_tongue(delta_time);
-
-        if self.speed_boost_timer > 0.0 {
-            self.speed_boost_timer -= delta_time;
-            if self.speed_boost_timer <= 0.0 {
-                self.is_speed_boosted = false;
-            }
+    fn draw_match_results(
+        &self,
+        transform: math::Matrix2d,
+        center_x: f64,
+        center_y: f64,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        let match_over = self.is_match_over();
+        let title = if match_over {
+            "MATCH OVER".to_string()
+        } else {
+            format!("ROUND {} OVER", self.round)
+        };
+        text::Text::new_color([1.0, 1.0, 1.0, 1.0], 36)
+            .draw(
+                &title,
+                glyphs,
+                &con.draw_state,
+                transform.trans(center_x - 120.0, center_y - 80.0),
+                g,
+            )
+            .unwrap();
+
+        let winner = if match_over {
+            self.match_winner()
+        } else {
+            self.winner()
+        };
+        let result_text = match winner {
+            Some(winner) if match_over => format!("{} WINS THE MATCH", winner.name()),
+            Some(winner) => format!("{} WINS THE ROUND", winner.name()),
+            None => "DRAW".to_string(),
+        };
+        text::Text::new_color([1.0, 0.84, 0.0, 1.0], 24)
+            .draw(
+                &result_text,
+                glyphs,
+                &con.draw_state,
+                transform.trans(center_x - 110.0, center_y - 40.0),
+                g,
+            )
+            .unwrap();
+
+        let mut row_y = center_y;
+        for (i, player) in self.players.iter().enumerate() {
+            let row_text = format!(
+                "{}   rounds: {}   round score: {}   total: {}",
+                player.name(),
+                self.round_wins[i],
+                player.score(),
+                self.match_scores[i]
+            );
+            text::Text::new_color([1.0, 1.0, 1.0, 1.0], 18)
+                .draw(
+                    &row_text,
+                    glyphs,
+                    &con.draw_state,
+                    transform.trans(center_x - 180.0, row_y),
+                    g,
+                )
+                .unwrap();
+            row_y += 28.0;
         }
 
-        if self.shield_timer > 0.0 {
-            self.shield_timer -= delta_time;
-            if self.shield_timer <= 0.0 {
-                self.is_shielded = false;
-            }
+        let prompt = if match_over {
+            "Press SPACE for a new match"
+        } else {
+            "Press SPACE for the next round"
+        };
+        let blink = (self.game_over_animation * 2.0).sin().abs() as f32;
+        text::Text::new_color([1.0, 1.0, 1.0, blink], 20)
+            .draw(
+                prompt,
+                glyphs,
+                &con.draw_state,
+                transform.trans(center_x - 130.0, row_y + 30.0),
+                g,
+            )
+            .unwrap();
+    }
+
+    fn draw_player_hud(
+        &self,
+        index: usize,
+        player: &Player,
+        x: f64,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        let score_text = if self.players.len() > 1 {
+            format!("{}: {}", player.name(), player.score())
+        } else {
+            format!("Score: {}", player.score())
+        };
+        let font_size = 20;
+        text::Text::new_color(SCORE_COLOR, font_size)
+            .draw(
+                &score_text,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(x, 30.0),
+                g,
+            )
+            .unwrap();
+
+        if self.best_of > 1 {
+            let wins_text = format!(
+                "Rounds: {}/{}",
+                self.round_wins[index],
+                self.best_of / 2 + 1
+            );
+            text::Text::new_color([0.8, 0.8, 0.8, 1.0], 16)
+                .draw(
+                    &wins_text,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(x, 55.0),
+                    g,
+                )
+                .unwrap();
+        } else if index == 0 && self.high_score > 0 {
+            let high_score_text = format!("Best: {}", self.high_score);
+            text::Text::new_color([0.8, 0.8, 0.8, 1.0], 16)
+                .draw(
+                    &high_score_text,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(x, 55.0),
+                    g,
+                )
+                .unwrap();
         }
 
-        if self.screen_shake_timer > 0.0 {
-            self.screen_shake_timer -= delta_time;
-            self.screen_shake_intensity *= 0.9;
+        let mut status_y = 80.0;
+        for active in player.effects() {
+            let effect = active.powerup_type.effect();
+            let status_text = effect.status(active);
+            text::Text::new_color(effect.color(), 14)
+                .draw(
+                    &status_text,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(x, status_y),
+                    g,
+                )
+                .unwrap();
+            status_y += 20.0;
         }
 
-        if self.combo_timer > 0.0 {
-            self.combo_timer -= delta_time;
-            if self.combo_timer <= 0.0 {
-                self.combo_count = 0;
+        if self.speed_ramp.is_some() {
+            let (level_text, color) = if player.level_up_timer() > 0.0 {
+                let mut color = LEVEL_UP_COLOR;
+                color[3] = (0.5 + 0.5 * (player.level_up_timer() * 10.0).sin().abs()) as f32;
+                (format!("LEVEL UP! {}", player.speed_level() + 1), color)
+            } else {
+                (
+                    format!("Level {}", player.speed_level() + 1),
+                    [0.8, 0.8, 0.8, 1.0],
+                )
+            };
+            text::Text::new_color(color, 14)
+                .draw(
+                    &level_text,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(x, status_y),
+                    g,
+                )
+                .unwrap();
+            status_y += 20.0;
+        }
+
+        if let Some(rules) = self.inventory {
+            self.draw_inventory(player, rules, x, status_y, con, g, glyphs);
+            status_y += 32.0;
+        }
+
+        if player.combo_count() > 1 {
+            let combo_text = format!("COMBO x{}", player.combo_count());
+            let combo_color = [
+                1.0,
+                1.0 - (player.combo_count() as f32 * 0.1).min(0.5),
+                0.0,
+                1.0,
+            ];
+            text::Text::new_color(combo_color, 16)
+                .draw(
+                    &combo_text,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(x, status_y),
+                    g,
+                )
+                .unwrap();
+        }
+    }
+
+    /// One square per inventory slot, filled with the stored power-up and
+    /// numbered with its activation key.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_inventory(
+        &self,
+        player: &Player,
+        rules: InventoryRules,
+        x: f64,
+        y: f64,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        let keys = match player.controller() {
+            Controller::Human(keys) => Some(keys),
+            Controller::Ai | Controller::Remote => None,
+        };
+        for slot in 0..rules.capacity {
+            let slot_x = x + slot as f64 * (INVENTORY_SLOT_SIZE + 6.0);
+            let slot_y = y - 14.0;
+            rectangle(
+                INVENTORY_SLOT_COLOR,
+                [slot_x, slot_y, INVENTORY_SLOT_SIZE, INVENTORY_SLOT_SIZE],
+                con.transform,
+                g,
+            );
+
+            if let Some(powerup_type) = player.inventory().get(slot) {
+                let effect = powerup_type.effect();
+                rectangle(
+                    effect.color(),
+                    [
+                        slot_x + 2.0,
+                        slot_y + 2.0,
+                        INVENTORY_SLOT_SIZE - 4.0,
+                        INVENTORY_SLOT_SIZE - 4.0,
+                    ],
+                    con.transform,
+                    g,
+                );
+                text::Text::new_color(POWERUP_ICON_COLOR, 14)
+                    .draw(
+                        effect.icon(),
+                        glyphs,
+                        &con.draw_state,
+                        con.transform.trans(slot_x + 8.0, slot_y + 18.0),
+                        g,
+                    )
+                    .unwrap();
+            }
+
+            if let Some(label) = keys.and_then(|keys| keys.inventory_key_label(slot)) {
+                text::Text::new_color(SCORE_COLOR, 10)
+                    .draw(
+                        label,
+                        glyphs,
+                        &con.draw_state,
+                        con.transform
+                            .trans(slot_x + 1.0, slot_y + INVENTORY_SLOT_SIZE + 10.0),
+                        g,
+                    )
+                    .unwrap();
             }
         }
+    }
+
+    /// Takes the events raised since the last call, including those raised
+    /// by key presses and restarts between updates. Whatever drives the game
+    /// should take them after every update so they do not pile up.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Delivers the events raised since the last call to each subscriber, in
+    /// the order they happened.
+    pub fn publish_events(&mut self, subscribers: &mut [&mut dyn EventSubscriber]) {
+        for event in self.take_events() {
+            for subscriber in subscribers.iter_mut() {
+                subscriber.on_event(&event, self);
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        self.update_timers(delta_time);
+
+        for index in 0..self.players.len() {
+            let combo = self.players[index].combo_count();
+            let expired = self.players[index].update(delta_time);
+            self.after_player_timers(index, delta_time, combo, expired);
+        }
 
         if self.is_game_over {
             self.game_over_animation += delta_time;
             return;
         }
 
-        self.powerups.retain_mut(|powerup| {
-            powerup.timer += delta_time;
-            powerup.timer < 10.0
-        });
+        self.update_pickups(delta_time);
 
-        if rand::rng().random::<f64>() < POWERUP_SPAWN_CHANCE {
-            self.spawn_powerup();
+        for index in 0..self.players.len() {
+            let player = &self.players[index];
+            if self.is_game_over || !player.is_alive() || !player.is_ready_to_move() {
+                continue;
+            }
+
+            let dir = match player.controller() {
+                Controller::Ai => ai::choose_direction(self, index),
+                Controller::Human(_) | Controller::Remote => {
+                    self.players[index].take_queued_direction()
+                }
+            };
+            self.update_snake(index, dir);
         }
+    }
 
-        if !self.food_exists {
-            self.add_food();
+    pub fn step(&mut self, dir: Option<Direction>) {
+        if self.is_game_over {
+            return;
         }
 
-        let movement_period = if self.is_speed_boosted {
-            SPEED_BOOST_PERIOD
+        let period = self.players[0].movement_period();
+
+        self.update_timers(period);
+        self.update_pickups(period);
+
+        for index in 0..self.players.len() {
+            let player = &self.players[index];
+            if self.is_game_over || !player.is_alive() {
+                continue;
+            }
+
+            let dir = match player.controller() {
+                Controller::Ai => ai::choose_direction(self, index),
+                Controller::Human(_) if index == 0 => dir.map(|d| player.steer(d)),
+                Controller::Human(_) | Controller::Remote => {
+                    self.players[index].take_queued_direction()
+                }
+            };
+            let player = &self.players[index];
+            let dir = dir.filter(|d| *d != player.snake().head_direction().opposite());
+
+            let player_period = player.movement_period();
+            let combo = player.combo_count();
+            let expired = self.players[index].update_timers(player_period);
+            self.after_player_timers(index, player_period, combo, expired);
+            self.update_snake(index, dir);
+        }
+    }
+
+    pub fn queue_direction(&mut self, index: usize, dir: Direction) {
+        if let Some(player) = self.players.get_mut(index) {
+            player.queue_direction(dir);
+        }
+    }
+
+    pub fn set_player_name(&mut self, index: usize, name: &str) {
+        if let Some(player) = self.players.get_mut(index) {
+            player.set_name(name);
+        }
+    }
+
+    pub fn next_round(&mut self) {
+        if self.is_match_over() {
+            self.new_match();
         } else {
-            MOVING_PERIOD
+            self.round += 1;
+            self.restart();
+        }
+    }
+
+    /// Hashes every field of the simulation state, including the RNG, so
+    /// peers running the same inputs can detect and locate a desync.
+    pub fn state_hashes(&self) -> StateHashes {
+        let mut hashes = StateHashes::new();
+        hashes.field("mode", |w| w.game_mode(self.mode));
+        hashes.field("rules", |w| w.collision_rules(self.rules));
+        hashes.field("difficulty", |w| w.difficulty(self.difficulty));
+        hashes.field("inventory", |w| w.inventory_rules(self.inventory));
+        hashes.field("speed_ramp", |w| w.speed_ramp(self.speed_ramp));
+        hashes.field("round", |w| {
+            w.u32(self.best_of);
+            w.u32(self.round);
+            w.list(&self.round_wins, |w, wins| w.u32(*wins));
+            w.list(&self.match_scores, |w, score| w.u32(*score));
+        });
+        hashes.field("food", |w| {
+            w.food_rules(self.food_rules);
+            w.list(&self.foods, |w, food| w.food(food));
+        });
+        hashes.field("size", |w| w.position((self.width, self.height)));
+        hashes.field("game_over", |w| {
+            w.bool(self.is_game_over);
+            w.f64(self.game_over_animation);
+        });
+        hashes.field("powerups", |w| {
+            w.list(&self.powerups, |w, p| {
+                w.position((p.x, p.y));
+                w.powerup_type(p.powerup_type);
+                w.f64(p.timer);
+            })
+        });
+        hashes.field("screen_shake", |w| {
+            w.f64(self.screen_shake_timer);
+            w.f64(self.screen_shake_intensity);
+        });
+        hashes.field("high_score", |w| w.u32(self.high_score));
+        hashes.field("rng", |w| w.u64(self.rng.clone().random()));
+        for (index, player) in self.players.iter().enumerate() {
+            player.hash_state(&format!("players[{}]", index), &mut hashes);
+        }
+        hashes
+    }
+
+    /// Writes the complete simulation state, including the RNG, so a game
+    /// restored with `read_state` continues exactly where it left off.
+    pub(crate) fn write_state(&self, w: &mut Writer) {
+        w.game_mode(self.mode);
+        w.collision_rules(self.rules);
+        w.difficulty(self.difficulty);
+        w.inventory_rules(self.inventory);
+        w.speed_ramp(self.speed_ramp);
+        w.u32(self.best_of);
+        w.u32(self.round);
+        w.list(&self.round_wins, |w, wins| w.u32(*wins));
+        w.list(&self.match_scores, |w, score| w.u32(*score));
+        w.food_rules(self.food_rules);
+        w.list(&self.foods, |w, food| w.food(food));
+        w.position((self.width, self.height));
+        w.bool(self.is_game_over);
+        w.f64(self.game_over_animation);
+        w.list(&self.powerups, |w, p| {
+            w.position((p.x, p.y));
+            w.powerup_type(p.powerup_type);
+            w.f64(p.timer);
+        });
+        w.f64(self.screen_shake_timer);
+        w.f64(self.screen_shake_intensity);
+        w.u32(self.high_score);
+        w.rng(&self.rng);
+        w.list(&self.players, |w, player| player.write_state(w));
+    }
+
+    pub(crate) fn read_state(r: &mut Reader) -> io::Result<Game> {
+        let mode = r.game_mode()?;
+        let rules = r.collision_rules()?;
+        let difficulty = r.difficulty()?;
+        let inventory = r.inventory_rules()?;
+        let speed_ramp = r.speed_ramp()?;
+        let best_of = r.u32()?;
+        let round = r.u32()?;
+        let round_wins = r.list(|r| r.u32())?;
+        let match_scores = r.list(|r| r.u32())?;
+        let food_rules = r.food_rules()?;
+        let foods = r.list(|r| r.food())?;
+        let (width, height) = r.position()?;
+        let is_game_over = r.bool()?;
+        let game_over_animation = r.f64()?;
+        let powerups = r.list(|r| {
+            let (x, y) = r.position()?;
+            Ok(PowerUp {
+                x,
+                y,
+                powerup_type: r.powerup_type()?,
+                timer: r.f64()?,
+            })
+        })?;
+        let screen_shake_timer = r.f64()?;
+        let screen_shake_intensity = r.f64()?;
+        let high_score = r.u32()?;
+        let rng = r.rng()?;
+        let mut players = r.list(Player::read_state)?;
+        for player in players.iter_mut() {
+            player.set_difficulty(difficulty);
+            player.set_speed_ramp(speed_ramp);
+        }
+
+        if players.is_empty() || players.len() > MAX_PLAYERS {
+            return Err(invalid(format!("invalid player count {}", players.len())));
+        }
+        if round_wins.len() != players.len() || match_scores.len() != players.len() {
+            return Err(invalid(
+                "round results do not match the players".to_string(),
+            ));
+        }
+        if width < 3 || height < 3 {
+            return Err(invalid(format!("invalid board size {}x{}", width, height)));
+        }
+
+        Ok(Game {
+            mode,
+            players,
+            rules,
+            difficulty,
+            inventory,
+            speed_ramp,
+            best_of,
+            round,
+            round_wins,
+            match_scores,
+            foods,
+            food_rules,
+            width,
+            height,
+            is_game_over,
+            powerups,
+            screen_shake_timer,
+            screen_shake_intensity,
+            game_over_animation,
+            high_score,
+            rng,
+            events: Vec::new(),
+        })
+    }
+
+    pub fn winner(&self) -> Option<&Player> {
+        self.winner_index().map(|index| &self.players[index])
+    }
+
+    fn winner_index(&self) -> Option<usize> {
+        let mut alive = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_alive())
+            .map(|(index, _)| index);
+        match (alive.next(), alive.next()) {
+            (Some(index), None) => Some(index),
+            _ => None,
+        }
+    }
+
+    fn match_winner(&self) -> Option<&Player> {
+        let best = *self.round_wins.iter().max()?;
+        let mut leaders = self
+            .round_wins
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((index, _)), None) => Some(&self.players[index]),
+            _ => None,
+        }
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        let shake = match event {
+            GameEvent::FoodEaten { player, .. } => Some((player, 0.1, 2.0)),
+            GameEvent::PowerUpCollected { player, .. } => Some((player, 0.2, 4.0)),
+            _ => None,
         };
+        if let Some((player, timer, intensity)) = shake {
+            if matches!(self.players[player].controller(), Controller::Human(_)) {
+                self.screen_shake_timer = timer;
+                self.screen_shake_intensity = intensity;
+            }
+        }
+        self.events.push(event);
+    }
 
-        if self.waiting_time > movement_period {
-            self.update_snake(None);
-            self.waiting_time = 0.0;
+    /// Runs the power-up hooks for a player whose timers just advanced, and
+    /// reports what changed.
+    fn after_player_timers(
+        &mut self,
+        index: usize,
+        delta_time: f64,
+        combo_before: u32,
+        expired: Vec<PowerUpType>,
+    ) {
+        let active: Vec<PowerUpType> = self.players[index]
+            .effects()
+            .iter()
+            .map(|e| e.powerup_type)
+            .collect();
+        for powerup_type in active {
+            powerup_type.effect().on_tick(self, index, delta_time);
+        }
+        for powerup_type in expired {
+            powerup_type.effect().on_expire(self, index);
+            self.emit(GameEvent::PowerUpExpired {
+                player: index,
+                powerup_type,
+            });
+        }
+
+        let combo = self.players[index].combo_count();
+        if combo != combo_before {
+            self.emit(GameEvent::ComboChanged {
+                player: index,
+                combo,
+            });
         }
     }
 
-    fn spawn_powerup(&mut self) {
-        let mut rng = rand::rng();
+    fn update_timers(&mut self, delta_time: f64) {
+        if self.screen_shake_timer > 0.0 {
+            self.screen_shake_timer -= delta_time;
+            self.screen_shake_intensity *= 0.9;
+        }
+    }
 
-        let mut new_x = rng.random_range(1..(self.width - 1));
-        let mut new_y = rng.random_range(1..(self.height - 1));
+    fn update_pickups(&mut self, delta_time: f64) {
+        self.powerups.retain_mut(|powerup| {
+            powerup.timer += delta_time;
+            powerup.timer < POWERUP_LIFETIME
+        });
 
-        while self.snake.is_overlap_except_tail(new_x, new_y)
-            || (self.food_exists && new_x == self.food_x && new_y == self.food_y)
-        {
-            new_x = rng.random_range(1..(self.width - 1));
-            new_y = rng.random_range(1..(self.height - 1));
+        if self.rng.random::<f64>() < POWERUP_SPAWN_CHANCE {
+            self.spawn_powerup();
         }
 
-        let powerup_type = if rng.random::<f64>() < 0.5 {
-            PowerUpType::Speed
-        } else {
-            PowerUpType::Shield
+        self.foods.retain_mut(|food| {
+            food.timer += delta_time;
+            if food.food_type == FoodType::Normal && food.timer >= ROT_TIME {
+                food.food_type = FoodType::Rotten;
+                food.timer = 0.0;
+            }
+            food.food_type
+                .lifetime()
+                .is_none_or(|lifetime| food.timer < lifetime)
+        });
+        self.flee_food(delta_time);
+
+        while self.foods.len() < self.food_target() {
+            if !self.add_food() {
+                break;
+            }
+        }
+    }
+
+    fn is_occupied(&self, x: i32, y: i32) -> bool {
+        self.players
+            .iter()
+            .any(|p| p.is_alive() && p.snake().is_overlap_except_tail(x, y))
+    }
+
+    /// Picks a random cell inside the walls that `taken` does not reject,
+    /// or `None` when every such cell is taken.
+    fn random_free_cell(&mut self, taken: impl Fn(&Game, i32, i32) -> bool) -> Option<(i32, i32)> {
+        for _ in 0..SPAWN_ATTEMPTS {
+            let x = self.rng.random_range(1..(self.width - 1));
+            let y = self.rng.random_range(1..(self.height - 1));
+            if !taken(self, x, y) {
+                return Some((x, y));
+            }
+        }
+
+        let free: Vec<(i32, i32)> = (1..(self.height - 1))
+            .flat_map(|y| (1..(self.width - 1)).map(move |x| (x, y)))
+            .filter(|&(x, y)| !taken(self, x, y))
+            .collect();
+        if free.is_empty() {
+            return None;
+        }
+        Some(free[self.rng.random_range(0..free.len())])
+    }
+
+    fn spawn_powerup(&mut self) {
+        let Some((new_x, new_y)) =
+            self.random_free_cell(|game, x, y| game.is_occupied(x, y) || game.is_food_at(x, y))
+        else {
+            return;
         };
 
+        let powerup_type = powerup::choose(self.rng.random::<f64>());
+
         self.powerups.push(PowerUp {
             x: new_x,
             y: new_y,
@@ -444,26 +1483,28 @@ impl Game {
         });
     }
 
-    fn check_eating(&mut self) {
-        let (head_x, head_y) = self.snake.head_position();
-
-        if self.food_exists && self.food_x == head_x && self.food_y == head_y {
-            self.food_exists = false;
-            self.snake.restore_last_removed();
-
-            self.combo_count += 1;
-            self.combo_timer = 3.0;
-
-            let combo_bonus = if self.combo_count > 1 {
-                self.combo_count * 2
-            } else {
-                0
-            };
-
-            self.score += 10 + combo_bonus;
-
-            self.screen_shake_timer = 0.1;
-            self.screen_shake_intensity = 2.0;
+    fn check_eating(&mut self, index: usize) {
+        let (head_x, head_y) = self.players[index].snake().head_position();
+
+        let eaten = self
+            .foods
+            .iter()
+            .position(|food| (food.x, food.y) == (head_x, head_y));
+        if let Some(food_index) = eaten {
+            let food = self.foods.remove(food_index);
+            self.players[index].eat_food(food.food_type);
+            self.emit(GameEvent::FoodEaten {
+                player: index,
+                food_type: food.food_type,
+            });
+            self.emit(GameEvent::ComboChanged {
+                player: index,
+                combo: self.players[index].combo_count(),
+            });
+            self.emit(GameEvent::ScoreChanged {
+                player: index,
+                score: self.players[index].score(),
+            });
         }
 
         let mut powerup_to_remove = None;
@@ -474,98 +1515,193 @@ impl Game {
             }
         }
 
-        if let Some((index, powerup_type)) = powerup_to_remove {
-            match powerup_type {
-                PowerUpType::Speed => {
-                    self.is_speed_boosted = true;
-                    self.speed_boost_timer = SPEED_BOOST_DURATION;
+        if let Some((powerup_index, powerup_type)) = powerup_to_remove {
+            self.powerups.remove(powerup_index);
+            match self.inventory {
+                Some(rules)
+                    if rules.stores(powerup_type)
+                        && self.players[index].inventory().len() < rules.capacity =>
+                {
+                    self.players[index].store_powerup(powerup_type);
                 }
-                PowerUpType::Shield => {
-                    self.is_shielded = true;
-                    self.shield_timer = SHIELD_DURATION;
+                _ => {
+                    self.players[index].collect_powerup(powerup_type);
+                    powerup_type.effect().on_pickup(self, index);
                 }
             }
-
-            self.score += 50;
-            self.screen_shake_timer = 0.2;
-            self.screen_shake_intensity = 4.0;
-            self.powerups.remove(index);
+            self.emit(GameEvent::PowerUpCollected {
+                player: index,
+                powerup_type,
+            });
+            self.emit(GameEvent::ScoreChanged {
+                player: index,
+                score: self.players[index].score(),
+            });
         }
     }
 
-    fn check_if_snake_alive(&self, dir: Option<Direction>) -> bool {
-        let (next_x, next_y) = self.snake.next_head_position(dir);
+    fn find_collision(&self, index: usize, x: i32, y: i32) -> Option<Collision> {
+        let player = &self.players[index];
+        if !player.passes_own_body() && player.snake().is_overlap_except_tail(x, y) {
+            return Some(Collision::Body);
+        }
 
-        if self.snake.is_overlap_except_tail(next_x, next_y) {
-            return self.is_shielded;
+        if self.is_wall(x, y) {
+            return Some(Collision::Wall);
         }
 
-        let wall_collision =
-            next_x <= 0 || next_y <= 0 || next_x >= self.width - 1 || next_y >= self.height - 1;
+        if !self.rules.snakes_collide {
+            return None;
+        }
 
-        if wall_collision {
-            return self.is_shielded;
+        for (other, player) in self.players.iter().enumerate() {
+            if other == index || !player.is_alive() {
+                continue;
+            }
+            if player.snake().head_position() == (x, y) {
+                return Some(Collision::Head(other));
+            }
+            if player.snake().occupies(x, y) {
+                return Some(Collision::Body);
+            }
         }
 
+        None
+    }
+
+    /// Returns false when there is no free cell left for the food.
+    fn add_food(&mut self) -> bool {
+        let Some((new_x, new_y)) = self.random_free_cell(|game, x, y| {
+            game.is_occupied(x, y)
+                || game.is_food_at(x, y)
+                || game.powerups.iter().any(|p| p.x == x && p.y == y)
+        }) else {
+            return false;
+        };
+
+        let food_type = food::choose(self.rng.random::<f64>());
+        self.foods.push(Food::new(new_x, new_y, food_type));
         true
     }
 
-    fn add_food(&mut self) {
-        let mut rng = rand::rng();
+    fn update_snake(&mut self, index: usize, dir: Option<Direction>) {
+        let level = self.players[index].speed_level();
+        let (next_x, next_y) = self.players[index].snake().next_head_position(dir);
+        let shielded = self.players[index].is_shielded();
+
+        let collision = self.find_collision(index, next_x, next_y);
+        let cause = match collision {
+            None => None,
+            Some(Collision::Wall) => Some(DeathCause::Wall),
+            Some(Collision::Body) => Some(DeathCause::Body),
+            Some(Collision::Head(other)) => Some(DeathCause::HeadOn { other }),
+        };
+        let (survives, shield_saved) = match collision {
+            None => (true, false),
+            Some(Collision::Wall) | Some(Collision::Body) => (shielded, shielded),
+            Some(Collision::Head(other)) => {
+                let own_length = self.players[index].snake().length();
+                let other_length = self.players[other].snake().length();
+                let (attacker_dies, other_dies) = match self.rules.head_on {
+                    HeadOnRule::BothDie => (true, true),
+                    HeadOnRule::ShorterDies => {
+                        (own_length <= other_length, other_length <= own_length)
+                    }
+                };
+
+                if other_dies && !self.players[other].is_shielded() {
+                    self.players[other].kill();
+                    self.emit(GameEvent::Died {
+                        player: other,
+                        cause: DeathCause::HeadOn { other: index },
+                    });
+                }
+                (!attacker_dies || shielded, attacker_dies && shielded)
+            }
+        };
 
-        let mut new_x = rng.random_range(1..(self.width - 1));
-        let mut new_y = rng.random_range(1..(self.height - 1));
+        if shield_saved {
+            self.emit(GameEvent::ShieldSaved { player: index });
+        }
+        match cause {
+            Some(cause) if !survives => {
+                self.players[index].kill();
+                self.emit(GameEvent::Died {
+                    player: index,
+                    cause,
+                });
+            }
+            _ => {
+                self.players[index].move_forward(dir);
+                self.check_eating(index);
+            }
+        }
 
-        while self.snake.is_overlap_except_tail(new_x, new_y)
-            || self.powerups.iter().any(|p| p.x == new_x && p.y == new_y)
-        {
-            new_x = rng.random_range(1..(self.width - 1));
-            new_y = rng.random_range(1..(self.height - 1));
+        if self.players[index].speed_level() > level {
+            self.players[index].level_up();
+            self.emit(GameEvent::LevelUp {
+                player: index,
+                level: self.players[index].speed_level(),
+            });
         }
 
-        self.food_x = new_x;
-        self.food_y = new_y;
-        self.food_exists = true;
-        self.food_timer = 0.0;
+        self.check_game_over();
     }
 
-    fn update_snake(&mut self, dir: Option<Direction>) {
-        if self.check_if_snake_alive(dir) {
-            self.snake.move_forward(dir);
-            self.check_eating();
-        } else {
-            self.is_game_over = true;
-            self.game_over_animation = 0.0;
-            self.speed_boost_timer = 0.0;
-            self.shield_timer = 0.0;
-            self.is_speed_boosted = false;
-            self.is_shielded = false;
-            self.screen_shake_timer = 0.0;
+    fn check_game_over(&mut self) {
+        let alive = self.players.iter().filter(|p| p.is_alive()).count();
+        let is_over = alive == 0 || (self.players.len() > 1 && alive == 1);
+        if !is_over || self.is_game_over {
+            return;
+        }
+
+        self.is_game_over = true;
+        self.game_over_animation = 0.0;
+        self.screen_shake_timer = 0.0;
+
+        for player in self.players.iter_mut() {
+            player.clear_effects();
+        }
 
-            if self.score > self.high_score {
-                self.high_score = self.score;
+        if self.players.len() > 1 {
+            if let Some(winner) = self.winner_index() {
+                self.round_wins[winner] += 1;
             }
+            for (total, player) in self.match_scores.iter_mut().zip(&self.players) {
+                *total += player.score();
+            }
+        }
+
+        if self.score() > self.high_score {
+            self.high_score = self.score();
         }
-        self.waiting_time = 0.0;
+    }
+
+    fn new_match(&mut self) {
+        self.round = 1;
+        self.restart();
+        self.round_wins = vec![0; self.players.len()];
+        self.match_scores = vec![0; self.players.len()];
     }
 
     fn restart(&mut self) {
-        self.snake = Snake::new(2, 2);
-        self.waiting_time = 0.0;
-        self.food_exists = true;
-        self.food_x = 5;
-        self.food_y = 3;
+        let names: Vec<String> = self.players.iter().map(|p| p.name().to_string()).collect();
+        self.players = Game::spawn_players(self.mode, self.width, self.height);
+        for player in self.players.iter_mut() {
+            player.set_difficulty(self.difficulty);
+            player.set_speed_ramp(self.speed_ramp);
+        }
+        if names.len() == self.players.len() {
+            for (player, name) in self.players.iter_mut().zip(&names) {
+                player.set_name(name);
+            }
+        }
+        self.foods = vec![Food::new(5, 3, FoodType::Normal)];
         self.is_game_over = false;
-        self.score = 0;
         self.powerups.clear();
-        self.speed_boost_timer = 0.0;
-        self.shield_timer = 0.0;
-        self.is_speed_boosted = false;
-        self.is_shielded = false;
         self.screen_shake_timer = 0.0;
         self.screen_shake_intensity = 0.0;
-        self.combo_count = 0;
-        self.combo_timer = 0.0;
         self.game_over_animation = 0.0;
+        self.events.push(GameEvent::Restarted);
     }
 }
@@ -0,0 +1,155 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::drawing::{draw_block, to_gui_coord};
+use crate::game::{Game, GameMode};
+use crate::replay::{Replay, ReplayConfig};
+use crate::sha256::sha256;
+
+pub const BEST_RUNS_DIR: &str = "replays/best";
+
+const GHOST_COLOR: Color = [0.85, 0.90, 1.0, 0.3];
+const AHEAD_COLOR: Color = [0.30, 0.85, 0.40, 1.0];
+const BEHIND_COLOR: Color = [0.91, 0.30, 0.24, 1.0];
+
+/// Replays a personal-best run tick for tick alongside the live game.
+pub struct Ghost {
+    replay: Replay,
+    game: Game,
+    tick: u32,
+    final_score: u32,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay) -> Ghost {
+        let final_score = run_score(&replay);
+        Ghost {
+            game: replay.config.new_game(),
+            replay,
+            tick: 0,
+            final_score,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Ghost> {
+        Ok(Ghost::new(Replay::load(path)?))
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn final_score(&self) -> u32 {
+        self.final_score
+    }
+
+    pub fn score(&self) -> u32 {
+        self.game.score()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game.is_game_over() || self.tick >= self.replay.ticks
+    }
+
+    pub fn restart(&mut self) {
+        self.game = self.replay.config.new_game();
+        self.tick = 0;
+    }
+
+    /// Fast-forwards the ghost to `tick` of its recording.
+    pub fn seek(&mut self, tick: u32) {
+        self.restart();
+        while self.tick < tick && !self.is_finished() {
+            self.update();
+        }
+    }
+
+    /// Advances the ghost by one tick of the recording.
+    pub fn update(&mut self) {
+        if !self.is_finished() {
+            self.replay.simulate_tick(&mut self.game, self.tick);
+            self.tick += 1;
+        }
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d) {
+        if self.is_finished() {
+            return;
+        }
+        for (x, y) in self.game.snake().body_positions() {
+            draw_block(GHOST_COLOR, x, y, con, g);
+        }
+    }
+
+    /// Shows how far the live score is ahead of or behind the ghost's score
+    /// at the same tick.
+    pub fn draw_hud(&self, live_score: u32, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let ghost_score = self.score();
+        let (line, color) = if live_score >= ghost_score {
+            (format!("Ghost +{}", live_score - ghost_score), AHEAD_COLOR)
+        } else {
+            (format!("Ghost -{}", ghost_score - live_score), BEHIND_COLOR)
+        };
+
+        text::Text::new_color(color, 18)
+            .draw(
+                &format!("{} (best {})", line, self.final_score),
+                glyphs,
+                &con.draw_state,
+                con.transform
+                    .trans(to_gui_coord(self.replay.config.width) / 2.0 - 70.0, 25.0),
+                g,
+            )
+            .unwrap();
+    }
+}
+
+/// Score of a single-player run when the recording stopped.
+pub fn run_score(replay: &Replay) -> u32 {
+    replay.play_out().score()
+}
+
+/// File in `dir` holding the personal best for `config`'s settings. Runs are
+/// only compared with runs under the same settings, whatever their seed.
+pub fn best_run_path(dir: &Path, config: &ReplayConfig) -> PathBuf {
+    let settings = Replay::new(ReplayConfig { seed: 0, ..*config }).encode();
+    let name: String = sha256(&settings)[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    dir.join(format!("{}.replay", name))
+}
+
+/// Keeps `replay` in `dir` as the personal best when it is a single-player
+/// run that beats the one stored for the same settings. Returns whether it
+/// was saved.
+pub fn save_if_best(replay: &Replay, dir: &Path) -> io::Result<bool> {
+    if replay.config.mode != GameMode::Single {
+        return Ok(false);
+    }
+
+    let score = run_score(replay);
+
+    let path = best_run_path(dir, &replay.config);
+    let best = match Replay::load(&path) {
+        Ok(best) => Some(run_score(&best)),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::InvalidData
+            ) =>
+        {
+            None
+        }
+        Err(e) => return Err(e),
+    };
+    if best.is_some_and(|best| best >= score) {
+        return Ok(false);
+    }
+
+    replay.save(&path)?;
+    Ok(true)
+}
@@ -0,0 +1,359 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::daily::Date;
+use crate::drawing::{draw_rectangle, to_gui_coord};
+use crate::game::{Difficulty, GameMode};
+use crate::net::{invalid, Reader, Writer};
+use crate::replay::ReplayConfig;
+
+pub const LEADERBOARD_PATH: &str = "profiles/leaderboards";
+pub const LEADERBOARD_VERSION: u16 = 1;
+pub const TABLE_SIZE: usize = 10;
+pub const MAX_NAME_LEN: usize = 12;
+
+const LEADERBOARD_MAGIC: u32 = u32::from_le_bytes(*b"SNKL");
+const SCREEN_BACKGROUND: Color = [0.0, 0.0, 0.0, 0.8];
+const SCREEN_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+const HIGHLIGHT_COLOR: Color = [1.0, 0.84, 0.0, 1.0];
+
+/// What a score is compared against: games are only ranked with others of
+/// the same mode, board size and difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardKey {
+    pub mode: GameMode,
+    pub width: i32,
+    pub height: i32,
+    pub difficulty: Difficulty,
+}
+
+impl BoardKey {
+    pub fn from_config(config: &ReplayConfig) -> BoardKey {
+        BoardKey {
+            mode: config.mode,
+            width: config.width,
+            height: config.height,
+            difficulty: config.difficulty,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mode = match self.mode {
+            GameMode::Single => "Single".to_string(),
+            GameMode::Versus => "Versus".to_string(),
+            GameMode::TwoPlayer => "Two player".to_string(),
+            GameMode::Online { players } => format!("Online x{}", players),
+        };
+        format!(
+            "{} {}x{} {}",
+            mode,
+            self.width,
+            self.height,
+            self.difficulty.name()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: u32,
+    pub length: u32,
+    pub duration: f64,
+    pub date: Date,
+}
+
+/// Top-10 tables, one per `BoardKey`, highest score first.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Leaderboards {
+    tables: Vec<(BoardKey, Vec<LeaderboardEntry>)>,
+}
+
+impl Leaderboards {
+    pub fn table(&self, key: &BoardKey) -> &[LeaderboardEntry] {
+        self.tables
+            .iter()
+            .find(|(k, _)| k == key)
+            .map_or(&[], |(_, entries)| entries)
+    }
+
+    pub fn best(&self, key: &BoardKey) -> u32 {
+        self.table(key).first().map_or(0, |entry| entry.score)
+    }
+
+    /// Whether `score` would make it into the table for `key`.
+    pub fn qualifies(&self, key: &BoardKey, score: u32) -> bool {
+        let table = self.table(key);
+        score > 0 && (table.len() < TABLE_SIZE || table.iter().any(|e| score > e.score))
+    }
+
+    /// Inserts `entry` in score order and returns its rank, or `None` if it
+    /// did not make the table. Equal scores keep the earlier entry first.
+    pub fn insert(&mut self, key: BoardKey, entry: LeaderboardEntry) -> Option<usize> {
+        if !self.qualifies(&key, entry.score) {
+            return None;
+        }
+
+        let index = match self.tables.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                self.tables.push((key, Vec::new()));
+                self.tables.len() - 1
+            }
+        };
+        let table = &mut self.tables[index].1;
+        let rank = table.partition_point(|e| e.score >= entry.score);
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.u32(LEADERBOARD_MAGIC);
+        w.u16(LEADERBOARD_VERSION);
+        w.list(&self.tables, |w, (key, entries)| {
+            w.game_mode(key.mode);
+            w.position((key.width, key.height));
+            w.difficulty(key.difficulty);
+            w.list(entries, |w, entry| {
+                w.string(&entry.name);
+                w.u32(entry.score);
+                w.u32(entry.length);
+                w.f64(entry.duration);
+                w.i32(entry.date.year);
+                w.u8(entry.date.month as u8);
+                w.u8(entry.date.day as u8);
+            });
+        });
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Leaderboards> {
+        let mut r = Reader::new(payload);
+        if r.u32()? != LEADERBOARD_MAGIC {
+            return Err(invalid("not a leaderboard file".to_string()));
+        }
+        let version = r.u16()?;
+        if version != LEADERBOARD_VERSION {
+            return Err(invalid(format!(
+                "leaderboard version {} is not supported, expected {}",
+                version, LEADERBOARD_VERSION
+            )));
+        }
+
+        let tables = r.list(|r| {
+            let mode = r.game_mode()?;
+            let (width, height) = r.position()?;
+            let key = BoardKey {
+                mode,
+                width,
+                height,
+                difficulty: r.difficulty()?,
+            };
+            let entries = r.list(|r| {
+                Ok(LeaderboardEntry {
+                    name: r.string()?,
+                    score: r.u32()?,
+                    length: r.u32()?,
+                    duration: r.f64()?,
+                    date: Date {
+                        year: r.i32()?,
+                        month: r.u8()? as u32,
+                        day: r.u8()? as u32,
+                    },
+                })
+            })?;
+            Ok((key, entries))
+        })?;
+        Ok(Leaderboards { tables })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    /// Loads the leaderboards at `path`, or empty ones if there are none yet.
+    pub fn load(path: &Path) -> io::Result<Leaderboards> {
+        match fs::read(path) {
+            Ok(payload) => Leaderboards::decode(&payload),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Leaderboards::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Draws the table for `key` over the board, highlighting `highlight`.
+    pub fn draw(
+        &self,
+        key: &BoardKey,
+        highlight: Option<usize>,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+    ) {
+        draw_rectangle(SCREEN_BACKGROUND, 0, 0, key.width, key.height, con, g);
+        let left = to_gui_coord(key.width) / 2.0 - 230.0;
+
+        let mut y = 70.0;
+        for (size, line) in [
+            (28, "LEADERBOARD".to_string()),
+            (18, key.describe()),
+            (
+                16,
+                " #  Name            Score  Length   Time        Date".to_string(),
+            ),
+        ] {
+            text::Text::new_color(SCREEN_COLOR, size)
+                .draw(
+                    &line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(left, y),
+                    g,
+                )
+                .unwrap();
+            y += 32.0;
+        }
+
+        let table = self.table(key);
+        if table.is_empty() {
+            text::Text::new_color(SCREEN_COLOR, 16)
+                .draw(
+                    "No scores yet",
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(left, y),
+                    g,
+                )
+                .unwrap();
+        }
+        for (rank, entry) in table.iter().enumerate() {
+            let color = if highlight == Some(rank) {
+                HIGHLIGHT_COLOR
+            } else {
+                SCREEN_COLOR
+            };
+            let line = format!(
+                "{:>2}  {:<12}  {:>6}  {:>6}  {:>4.0}s  {}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                entry.length,
+                entry.duration,
+                entry.date
+            );
+            text::Text::new_color(color, 16)
+                .draw(
+                    &line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(left, y),
+                    g,
+                )
+                .unwrap();
+            y += 24.0;
+        }
+
+        text::Text::new_color(SCREEN_COLOR, 16)
+            .draw(
+                "Press L to close",
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(left, to_gui_coord(key.height) - 40.0),
+                g,
+            )
+            .unwrap();
+    }
+}
+
+/// Asks for a name for a score that made the leaderboard.
+pub struct NameEntry {
+    pub name: String,
+    pub player: String,
+    pub score: u32,
+    pub length: u32,
+    pub duration: f64,
+}
+
+impl NameEntry {
+    pub fn new(name: &str, player: &str, score: u32, length: usize, duration: f64) -> NameEntry {
+        NameEntry {
+            name: name.chars().take(MAX_NAME_LEN).collect(),
+            player: player.to_string(),
+            score,
+            length: length as u32,
+            duration,
+        }
+    }
+
+    pub fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.name.chars().count() < MAX_NAME_LEN && (c.is_alphanumeric() || c == ' ') {
+                self.name.push(c);
+            }
+        }
+    }
+
+    /// Handles editing keys. Returns true once the name is confirmed.
+    pub fn key_pressed(&mut self, key: Key) -> bool {
+        match key {
+            Key::Backspace => {
+                self.name.pop();
+                false
+            }
+            Key::Return => !self.name.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    pub fn entry(&self, date: Date) -> LeaderboardEntry {
+        LeaderboardEntry {
+            name: self.name.trim().to_string(),
+            score: self.score,
+            length: self.length,
+            duration: self.duration,
+            date,
+        }
+    }
+
+    pub fn draw(&self, width: i32, height: i32, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        draw_rectangle(SCREEN_BACKGROUND, 0, 0, width, height, con, g);
+        let center_x = to_gui_coord(width) / 2.0;
+        let center_y = to_gui_coord(height) / 2.0;
+
+        let lines = [
+            (HIGHLIGHT_COLOR, 32, "NEW RECORD!".to_string(), -100.0),
+            (
+                SCREEN_COLOR,
+                20,
+                format!("{} scored {}", self.player, self.score),
+                -50.0,
+            ),
+            (SCREEN_COLOR, 24, format!("Name: {}_", self.name), 0.0),
+            (
+                SCREEN_COLOR,
+                16,
+                "Type a name, Enter to save".to_string(),
+                50.0,
+            ),
+        ];
+        for (color, size, line, offset_y) in &lines {
+            text::Text::new_color(*color, *size)
+                .draw(
+                    line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(center_x - 120.0, center_y + offset_y),
+                    g,
+                )
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+extern crate gif;
+extern crate piston_window;
+extern crate png;
+extern crate rand;
+extern crate rand_chacha;
+
+pub mod achievements;
+pub mod ai;
+pub mod batch;
+pub mod client;
+pub mod clip;
+pub mod daily;
+pub mod desync;
+pub mod drawing;
+pub mod env;
+pub mod events;
+pub mod food;
+pub mod game;
+pub mod ghost;
+pub mod leaderboard;
+pub mod lockstep;
+pub mod net;
+pub mod player;
+pub mod powerup;
+pub mod profile;
+pub mod raster;
+pub mod replay;
+pub mod save;
+pub mod server;
+pub mod sha256;
+pub mod snake;
+pub mod spectate;
+pub mod submission;
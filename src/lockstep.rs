@@ -0,0 +1,583 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::desync::StateHashes;
+use crate::game::{Game, GameMode};
+use crate::net::{invalid, Connection, Reader, Writer, PROTOCOL_VERSION};
+use crate::player::MAX_PLAYERS;
+use crate::snake::Direction;
+
+pub const MAX_ROLLBACK: u32 = 30;
+
+const HASH_HISTORY: u32 = 256;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct LockstepConfig {
+    pub player: usize,
+    /// Listen address of every peer, indexed by player.
+    pub peers: Vec<String>,
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    pub tick_rate: f64,
+    pub input_delay: u32,
+    pub restart_delay: u32,
+    /// Only player 0's seed is used; the others receive it when connecting.
+    pub seed: Option<u64>,
+}
+
+impl Default for LockstepConfig {
+    fn default() -> LockstepConfig {
+        LockstepConfig {
+            player: 0,
+            peers: Vec::new(),
+            name: "Player".to_string(),
+            width: 27,
+            height: 22,
+            tick_rate: 20.0,
+            input_delay: 2,
+            restart_delay: 60,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerMessage {
+    Hello {
+        version: u16,
+        player: u8,
+        name: String,
+        seed: u64,
+    },
+    Input {
+        tick: u32,
+        direction: Option<Direction>,
+    },
+    Checksum {
+        tick: u32,
+        hash: u64,
+    },
+    Hashes {
+        tick: u32,
+        hashes: StateHashes,
+    },
+}
+
+impl PeerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        match self {
+            PeerMessage::Hello {
+                version,
+                player,
+                name,
+                seed,
+            } => {
+                w.u8(0);
+                w.u16(*version);
+                w.u8(*player);
+                w.string(name);
+                w.u64(*seed);
+            }
+            PeerMessage::Input { tick, direction } => {
+                w.u8(1);
+                w.u32(*tick);
+                w.option(direction.as_ref(), |w, d| w.direction(*d));
+            }
+            PeerMessage::Checksum { tick, hash } => {
+                w.u8(2);
+                w.u32(*tick);
+                w.u64(*hash);
+            }
+            PeerMessage::Hashes { tick, hashes } => {
+                w.u8(3);
+                w.u32(*tick);
+                hashes.write(&mut w);
+            }
+        }
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<PeerMessage> {
+        let mut r = Reader::new(payload);
+        let message = match r.u8()? {
+            0 => PeerMessage::Hello {
+                version: r.u16()?,
+                player: r.u8()?,
+                name: r.string()?,
+                seed: r.u64()?,
+            },
+            1 => PeerMessage::Input {
+                tick: r.u32()?,
+                direction: r.option(|r| r.direction())?,
+            },
+            2 => PeerMessage::Checksum {
+                tick: r.u32()?,
+                hash: r.u64()?,
+            },
+            3 => PeerMessage::Hashes {
+                tick: r.u32()?,
+                hashes: StateHashes::read(&mut r)?,
+            },
+            tag => return Err(invalid(format!("unknown peer message {}", tag))),
+        };
+        Ok(message)
+    }
+}
+
+/// The first tick at which a peer's state stopped matching ours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Desync {
+    pub tick: u32,
+    pub peer: usize,
+    pub field: String,
+}
+
+struct Frame {
+    tick: u32,
+    game: Game,
+    game_over_ticks: u32,
+}
+
+/// Deterministic lockstep between peers that exchange only their inputs.
+/// Missing remote inputs are predicted as "no turn"; when a late input
+/// proves the prediction wrong the game rolls back to that tick and
+/// resimulates. Every fully confirmed tick is checksummed and compared with
+/// the other peers to detect desyncs.
+pub struct Lockstep {
+    config: LockstepConfig,
+    peers: Vec<Option<Connection<TcpStream>>>,
+    game: Game,
+    tick: u32,
+    game_over_ticks: u32,
+    elapsed: f64,
+    pending_direction: Option<Direction>,
+    inputs: Vec<BTreeMap<u32, Option<Direction>>>,
+    received: Vec<u32>,
+    frames: VecDeque<Frame>,
+    rollback_to: Option<u32>,
+    rollbacks: u32,
+    hashed: u32,
+    local_hashes: BTreeMap<u32, StateHashes>,
+    remote_checksums: BTreeMap<u32, Vec<(usize, u64)>>,
+    verified: Vec<u32>,
+    reported: Vec<bool>,
+    desync: Option<Desync>,
+}
+
+impl Lockstep {
+    /// Listens on this player's address, connects to every lower-numbered
+    /// peer and waits until all peers have introduced themselves.
+    pub fn connect(config: LockstepConfig) -> io::Result<Lockstep> {
+        let count = config.peers.len();
+        if !(2..=MAX_PLAYERS).contains(&count) || config.player >= count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("player {} of {} peers", config.player, count),
+            ));
+        }
+
+        let listener = TcpListener::bind(&config.peers[config.player])?;
+        listener.set_nonblocking(true)?;
+
+        let own_seed = config.seed.unwrap_or_else(|| rand::rng().random());
+        let hello = PeerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            player: config.player as u8,
+            name: config.name.clone(),
+            seed: own_seed,
+        }
+        .encode();
+
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let mut unidentified = Vec::new();
+        for address in &config.peers[..config.player] {
+            let stream = loop {
+                match TcpStream::connect(address) {
+                    Ok(stream) => break stream,
+                    Err(_) if Instant::now() < deadline => {
+                        thread::sleep(Duration::from_millis(100))
+                    }
+                    Err(e) => return Err(e),
+                }
+            };
+            stream.set_nodelay(true)?;
+            stream.set_nonblocking(true)?;
+            let mut connection = Connection::new(stream);
+            connection.send(&hello);
+            connection.flush()?;
+            unidentified.push(connection);
+        }
+
+        let mut peers: Vec<Option<Connection<TcpStream>>> = (0..count).map(|_| None).collect();
+        let mut names = vec![String::new(); count];
+        names[config.player] = config.name.clone();
+        let mut seed = if config.player == 0 {
+            Some(own_seed)
+        } else {
+            None
+        };
+        let mut backlog = Vec::new();
+
+        while (0..count).any(|p| p != config.player && peers[p].is_none()) {
+            if Instant::now() >= deadline {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nodelay(true)?;
+                        stream.set_nonblocking(true)?;
+                        let mut connection = Connection::new(stream);
+                        connection.send(&hello);
+                        connection.flush()?;
+                        unidentified.push(connection);
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            for mut connection in std::mem::take(&mut unidentified) {
+                let frames = connection.receive()?;
+                let Some(first) = frames.first() else {
+                    unidentified.push(connection);
+                    continue;
+                };
+
+                let PeerMessage::Hello {
+                    version,
+                    player,
+                    name,
+                    seed: peer_seed,
+                } = PeerMessage::decode(first)?
+                else {
+                    return Err(invalid("expected hello".to_string()));
+                };
+                let player = player as usize;
+                if version != PROTOCOL_VERSION {
+                    return Err(invalid(format!(
+                        "peer {} speaks protocol version {}, expected {}",
+                        player, version, PROTOCOL_VERSION
+                    )));
+                }
+                if player >= count || player == config.player || peers[player].is_some() {
+                    return Err(invalid(format!("unexpected peer {}", player)));
+                }
+
+                if player == 0 {
+                    seed = Some(peer_seed);
+                }
+                names[player] = name;
+                backlog.extend(frames[1..].iter().map(|frame| (player, frame.clone())));
+                peers[player] = Some(connection);
+            }
+
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let mut game = Game::with_mode(
+            config.width,
+            config.height,
+            GameMode::Online { players: count },
+        );
+        game.reset(seed.unwrap_or(own_seed));
+        for (index, name) in names.iter().enumerate() {
+            game.set_player_name(index, name);
+        }
+
+        let delay = config.input_delay;
+        let mut lockstep = Lockstep {
+            config,
+            peers,
+            game,
+            tick: 0,
+            game_over_ticks: 0,
+            elapsed: 0.0,
+            pending_direction: None,
+            inputs: vec![(0..delay).map(|tick| (tick, None)).collect(); count],
+            received: vec![delay; count],
+            frames: VecDeque::new(),
+            rollback_to: None,
+            rollbacks: 0,
+            hashed: 0,
+            local_hashes: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            verified: vec![0; count],
+            reported: vec![false; count],
+            desync: None,
+        };
+        for (player, frame) in backlog {
+            lockstep.handle(player, &frame)?;
+        }
+        Ok(lockstep)
+    }
+
+    pub fn player_index(&self) -> usize {
+        self.config.player
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Ticks before this one have inputs from every peer and will not be
+    /// rolled back again.
+    pub fn confirmed_tick(&self) -> u32 {
+        self.received.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Ticks before this one have matched the checksums of every peer.
+    pub fn verified_tick(&self) -> u32 {
+        (0..self.verified.len())
+            .filter(|&peer| peer != self.config.player)
+            .map(|peer| self.verified[peer])
+            .min()
+            .unwrap_or(self.hashed)
+    }
+
+    /// A peer that disconnects stalls the session once its last inputs have
+    /// been simulated.
+    pub fn is_connected(&self, peer: usize) -> bool {
+        peer == self.config.player || self.peers.get(peer).is_some_and(|p| p.is_some())
+    }
+
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+
+    pub fn desync(&self) -> Option<&Desync> {
+        self.desync.as_ref()
+    }
+
+    /// Queues a turn for the next tick this peer simulates; it takes effect
+    /// `input_delay` ticks later on every peer.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.pending_direction = Some(direction);
+    }
+
+    /// Advances one tick for every `1 / tick_rate` seconds elapsed, stalling
+    /// when a peer falls more than `MAX_ROLLBACK` ticks behind.
+    pub fn update(&mut self, delta_time: f64) -> io::Result<()> {
+        let period = 1.0 / self.config.tick_rate;
+        self.elapsed = (self.elapsed + delta_time).min(period * MAX_ROLLBACK as f64);
+
+        loop {
+            let due = self.elapsed >= period;
+            if !self.poll(due)? {
+                return Ok(());
+            }
+            self.elapsed -= period;
+        }
+    }
+
+    fn poll(&mut self, advance: bool) -> io::Result<bool> {
+        self.receive()?;
+
+        if let Some(tick) = self.rollback_to.take() {
+            self.rollback(tick);
+        }
+
+        let advanced = advance && self.tick < self.confirmed_tick() + MAX_ROLLBACK;
+        if advanced {
+            self.advance();
+        }
+
+        self.exchange_checksums();
+        self.flush();
+        Ok(advanced)
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        for peer in 0..self.peers.len() {
+            let Some(connection) = self.peers[peer].as_mut() else {
+                continue;
+            };
+            let Ok(frames) = connection.receive() else {
+                self.peers[peer] = None;
+                continue;
+            };
+            for frame in frames {
+                self.handle(peer, &frame)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, peer: usize, frame: &[u8]) -> io::Result<()> {
+        match PeerMessage::decode(frame)? {
+            PeerMessage::Hello { .. } => {}
+            PeerMessage::Input { tick, direction } => {
+                if tick < self.received[peer] {
+                    return Ok(());
+                }
+                self.inputs[peer].insert(tick, direction);
+                self.received[peer] = tick + 1;
+
+                if tick < self.tick && direction.is_some() {
+                    self.rollback_to = Some(self.rollback_to.map_or(tick, |t| t.min(tick)));
+                }
+            }
+            PeerMessage::Checksum { tick, hash } => {
+                if self.local_hashes.contains_key(&tick) {
+                    self.compare_checksum(peer, tick, hash);
+                } else if tick >= self.hashed {
+                    self.remote_checksums
+                        .entry(tick)
+                        .or_default()
+                        .push((peer, hash));
+                }
+            }
+            PeerMessage::Hashes { tick, hashes } => {
+                if let (None, Some(local)) = (&self.desync, self.local_hashes.get(&tick)) {
+                    let field = local
+                        .first_divergence(&hashes)
+                        .unwrap_or_else(|| "checksum".to_string());
+                    self.desync = Some(Desync { tick, peer, field });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) {
+        let input_tick = self.tick + self.config.input_delay;
+        let direction = self.pending_direction.take();
+        self.inputs[self.config.player].insert(input_tick, direction);
+        self.received[self.config.player] = input_tick + 1;
+        self.broadcast(&PeerMessage::Input {
+            tick: input_tick,
+            direction,
+        });
+
+        self.save_frame();
+        self.simulate_tick();
+    }
+
+    fn rollback(&mut self, tick: u32) {
+        let Some(index) = self.frames.iter().position(|f| f.tick == tick) else {
+            return;
+        };
+
+        let current = self.tick;
+        let frame = self.frames.drain(index..).next().unwrap();
+        self.game = frame.game;
+        self.tick = frame.tick;
+        self.game_over_ticks = frame.game_over_ticks;
+
+        while self.tick < current {
+            self.save_frame();
+            self.simulate_tick();
+        }
+        self.rollbacks += 1;
+    }
+
+    fn save_frame(&mut self) {
+        self.frames.push_back(Frame {
+            tick: self.tick,
+            game: self.game.clone(),
+            game_over_ticks: self.game_over_ticks,
+        });
+    }
+
+    fn simulate_tick(&mut self) {
+        for (player, inputs) in self.inputs.iter().enumerate() {
+            if let Some(Some(direction)) = inputs.get(&self.tick) {
+                self.game.queue_direction(player, *direction);
+            }
+        }
+
+        self.game.update(1.0 / self.config.tick_rate);
+        self.game.take_events();
+        self.tick += 1;
+
+        if self.game.is_game_over() {
+            self.game_over_ticks += 1;
+            if self.game_over_ticks >= self.config.restart_delay {
+                self.game_over_ticks = 0;
+                self.game.next_round();
+            }
+        }
+    }
+
+    fn exchange_checksums(&mut self) {
+        let confirmed = self.confirmed_tick().min(self.tick);
+        while self.hashed < confirmed {
+            let tick = self.hashed;
+            let hashes = match self.frames.iter().find(|f| f.tick == tick + 1) {
+                Some(frame) => frame.game.state_hashes(),
+                None => self.game.state_hashes(),
+            };
+            self.broadcast(&PeerMessage::Checksum {
+                tick,
+                hash: hashes.checksum(),
+            });
+            self.local_hashes.insert(tick, hashes);
+            self.hashed += 1;
+
+            for (peer, hash) in self.remote_checksums.remove(&tick).unwrap_or_default() {
+                self.compare_checksum(peer, tick, hash);
+            }
+        }
+
+        let hashed = self.hashed;
+        while self.frames.front().is_some_and(|f| f.tick < hashed) {
+            self.frames.pop_front();
+        }
+        for inputs in self.inputs.iter_mut() {
+            *inputs = inputs.split_off(&hashed);
+        }
+        let oldest = hashed.saturating_sub(HASH_HISTORY);
+        self.local_hashes = self.local_hashes.split_off(&oldest);
+        self.remote_checksums = self.remote_checksums.split_off(&oldest);
+    }
+
+    fn compare_checksum(&mut self, peer: usize, tick: u32, hash: u64) {
+        let Some(local) = self.local_hashes.get(&tick) else {
+            return;
+        };
+        if local.checksum() == hash {
+            self.verified[peer] = self.verified[peer].max(tick + 1);
+            return;
+        }
+        if self.reported[peer] {
+            return;
+        }
+
+        self.reported[peer] = true;
+        let message = PeerMessage::Hashes {
+            tick,
+            hashes: local.clone(),
+        };
+        if let Some(connection) = self.peers[peer].as_mut() {
+            connection.send(&message.encode());
+        }
+    }
+
+    fn broadcast(&mut self, message: &PeerMessage) {
+        let payload = message.encode();
+        for connection in self.peers.iter_mut().flatten() {
+            connection.send(&payload);
+        }
+    }
+
+    fn flush(&mut self) {
+        for peer in self.peers.iter_mut() {
+            if peer.as_mut().is_some_and(|c| c.flush().is_err()) {
+                *peer = None;
+            }
+        }
+    }
+}
@@ -1,63 +1,778 @@
 extern crate piston_window;
 extern crate rand;
+extern crate rust_snakegame;
 
-mod drawing;
-mod game;
-mod snake;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use piston_window::types::Color;
 use piston_window::*;
 
-use crate::drawing::to_gui_coord_u32;
-use crate::game::Game;
+use rust_snakegame::achievements::{Achievement, Achievements};
+use rust_snakegame::client::NetClient;
+use rust_snakegame::clip::{export_clip, ClipBuffer, ClipFormat, DEFAULT_CLIP_SECONDS};
+use rust_snakegame::daily::{Daily, DailyHistory, Date, DAILY_HISTORY_PATH};
+use rust_snakegame::drawing::to_gui_coord_u32;
+use rust_snakegame::food::FoodRules;
+use rust_snakegame::game::{
+    CollisionRules, Difficulty, Game, GameMode, HeadOnRule, RampMetric, SpeedCurve, SpeedRamp,
+};
+use rust_snakegame::ghost::{best_run_path, save_if_best, Ghost, BEST_RUNS_DIR};
+use rust_snakegame::leaderboard::{BoardKey, Leaderboards, NameEntry, LEADERBOARD_PATH};
+use rust_snakegame::lockstep::{Lockstep, LockstepConfig};
+use rust_snakegame::player::{Controller, KeyScheme};
+use rust_snakegame::powerup::{InventoryRules, PowerUpType};
+use rust_snakegame::profile::{Profiles, PROFILES_PATH};
+use rust_snakegame::replay::{Recorder, Replay, ReplayConfig, ReplayPlayer};
+use rust_snakegame::save::{SavedGame, SAVE_PATH};
+use rust_snakegame::spectate::{Broadcaster, Spectator, DEFAULT_DELAY};
+use rust_snakegame::submission::{ScoreRecord, SCORE_KEY_ENV};
 
 const BACKGROUND_COLOR: Color = [0.08, 0.10, 0.12, 1.0];
 
 fn main() {
     let (width, height) = (27, 22);
 
-    let mut window_settings = WindowSettings::new(
-        "🐍 Rust Snake Game",
-        [to_gui_coord_u32(width), to_gui_coord_u32(height)],
-    )
-    .exit_on_esc(true);
+    let args: Vec<String> = std::env::args().collect();
+    let has_flag = |flag: &str| args.iter().any(|arg| arg == flag);
+    let flag_value = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+    };
 
-    window_settings.set_vsync(true);
+    if let Some(address) = flag_value("--connect") {
+        let name = flag_value("--name").map_or("Player", |name| name.as_str());
+        run_online(address, name);
+        return;
+    }
 
-    let mut window: PistonWindow = window_settings.build().unwrap();
+    if let Some(address) = flag_value("--spectate") {
+        let delay = flag_value("--delay")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DELAY);
+        run_spectator(address, delay);
+        return;
+    }
 
-    let mut game = Game::new(width, height);
+    let clip_seconds = flag_value("--clip-seconds")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_CLIP_SECONDS);
+    let clip_format = match flag_value("--clip-format").map(|value| value.as_str()) {
+        Some("apng") | Some("png") => ClipFormat::Apng,
+        _ => ClipFormat::Gif,
+    };
 
-    let font_path = std::path::Path::new("assets/FiraSans-Regular.ttf");
-    let fallback_font = if cfg!(target_os = "windows") {
-        std::path::Path::new("C:\\Windows\\Fonts\\arial.ttf")
-    } else if cfg!(target_os = "macos") {
-        std::path::Path::new("/System/Library/Fonts/Arial.ttf")
+    if let Some(path) = flag_value("--replay") {
+        match flag_value("--export-clip") {
+            Some(output) => export_replay_clip(Path::new(path), Path::new(output), clip_seconds),
+            None => run_replay(Path::new(path), clip_seconds, clip_format),
+        }
+        return;
+    }
+
+    let mut broadcaster = flag_value("--broadcast").and_then(|address| {
+        Broadcaster::bind(address, width, height)
+            .map_err(|e| eprintln!("Could not broadcast on {}: {}", address, e))
+            .ok()
+    });
+
+    if let Some(peers) = flag_value("--peers") {
+        let defaults = LockstepConfig::default();
+        let config = LockstepConfig {
+            player: flag_value("--player")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0),
+            peers: peers.split(',').map(|peer| peer.to_string()).collect(),
+            name: flag_value("--name").cloned().unwrap_or(defaults.name),
+            seed: flag_value("--seed").and_then(|value| value.parse().ok()),
+            ..defaults
+        };
+        run_lockstep(config, broadcaster);
+        return;
+    }
+
+    if has_flag("--daily-history") {
+        print_daily_history(Path::new(DAILY_HISTORY_PATH));
+        return;
+    }
+
+    let mut daily = if has_flag("--daily") {
+        match Daily::load(Date::today(), Path::new(DAILY_HISTORY_PATH)) {
+            Ok(daily) => Some(daily),
+            Err(e) => {
+                eprintln!("Could not read daily history {}: {}", DAILY_HISTORY_PATH, e);
+                return;
+            }
+        }
     } else {
-        std::path::Path::new("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf")
+        None
     };
 
-    let mut glyphs = if font_path.exists() {
-        window.load_font(font_path).unwrap()
+    let mut profiles = match Profiles::load(Path::new(PROFILES_PATH)) {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            eprintln!("Could not read profiles {}: {}", PROFILES_PATH, e);
+            return;
+        }
+    };
+    if let Some(name) = flag_value("--profile") {
+        profiles.select(name);
+        save_profiles(&profiles);
+    }
+    let mut leaderboards = match Leaderboards::load(Path::new(LEADERBOARD_PATH)) {
+        Ok(leaderboards) => leaderboards,
+        Err(e) => {
+            eprintln!("Could not read leaderboards {}: {}", LEADERBOARD_PATH, e);
+            return;
+        }
+    };
+
+    let score_key = flag_value("--score-key")
+        .cloned()
+        .or_else(|| std::env::var(SCORE_KEY_ENV).ok());
+
+    let mut window = create_window(width, height);
+
+    let mode = if daily.is_some() {
+        GameMode::Single
+    } else if has_flag("--two-player") {
+        GameMode::TwoPlayer
+    } else if has_flag("--versus") {
+        GameMode::Versus
     } else {
-        window
-            .load_font(fallback_font)
-            .expect("Could not load any font")
+        GameMode::Single
+    };
+
+    let default_rounds = if mode == GameMode::TwoPlayer { 3 } else { 1 };
+    let mut config = ReplayConfig {
+        width,
+        height,
+        mode,
+        rules: CollisionRules {
+            snakes_collide: !has_flag("--pass-through"),
+            head_on: if has_flag("--head-on-both-die") {
+                HeadOnRule::BothDie
+            } else {
+                HeadOnRule::ShorterDies
+            },
+        },
+        difficulty: flag_value("--difficulty")
+            .and_then(|value| Difficulty::from_name(value))
+            .filter(|_| daily.is_none())
+            .unwrap_or(Difficulty::Normal),
+        inventory: if has_flag("--inventory") && daily.is_none() {
+            Some(inventory_rules(
+                flag_value("--inventory-size"),
+                flag_value("--storable"),
+            ))
+        } else {
+            None
+        },
+        food: food_rules(
+            flag_value("--food").filter(|_| daily.is_none()),
+            flag_value("--cells-per-food").filter(|_| daily.is_none()),
+        ),
+        speed_ramp: match flag_value("--ramp").map(|value| value.as_str()) {
+            _ if daily.is_some() => None,
+            Some("score") => Some(RampMetric::Score),
+            Some("length") => Some(RampMetric::Length),
+            _ => None,
+        }
+        .map(|metric| {
+            speed_ramp(
+                metric,
+                flag_value("--ramp-curve"),
+                flag_value("--ramp-every"),
+                flag_value("--min-period"),
+            )
+        }),
+        best_of: flag_value("--best-of")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default_rounds),
+        seed: match &daily {
+            Some(daily) => daily.seed(),
+            None => flag_value("--seed")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(rand::random),
+        },
+        tick_duration: 1.0 / window.get_event_settings().ups as f64,
     };
 
+    let racing = has_flag("--ghost") && mode == GameMode::Single && daily.is_none();
+    let fixed_seed = daily.is_some() || flag_value("--seed").is_some();
+    let mut ghost = if racing {
+        Ghost::load(&best_run_path(Path::new(BEST_RUNS_DIR), &config)).ok()
+    } else {
+        None
+    };
+    if let Some(ghost) = &ghost {
+        config = ghost.replay().config;
+    }
+
+    let mut game = config.new_game();
+    game.set_high_score(leaderboards.best(&BoardKey::from_config(&config)));
+    let mut recorder = Recorder::new(config);
+    let mut replay_path = timestamped_path("replays", "replay");
+    let mut was_game_over = false;
+    let mut round_start = 0;
+    let mut name_entries: VecDeque<NameEntry> = VecDeque::new();
+    let mut show_leaderboard = false;
+    let mut highlight = None;
+    let mut clip = ClipBuffer::new(clip_seconds);
+    let mut saved = if daily.is_none() {
+        load_saved_game(Path::new(SAVE_PATH))
+    } else {
+        None
+    };
+
+    let mut achievements = Achievements::new(&profiles.current().achievements);
+
+    let mut glyphs = load_glyphs(&mut window);
+
     while let Some(event) = window.next() {
         if let Some(Button::Keyboard(key)) = event.press_args() {
-            game.key_pressed(key);
+            if let Some(entry) = name_entries.front_mut() {
+                if entry.key_pressed(key) {
+                    let entry = name_entries.pop_front().unwrap();
+                    highlight = leaderboards
+                        .insert(BoardKey::from_config(&config), entry.entry(Date::today()));
+                    if let Err(e) = leaderboards.save(Path::new(LEADERBOARD_PATH)) {
+                        eprintln!("Could not save leaderboards: {}", e);
+                    }
+                    show_leaderboard = true;
+                }
+            } else if saved.is_some() {
+                match key {
+                    Key::C | Key::Return => {
+                        let resumed = saved.take().unwrap();
+                        config = resumed.replay.config;
+                        ghost = ghost.filter(|ghost| ghost.replay().config == config);
+                        if let Some(ghost) = ghost.as_mut() {
+                            ghost.seek(resumed.replay.ticks);
+                        }
+                        game = resumed.game;
+                        recorder = Recorder::resume(resumed.replay);
+                        round_start = 0;
+                        remove_saved_game(Path::new(SAVE_PATH));
+                    }
+                    Key::N => {
+                        saved = None;
+                        remove_saved_game(Path::new(SAVE_PATH));
+                    }
+                    _ => {}
+                }
+            } else if game.is_match_over() && key == Key::Space {
+                if racing {
+                    ghost = Ghost::load(&best_run_path(Path::new(BEST_RUNS_DIR), &config)).ok();
+                    if let Some(ghost) = &ghost {
+                        config = ghost.replay().config;
+                    }
+                } else if !fixed_seed {
+                    config.seed = rand::random();
+                }
+                game = config.new_game();
+                game.set_high_score(leaderboards.best(&BoardKey::from_config(&config)));
+                recorder = Recorder::new(config);
+                round_start = 0;
+                replay_path = timestamped_path("replays", "replay");
+            } else if key == Key::F12 {
+                save_clip(&clip, clip_format);
+            } else if let (Some(daily), Key::H) = (daily.as_mut(), key) {
+                daily.toggle_history();
+            } else if key == Key::L {
+                show_leaderboard = !show_leaderboard;
+                highlight = None;
+            } else if key == Key::G {
+                achievements.toggle_gallery();
+            } else {
+                game.key_pressed(key);
+                recorder.key_pressed(key);
+            }
         }
 
+        event.text(|text| {
+            if let Some(entry) = name_entries.front_mut() {
+                entry.text(text);
+            }
+        });
+
         window.draw_2d(&event, |c, g, device| {
             clear(BACKGROUND_COLOR, g);
+            if let Some(ghost) = &ghost {
+                ghost.draw(&c, g);
+            }
             game.draw(&c, g, &mut glyphs);
+            if let Some(ghost) = &ghost {
+                ghost.draw_hud(game.score(), &c, g, &mut glyphs);
+            }
+            if let Some(daily) = &daily {
+                daily.draw(width, height, &c, g, &mut glyphs);
+            }
+            if let Some(entry) = name_entries.front() {
+                entry.draw(width, height, &c, g, &mut glyphs);
+            } else if show_leaderboard {
+                let key = BoardKey::from_config(&config);
+                leaderboards.draw(&key, highlight, &c, g, &mut glyphs);
+            }
+            achievements.draw(width, height, &c, g, &mut glyphs);
+            if let Some(saved) = &saved {
+                saved.draw_prompt(&c, g, &mut glyphs);
+            }
             glyphs.factory.encoder.flush(device);
         });
 
         event.update(|arg| {
+            if saved.is_some() {
+                return;
+            }
             game.update(arg.dt);
+            recorder.tick();
+            achievements.update(arg.dt, &game);
+            clip.update(arg.dt, &game);
+            if let Some(ghost) = ghost.as_mut() {
+                ghost.update();
+            }
+            if let Some(broadcaster) = broadcaster.as_mut() {
+                let _ = broadcaster.update(arg.dt, &game);
+            }
         });
+
+        game.publish_events(&mut [&mut achievements]);
+        unlock_achievements(&mut profiles, &achievements.take_newly_unlocked());
+
+        if game.is_game_over() && !was_game_over {
+            save_replay(recorder.replay(), &replay_path);
+            match save_if_best(recorder.replay(), Path::new(BEST_RUNS_DIR)) {
+                Ok(true) => println!("New personal best: {}", game.score()),
+                Ok(false) => {}
+                Err(e) => eprintln!("Could not save personal best: {}", e),
+            }
+            if let Some(daily) = daily.as_mut() {
+                finish_daily(daily, &game, recorder.replay());
+            }
+
+            profiles.current_mut().record_game(game.score());
+            save_profiles(&profiles);
+            if let Some(key) = &score_key {
+                save_score_record(&profiles.current().name, recorder.replay(), key);
+            }
+            let key = BoardKey::from_config(&config);
+            for (index, player) in game.players().iter().enumerate() {
+                if !matches!(player.controller(), Controller::Human(_))
+                    || !leaderboards.qualifies(&key, player.score())
+                {
+                    continue;
+                }
+                let name = if index == 0 {
+                    profiles.current().name.as_str()
+                } else {
+                    player.name()
+                };
+                name_entries.push_back(NameEntry::new(
+                    name,
+                    player.name(),
+                    player.score(),
+                    player.snake().length(),
+                    (recorder.replay().ticks - round_start) as f64 * config.tick_duration,
+                ));
+            }
+        }
+        if was_game_over && !game.is_game_over() {
+            round_start = recorder.replay().ticks;
+        }
+        was_game_over = game.is_game_over();
+    }
+
+    save_replay(recorder.replay(), &replay_path);
+    println!("Replay saved to {}", replay_path.display());
+
+    if let Some(daily) = daily.as_mut() {
+        if !game.is_game_over() && recorder.replay().ticks > 0 {
+            finish_daily(daily, &game, recorder.replay());
+        }
+    } else if saved.is_none() && !game.is_game_over() && recorder.replay().ticks > 0 {
+        let quit = SavedGame {
+            replay: recorder.replay().clone(),
+            game,
+        };
+        match quit.save(Path::new(SAVE_PATH)) {
+            Ok(()) => println!("Game saved to {}", SAVE_PATH),
+            Err(e) => eprintln!("Could not save game to {}: {}", SAVE_PATH, e),
+        }
+    }
+}
+
+/// Records today's first daily attempt, whether it ended or was abandoned.
+fn finish_daily(daily: &mut Daily, game: &Game, replay: &Replay) {
+    match daily.finish(game.score(), game.snake().length(), replay.duration()) {
+        Ok(true) => println!("Daily {} scored: {}", daily.date(), game.score()),
+        Ok(false) => {}
+        Err(e) => eprintln!("Could not save daily result: {}", e),
+    }
+}
+
+fn print_daily_history(path: &Path) {
+    let history = match DailyHistory::load(path) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("Could not read daily history {}: {}", path.display(), e);
+            return;
+        }
+    };
+    if history.results().is_empty() {
+        println!("No daily results yet");
+        return;
+    }
+
+    println!("Date        Score  Length  Time");
+    for result in history.results().iter().rev() {
+        println!(
+            "{}  {:>5}  {:>6}  {:>4.0}s",
+            result.date, result.score, result.length, result.duration
+        );
+    }
+}
+
+/// Writes a signed record of the run that a leaderboard server can verify.
+fn save_score_record(name: &str, replay: &Replay, key: &str) {
+    let record = ScoreRecord::from_replay(name, Date::today(), replay.clone());
+    let path = timestamped_path("scores", "record");
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, record.sign(key.as_bytes())));
+    match written {
+        Ok(()) => println!("Score record saved to {}", path.display()),
+        Err(e) => eprintln!("Could not save score record to {}: {}", path.display(), e),
+    }
+}
+
+/// Speed ramp settings from `--ramp-curve`, `--ramp-every` and
+/// `--min-period`, falling back to the defaults for anything missing.
+fn speed_ramp(
+    metric: RampMetric,
+    curve: Option<&String>,
+    per_level: Option<&String>,
+    min_period: Option<&String>,
+) -> SpeedRamp {
+    let defaults = SpeedRamp::new(metric);
+    SpeedRamp {
+        curve: curve
+            .and_then(|value| SpeedCurve::from_name(value))
+            .unwrap_or(defaults.curve),
+        per_level: per_level
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.per_level),
+        min_period: min_period
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.min_period),
+        ..defaults
+    }
+}
+
+/// Food settings from `--food` (the base count) and `--cells-per-food`,
+/// falling back to the defaults for anything missing.
+fn food_rules(base: Option<&String>, cells_per_food: Option<&String>) -> FoodRules {
+    let defaults = FoodRules::default();
+    FoodRules {
+        base: base
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.base),
+        cells_per_food: cells_per_food
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(defaults.cells_per_food),
+        ..defaults
+    }
+}
+
+/// Inventory settings from `--inventory-size` and a comma-separated
+/// `--storable` list, falling back to the defaults for anything missing.
+fn inventory_rules(size: Option<&String>, storable: Option<&String>) -> InventoryRules {
+    let defaults = InventoryRules::default();
+    let capacity = size
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(defaults.capacity)
+        .min(9);
+    let Some(names) = storable else {
+        return defaults.with_capacity(capacity);
+    };
+    let types: Vec<PowerUpType> = names
+        .split(',')
+        .filter_map(|name| {
+            let powerup_type = PowerUpType::from_name(name.trim());
+            if powerup_type.is_none() {
+                eprintln!("Unknown power-up {}", name);
+            }
+            powerup_type
+        })
+        .collect();
+    InventoryRules::new(capacity, &types)
+}
+
+/// Stores newly unlocked achievements in the current profile.
+fn unlock_achievements(profiles: &mut Profiles, earned: &[Achievement]) {
+    let mut changed = false;
+    for achievement in earned {
+        changed |= profiles.current_mut().unlock(achievement.id());
+    }
+    if changed {
+        save_profiles(profiles);
+    }
+}
+
+fn save_profiles(profiles: &Profiles) {
+    if let Err(e) = profiles.save(Path::new(PROFILES_PATH)) {
+        eprintln!("Could not save profiles: {}", e);
+    }
+}
+
+fn load_saved_game(path: &Path) -> Option<SavedGame> {
+    match SavedGame::load(path) {
+        Ok(saved) => Some(saved),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            eprintln!(
+                "Ignoring saved game {}: {}. Starting a new game.",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+fn remove_saved_game(path: &Path) {
+    if let Err(e) = SavedGame::remove(path) {
+        eprintln!("Could not remove saved game {}: {}", path.display(), e);
+    }
+}
+
+fn timestamped_path(dir: &str, extension: &str) -> PathBuf {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    PathBuf::from(dir).join(format!("{}.{}", started, extension))
+}
+
+fn save_clip(clip: &ClipBuffer, format: ClipFormat) {
+    let frames = clip.frames();
+    let path = timestamped_path("clips", format.extension());
+    thread::spawn(
+        move || match export_clip(&frames, BACKGROUND_COLOR, format, &path) {
+            Ok(()) => println!("Clip saved to {}", path.display()),
+            Err(e) => eprintln!("Could not save clip to {}: {}", path.display(), e),
+        },
+    );
+}
+
+fn export_replay_clip(path: &Path, output: &Path, seconds: f64) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Could not load replay {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let mut game = replay.config.new_game();
+    let mut clip = ClipBuffer::new(seconds);
+    for tick in 0..replay.ticks {
+        replay.simulate_tick(&mut game, tick);
+        clip.update(replay.config.tick_duration, &game);
+    }
+
+    let format = ClipFormat::from_path(output);
+    match export_clip(&clip.frames(), BACKGROUND_COLOR, format, output) {
+        Ok(()) => println!("Clip saved to {}", output.display()),
+        Err(e) => eprintln!("Could not save clip to {}: {}", output.display(), e),
+    }
+}
+
+fn save_replay(replay: &Replay, path: &Path) {
+    if let Err(e) = replay.save(path) {
+        eprintln!("Could not save replay to {}: {}", path.display(), e);
+    }
+}
+
+fn run_replay(path: &Path, clip_seconds: f64, clip_format: ClipFormat) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Could not load replay {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let mut window = create_window(replay.config.width, replay.config.height);
+    let mut glyphs = load_glyphs(&mut window);
+    let mut player = ReplayPlayer::new(replay);
+    let mut clip = ClipBuffer::new(clip_seconds);
+
+    while let Some(event) = window.next() {
+        if let Some(Button::Keyboard(key)) = event.press_args() {
+            if key == Key::F12 {
+                save_clip(&clip, clip_format);
+            } else {
+                player.key_pressed(key);
+            }
+        }
+
+        window.draw_2d(&event, |c, g, device| {
+            clear(BACKGROUND_COLOR, g);
+            player.draw(&c, g, &mut glyphs);
+            glyphs.factory.encoder.flush(device);
+        });
+
+        event.update(|arg| {
+            player.update(arg.dt);
+            clip.update(arg.dt, player.game());
+        });
+    }
+}
+
+fn run_online(address: &str, name: &str) {
+    let mut client = match NetClient::connect(address, name) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Could not join {}: {}", address, e);
+            return;
+        }
+    };
+
+    let mut window = create_window(client.width(), client.height());
+    let mut glyphs = load_glyphs(&mut window);
+
+    while let Some(event) = window.next() {
+        if let Some(Button::Keyboard(key)) = event.press_args() {
+            if let Some(dir) = KeyScheme::Arrows.direction(key) {
+                let _ = client.send_direction(dir);
+            }
+        }
+
+        window.draw_2d(&event, |c, g, device| {
+            clear(BACKGROUND_COLOR, g);
+            client.draw(&c, g, &mut glyphs);
+            glyphs.factory.encoder.flush(device);
+        });
+
+        event.update(|_| {
+            if client.poll().is_err() && client.reconnect().is_err() {
+                eprintln!("Lost connection to {}", address);
+            }
+        });
+    }
+}
+
+fn run_lockstep(config: LockstepConfig, mut broadcaster: Option<Broadcaster>) {
+    let mut session = match Lockstep::connect(config.clone()) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Could not connect to peers: {}", e);
+            return;
+        }
+    };
+
+    let mut window = create_window(config.width, config.height);
+    let mut glyphs = load_glyphs(&mut window);
+    let mut reported = false;
+
+    while let Some(event) = window.next() {
+        if let Some(Button::Keyboard(key)) = event.press_args() {
+            if let Some(dir) = KeyScheme::Arrows.direction(key) {
+                session.set_direction(dir);
+            }
+        }
+
+        window.draw_2d(&event, |c, g, device| {
+            clear(BACKGROUND_COLOR, g);
+            session.game().draw(&c, g, &mut glyphs);
+            glyphs.factory.encoder.flush(device);
+        });
+
+        event.update(|arg| {
+            if let Err(e) = session.update(arg.dt) {
+                eprintln!("Lockstep session failed: {}", e);
+            }
+            if let Some(broadcaster) = broadcaster.as_mut() {
+                let _ = broadcaster.update(arg.dt, session.game());
+            }
+        });
+
+        if let (false, Some(desync)) = (reported, session.desync()) {
+            reported = true;
+            eprintln!(
+                "Desync with peer {} at tick {}: {} differs",
+                desync.peer + 1,
+                desync.tick,
+                desync.field
+            );
+        }
+    }
+}
+
+fn run_spectator(address: &str, delay: f64) {
+    let mut spectator = match Spectator::connect(address, delay) {
+        Ok(spectator) => spectator,
+        Err(e) => {
+            eprintln!("Could not watch {}: {}", address, e);
+            return;
+        }
+    };
+
+    let mut window = create_window(spectator.width(), spectator.height());
+    let mut glyphs = load_glyphs(&mut window);
+
+    while let Some(event) = window.next() {
+        if let Some(Button::Keyboard(key)) = event.press_args() {
+            spectator.key_pressed(key);
+        }
+
+        window.draw_2d(&event, |c, g, device| {
+            clear(BACKGROUND_COLOR, g);
+            spectator.draw(&c, g, &mut glyphs);
+            glyphs.factory.encoder.flush(device);
+        });
+
+        let mut ended = false;
+        event.update(|arg| ended = spectator.update(arg.dt).is_err());
+        if ended {
+            eprintln!("Broadcast from {} ended", address);
+            break;
+        }
+    }
+}
+
+fn create_window(width: i32, height: i32) -> PistonWindow {
+    let mut window_settings = WindowSettings::new(
+        "🐍 Rust Snake Game",
+        [to_gui_coord_u32(width), to_gui_coord_u32(height)],
+    )
+    .exit_on_esc(true);
+
+    window_settings.set_vsync(true);
+
+    window_settings.build().unwrap()
+}
+
+fn load_glyphs(window: &mut PistonWindow) -> Glyphs {
+    let font_path = std::path::Path::new("assets/FiraSans-Regular.ttf");
+    let fallback_font = if cfg!(target_os = "windows") {
+        std::path::Path::new("C:\\Windows\\Fonts\\arial.ttf")
+    } else if cfg!(target_os = "macos") {
+        std::path::Path::new("/System/Library/Fonts/Arial.ttf")
+    } else {
+        std::path::Path::new("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf")
+    };
+
+    if font_path.exists() {
+        window.load_font(font_path).unwrap()
+    } else {
+        window
+            .load_font(fallback_font)
+            .expect("Could not load any font")
     }
 }
@@ -0,0 +1,877 @@
+use std::io::{self, Read, Write};
+
+use piston_window::types::Color;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::food::{Food, FoodRules, FoodType};
+use crate::game::{
+    CollisionRules, Difficulty, Game, GameMode, HeadOnRule, RampMetric, SpeedCurve, SpeedRamp,
+};
+use crate::player::{Controller, KeyScheme};
+use crate::powerup::{InventoryRules, PowerUpType};
+use crate::snake::Direction;
+
+pub const PROTOCOL_VERSION: u16 = 7;
+pub const DEFAULT_PORT: u16 = 7878;
+
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    Hello {
+        version: u16,
+        name: String,
+        resume_token: Option<u64>,
+    },
+    Input {
+        tick: u32,
+        direction: Direction,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        player: u8,
+        token: u64,
+        tick: u32,
+        input_delay: u32,
+        width: i32,
+        height: i32,
+    },
+    Rejected(String),
+    Snapshot(NetState),
+    Delta(NetDelta),
+    Spectate {
+        width: i32,
+        height: i32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetFood {
+    pub x: i32,
+    pub y: i32,
+    pub food_type: FoodType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetPowerUp {
+    pub x: i32,
+    pub y: i32,
+    pub powerup_type: PowerUpType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetPlayer {
+    pub name: String,
+    pub alive: bool,
+    pub connected: bool,
+    pub score: u32,
+    pub direction: Direction,
+    pub effects: Vec<(PowerUpType, f32)>,
+    pub body: Vec<(i32, i32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetState {
+    pub tick: u32,
+    pub started: bool,
+    pub game_over: bool,
+    pub round: u32,
+    pub foods: Vec<NetFood>,
+    pub powerups: Vec<NetPowerUp>,
+    pub players: Vec<NetPlayer>,
+}
+
+/// Changes since the previous broadcast: only players whose state differs,
+/// and the powerup list only when it changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetDelta {
+    pub tick: u32,
+    pub started: bool,
+    pub game_over: bool,
+    pub round: u32,
+    pub foods: Option<Vec<NetFood>>,
+    pub powerups: Option<Vec<NetPowerUp>>,
+    pub players: Vec<(u8, NetPlayer)>,
+}
+
+impl NetState {
+    pub fn empty() -> NetState {
+        NetState {
+            tick: 0,
+            started: false,
+            game_over: false,
+            round: 1,
+            foods: Vec::new(),
+            powerups: Vec::new(),
+            players: Vec::new(),
+        }
+    }
+
+    pub fn from_game(game: &Game, tick: u32, started: bool, connected: &[bool]) -> NetState {
+        NetState {
+            tick,
+            started,
+            game_over: game.is_game_over(),
+            round: game.round(),
+            foods: game
+                .foods()
+                .iter()
+                .map(|food| NetFood {
+                    x: food.x,
+                    y: food.y,
+                    food_type: food.food_type,
+                })
+                .collect(),
+            powerups: game
+                .powerups()
+                .iter()
+                .map(|p| NetPowerUp {
+                    x: p.x,
+                    y: p.y,
+                    powerup_type: p.powerup_type,
+                })
+                .collect(),
+            players: game
+                .players()
+                .iter()
+                .enumerate()
+                .map(|(i, p)| NetPlayer {
+                    name: p.name().to_string(),
+                    alive: p.is_alive(),
+                    connected: connected.get(i).copied().unwrap_or(false),
+                    score: p.score(),
+                    direction: p.snake().head_direction(),
+                    effects: p
+                        .effects()
+                        .iter()
+                        .map(|e| (e.powerup_type, e.remaining.max(0.0) as f32))
+                        .collect(),
+                    body: p.snake().body_positions().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn diff(&self, newer: &NetState) -> NetDelta {
+        NetDelta {
+            tick: newer.tick,
+            started: newer.started,
+            game_over: newer.game_over,
+            round: newer.round,
+            foods: if self.foods == newer.foods {
+                None
+            } else {
+                Some(newer.foods.clone())
+            },
+            powerups: if self.powerups == newer.powerups {
+                None
+            } else {
+                Some(newer.powerups.clone())
+            },
+            players: newer
+                .players
+                .iter()
+                .enumerate()
+                .filter(|(i, p)| self.players.get(*i) != Some(*p))
+                .map(|(i, p)| (i as u8, p.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&mut self, delta: NetDelta) {
+        self.tick = delta.tick;
+        self.started = delta.started;
+        self.game_over = delta.game_over;
+        self.round = delta.round;
+        if let Some(foods) = delta.foods {
+            self.foods = foods;
+        }
+        if let Some(powerups) = delta.powerups {
+            self.powerups = powerups;
+        }
+        for (index, player) in delta.players {
+            let index = index as usize;
+            if index < self.players.len() {
+                self.players[index] = player;
+            } else {
+                self.players.push(player);
+            }
+        }
+    }
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        match self {
+            ClientMessage::Hello {
+                version,
+                name,
+                resume_token,
+            } => {
+                w.u8(0);
+                w.u16(*version);
+                w.string(name);
+                w.option(resume_token.as_ref(), |w, token| w.u64(*token));
+            }
+            ClientMessage::Input { tick, direction } => {
+                w.u8(1);
+                w.u32(*tick);
+                w.direction(*direction);
+            }
+        }
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<ClientMessage> {
+        let mut r = Reader::new(payload);
+        let message = match r.u8()? {
+            0 => ClientMessage::Hello {
+                version: r.u16()?,
+                name: r.string()?,
+                resume_token: r.option(|r| r.u64())?,
+            },
+            1 => ClientMessage::Input {
+                tick: r.u32()?,
+                direction: r.direction()?,
+            },
+            tag => return Err(invalid(format!("unknown client message {}", tag))),
+        };
+        Ok(message)
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        match self {
+            ServerMessage::Welcome {
+                player,
+                token,
+                tick,
+                input_delay,
+                width,
+                height,
+            } => {
+                w.u8(0);
+                w.u8(*player);
+                w.u64(*token);
+                w.u32(*tick);
+                w.u32(*input_delay);
+                w.i32(*width);
+                w.i32(*height);
+            }
+            ServerMessage::Rejected(reason) => {
+                w.u8(1);
+                w.string(reason);
+            }
+            ServerMessage::Snapshot(state) => {
+                w.u8(2);
+                w.u32(state.tick);
+                w.bool(state.started);
+                w.bool(state.game_over);
+                w.u32(state.round);
+                w.list(&state.foods, |w, food| w.net_food(food));
+                w.list(&state.powerups, |w, p| w.powerup(p));
+                w.list(&state.players, |w, p| w.player(p));
+            }
+            ServerMessage::Delta(delta) => {
+                w.u8(3);
+                w.u32(delta.tick);
+                w.bool(delta.started);
+                w.bool(delta.game_over);
+                w.u32(delta.round);
+                w.option(delta.foods.as_ref(), |w, foods| {
+                    w.list(foods, |w, food| w.net_food(food))
+                });
+                w.option(delta.powerups.as_ref(), |w, powerups| {
+                    w.list(powerups, |w, p| w.powerup(p))
+                });
+                w.list(&delta.players, |w, (index, p)| {
+                    w.u8(*index);
+                    w.player(p);
+                });
+            }
+            ServerMessage::Spectate { width, height } => {
+                w.u8(4);
+                w.i32(*width);
+                w.i32(*height);
+            }
+        }
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<ServerMessage> {
+        let mut r = Reader::new(payload);
+        let message = match r.u8()? {
+            0 => ServerMessage::Welcome {
+                player: r.u8()?,
+                token: r.u64()?,
+                tick: r.u32()?,
+                input_delay: r.u32()?,
+                width: r.i32()?,
+                height: r.i32()?,
+            },
+            1 => ServerMessage::Rejected(r.string()?),
+            2 => ServerMessage::Snapshot(NetState {
+                tick: r.u32()?,
+                started: r.bool()?,
+                game_over: r.bool()?,
+                round: r.u32()?,
+                foods: r.list(|r| r.net_food())?,
+                powerups: r.list(|r| r.powerup())?,
+                players: r.list(|r| r.player())?,
+            }),
+            3 => ServerMessage::Delta(NetDelta {
+                tick: r.u32()?,
+                started: r.bool()?,
+                game_over: r.bool()?,
+                round: r.u32()?,
+                foods: r.option(|r| r.list(|r| r.net_food()))?,
+                powerups: r.option(|r| r.list(|r| r.powerup()))?,
+                players: r.list(|r| Ok((r.u8()?, r.player()?)))?,
+            }),
+            4 => ServerMessage::Spectate {
+                width: r.i32()?,
+                height: r.i32()?,
+            },
+            tag => return Err(invalid(format!("unknown server message {}", tag))),
+        };
+        Ok(message)
+    }
+}
+
+/// A non-blocking, length-prefixed framed connection.
+pub struct Connection<S> {
+    stream: S,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn new(stream: S) -> Connection<S> {
+        Connection {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        }
+    }
+
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn send(&mut self, payload: &[u8]) {
+        self.outgoing
+            .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.outgoing.extend_from_slice(payload);
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads whatever is available and returns the complete frames received.
+    /// An orderly shutdown by the peer is reported as `UnexpectedEof` once
+    /// every frame sent before it has been returned.
+    pub fn receive(&mut self) -> io::Result<Vec<Vec<u8>>> {
+        let mut buffer = [0u8; 4096];
+        let mut closed = false;
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(n) => self.incoming.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut frames = Vec::new();
+        while self.incoming.len() >= 4 {
+            let mut len_bytes = [0u8; 4];
+            len_bytes.copy_from_slice(&self.incoming[..4]);
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            if len > MAX_FRAME_LEN {
+                return Err(invalid(format!("frame of {} bytes is too large", len)));
+            }
+            if self.incoming.len() < 4 + len {
+                break;
+            }
+            frames.push(self.incoming[4..4 + len].to_vec());
+            self.incoming.drain(..4 + len);
+        }
+
+        if closed && frames.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(frames)
+    }
+}
+
+pub(crate) fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(crate) struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new() -> Writer {
+        Writer { buf: Vec::new() }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub(crate) fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub(crate) fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    pub(crate) fn u16(&mut self, v: u16) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn i32(&mut self, v: i32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn f32(&mut self, v: f32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn string(&mut self, v: &str) {
+        self.u16(v.len() as u16);
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, v: &[u8]) {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v);
+    }
+
+    pub(crate) fn color(&mut self, v: Color) {
+        for channel in v {
+            self.f32(channel);
+        }
+    }
+
+    /// Writes the full generator state, so a restored generator continues
+    /// the same sequence.
+    pub(crate) fn rng(&mut self, v: &ChaCha12Rng) {
+        self.buf.extend_from_slice(&v.get_seed());
+        self.u64(v.get_stream());
+        let word_pos = v.get_word_pos();
+        self.u64(word_pos as u64);
+        self.u64((word_pos >> 64) as u64);
+    }
+
+    pub(crate) fn option<T>(&mut self, v: Option<&T>, write: impl FnOnce(&mut Writer, &T)) {
+        match v {
+            Some(value) => {
+                self.u8(1);
+                write(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    pub(crate) fn list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Writer, &T)) {
+        self.u32(items.len() as u32);
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    pub(crate) fn direction(&mut self, v: Direction) {
+        self.u8(match v {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        });
+    }
+
+    pub(crate) fn position(&mut self, (x, y): (i32, i32)) {
+        self.i32(x);
+        self.i32(y);
+    }
+
+    pub(crate) fn powerup_type(&mut self, v: PowerUpType) {
+        self.u8(v.id());
+    }
+
+    pub(crate) fn game_mode(&mut self, v: GameMode) {
+        match v {
+            GameMode::Single => self.u8(0),
+            GameMode::Versus => self.u8(1),
+            GameMode::TwoPlayer => self.u8(2),
+            GameMode::Online { players } => {
+                self.u8(3);
+                self.u8(players as u8);
+            }
+        }
+    }
+
+    pub(crate) fn difficulty(&mut self, v: Difficulty) {
+        self.u8(match v {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        });
+    }
+
+    pub(crate) fn speed_ramp(&mut self, v: Option<SpeedRamp>) {
+        self.option(v.as_ref(), |w, ramp| {
+            w.u8(match ramp.metric {
+                RampMetric::Score => 0,
+                RampMetric::Length => 1,
+            });
+            w.u8(match ramp.curve {
+                SpeedCurve::Linear => 0,
+                SpeedCurve::Exponential => 1,
+            });
+            w.u32(ramp.per_level);
+            w.f64(ramp.min_period);
+        });
+    }
+
+    pub(crate) fn controller(&mut self, v: Controller) {
+        self.u8(match v {
+            Controller::Human(KeyScheme::Arrows) => 0,
+            Controller::Human(KeyScheme::Wasd) => 1,
+            Controller::Ai => 2,
+            Controller::Remote => 3,
+        });
+    }
+
+    pub(crate) fn collision_rules(&mut self, v: CollisionRules) {
+        self.bool(v.snakes_collide);
+        self.u8(match v.head_on {
+            HeadOnRule::BothDie => 0,
+            HeadOnRule::ShorterDies => 1,
+        });
+    }
+
+    pub(crate) fn food_type(&mut self, v: FoodType) {
+        self.u8(v.id());
+    }
+
+    pub(crate) fn food(&mut self, f: &Food) {
+        self.position((f.x, f.y));
+        self.food_type(f.food_type);
+        self.f64(f.timer);
+        self.f64(f.flee_timer);
+    }
+
+    pub(crate) fn food_rules(&mut self, v: FoodRules) {
+        self.u32(v.base);
+        self.u32(v.cells_per_food);
+        self.u32(v.per_extra_player);
+    }
+
+    pub(crate) fn inventory_rules(&mut self, v: Option<InventoryRules>) {
+        self.option(v.as_ref(), |w, rules| {
+            w.u8(rules.capacity as u8);
+            w.u32(rules.storable_bits());
+        });
+    }
+
+    fn net_food(&mut self, f: &NetFood) {
+        self.position((f.x, f.y));
+        self.food_type(f.food_type);
+    }
+
+    fn powerup(&mut self, p: &NetPowerUp) {
+        self.position((p.x, p.y));
+        self.powerup_type(p.powerup_type);
+    }
+
+    fn player(&mut self, p: &NetPlayer) {
+        self.string(&p.name);
+        self.bool(p.alive);
+        self.bool(p.connected);
+        self.u32(p.score);
+        self.direction(p.direction);
+        self.list(&p.effects, |w, (powerup_type, remaining)| {
+            w.powerup_type(*powerup_type);
+            w.f32(*remaining);
+        });
+        self.list(&p.body, |w, pos| w.position(*pos));
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.buf.len() - self.pos < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn string(&mut self) -> io::Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub(crate) fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub(crate) fn color(&mut self) -> io::Result<Color> {
+        Ok([self.f32()?, self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    pub(crate) fn rng(&mut self) -> io::Result<ChaCha12Rng> {
+        let mut rng = ChaCha12Rng::from_seed(self.array()?);
+        rng.set_stream(self.u64()?);
+        let low = self.u64()? as u128;
+        let high = self.u64()? as u128;
+        rng.set_word_pos(high << 64 | low);
+        Ok(rng)
+    }
+
+    pub(crate) fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Reader<'a>) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+
+    pub(crate) fn list<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Reader<'a>) -> io::Result<T>,
+    ) -> io::Result<Vec<T>> {
+        let len = self.u32()? as usize;
+        if len > self.buf.len() - self.pos {
+            return Err(invalid(format!("list of {} items is too long", len)));
+        }
+        (0..len).map(|_| read(self)).collect()
+    }
+
+    pub(crate) fn direction(&mut self) -> io::Result<Direction> {
+        match self.u8()? {
+            0 => Ok(Direction::Up),
+            1 => Ok(Direction::Down),
+            2 => Ok(Direction::Left),
+            3 => Ok(Direction::Right),
+            v => Err(invalid(format!("invalid direction {}", v))),
+        }
+    }
+
+    pub(crate) fn position(&mut self) -> io::Result<(i32, i32)> {
+        Ok((self.i32()?, self.i32()?))
+    }
+
+    pub(crate) fn powerup_type(&mut self) -> io::Result<PowerUpType> {
+        let v = self.u8()?;
+        PowerUpType::from_id(v).ok_or_else(|| invalid(format!("invalid powerup type {}", v)))
+    }
+
+    pub(crate) fn game_mode(&mut self) -> io::Result<GameMode> {
+        match self.u8()? {
+            0 => Ok(GameMode::Single),
+            1 => Ok(GameMode::Versus),
+            2 => Ok(GameMode::TwoPlayer),
+            3 => Ok(GameMode::Online {
+                players: self.u8()? as usize,
+            }),
+            v => Err(invalid(format!("invalid game mode {}", v))),
+        }
+    }
+
+    pub(crate) fn difficulty(&mut self) -> io::Result<Difficulty> {
+        match self.u8()? {
+            0 => Ok(Difficulty::Easy),
+            1 => Ok(Difficulty::Normal),
+            2 => Ok(Difficulty::Hard),
+            v => Err(invalid(format!("invalid difficulty {}", v))),
+        }
+    }
+
+    pub(crate) fn speed_ramp(&mut self) -> io::Result<Option<SpeedRamp>> {
+        self.option(|r| {
+            let metric = match r.u8()? {
+                0 => RampMetric::Score,
+                1 => RampMetric::Length,
+                v => return Err(invalid(format!("invalid ramp metric {}", v))),
+            };
+            let curve = match r.u8()? {
+                0 => SpeedCurve::Linear,
+                1 => SpeedCurve::Exponential,
+                v => return Err(invalid(format!("invalid speed curve {}", v))),
+            };
+            Ok(SpeedRamp {
+                metric,
+                curve,
+                per_level: r.u32()?,
+                min_period: r.f64()?,
+            })
+        })
+    }
+
+    pub(crate) fn controller(&mut self) -> io::Result<Controller> {
+        match self.u8()? {
+            0 => Ok(Controller::Human(KeyScheme::Arrows)),
+            1 => Ok(Controller::Human(KeyScheme::Wasd)),
+            2 => Ok(Controller::Ai),
+            3 => Ok(Controller::Remote),
+            v => Err(invalid(format!("invalid controller {}", v))),
+        }
+    }
+
+    pub(crate) fn collision_rules(&mut self) -> io::Result<CollisionRules> {
+        Ok(CollisionRules {
+            snakes_collide: self.bool()?,
+            head_on: match self.u8()? {
+                0 => HeadOnRule::BothDie,
+                1 => HeadOnRule::ShorterDies,
+                v => return Err(invalid(format!("invalid head-on rule {}", v))),
+            },
+        })
+    }
+
+    pub(crate) fn food_type(&mut self) -> io::Result<FoodType> {
+        let v = self.u8()?;
+        FoodType::from_id(v).ok_or_else(|| invalid(format!("invalid food type {}", v)))
+    }
+
+    pub(crate) fn food(&mut self) -> io::Result<Food> {
+        let (x, y) = self.position()?;
+        let mut food = Food::new(x, y, self.food_type()?);
+        food.timer = self.f64()?;
+        food.flee_timer = self.f64()?;
+        Ok(food)
+    }
+
+    pub(crate) fn food_rules(&mut self) -> io::Result<FoodRules> {
+        Ok(FoodRules {
+            base: self.u32()?,
+            cells_per_food: self.u32()?,
+            per_extra_player: self.u32()?,
+        })
+    }
+
+    pub(crate) fn inventory_rules(&mut self) -> io::Result<Option<InventoryRules>> {
+        self.option(|r| Ok(InventoryRules::from_bits(r.u8()? as usize, r.u32()?)))
+    }
+
+    fn net_food(&mut self) -> io::Result<NetFood> {
+        let (x, y) = self.position()?;
+        Ok(NetFood {
+            x,
+            y,
+            food_type: self.food_type()?,
+        })
+    }
+
+    fn powerup(&mut self) -> io::Result<NetPowerUp> {
+        let (x, y) = self.position()?;
+        Ok(NetPowerUp {
+            x,
+            y,
+            powerup_type: self.powerup_type()?,
+        })
+    }
+
+    fn player(&mut self) -> io::Result<NetPlayer> {
+        Ok(NetPlayer {
+            name: self.string()?,
+            alive: self.bool()?,
+            connected: self.bool()?,
+            score: self.u32()?,
+            direction: self.direction()?,
+            effects: self.list(|r| Ok((r.powerup_type()?, r.f32()?)))?,
+            body: self.list(|r| r.position())?,
+        })
+    }
+}
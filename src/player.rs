@@ -0,0 +1,461 @@
+use std::io;
+
+use piston_window::types::Color;
+use piston_window::Key;
+
+use crate::desync::StateHashes;
+use crate::food::FoodType;
+use crate::game::{Difficulty, SpeedRamp, COMBO_WINDOW, LEVEL_UP_FLASH, MOVING_PERIOD};
+use crate::net::{Reader, Writer};
+use crate::powerup::{ActiveEffect, PowerUpType, Stacking};
+use crate::snake::{Direction, Snake};
+
+pub const MAX_PLAYERS: usize = 8;
+
+pub const PLAYER_COLORS: [(Color, Color); MAX_PLAYERS] = [
+    ([0.30, 0.80, 0.30, 1.0], [0.20, 0.70, 0.20, 1.0]),
+    ([0.30, 0.55, 0.90, 1.0], [0.20, 0.45, 0.80, 1.0]),
+    ([0.90, 0.50, 0.20, 1.0], [0.80, 0.40, 0.10, 1.0]),
+    ([0.85, 0.35, 0.75, 1.0], [0.75, 0.25, 0.65, 1.0]),
+    ([0.90, 0.85, 0.30, 1.0], [0.80, 0.75, 0.20, 1.0]),
+    ([0.30, 0.85, 0.80, 1.0], [0.20, 0.75, 0.70, 1.0]),
+    ([0.90, 0.90, 0.90, 1.0], [0.75, 0.75, 0.75, 1.0]),
+    ([0.60, 0.45, 0.30, 1.0], [0.50, 0.35, 0.20, 1.0]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyScheme {
+    Arrows,
+    Wasd,
+}
+
+impl KeyScheme {
+    /// Inventory slot triggered by `key`: 1-9 for the arrow keys player, and
+    /// Q, E, R for the WASD player.
+    pub fn inventory_slot(&self, key: Key) -> Option<usize> {
+        let slots: &[Key] = match *self {
+            KeyScheme::Arrows => &[
+                Key::D1,
+                Key::D2,
+                Key::D3,
+                Key::D4,
+                Key::D5,
+                Key::D6,
+                Key::D7,
+                Key::D8,
+                Key::D9,
+            ],
+            KeyScheme::Wasd => &[Key::Q, Key::E, Key::R],
+        };
+        slots.iter().position(|k| *k == key)
+    }
+
+    /// Name of the key that triggers inventory `slot`, for the HUD.
+    pub fn inventory_key_label(&self, slot: usize) -> Option<&'static str> {
+        let labels: &[&str] = match *self {
+            KeyScheme::Arrows => &["1", "2", "3", "4", "5", "6", "7", "8", "9"],
+            KeyScheme::Wasd => &["Q", "E", "R"],
+        };
+        labels.get(slot).copied()
+    }
+
+    pub fn direction(&self, key: Key) -> Option<Direction> {
+        match (*self, key) {
+            (KeyScheme::Arrows, Key::Up) | (KeyScheme::Wasd, Key::W) => Some(Direction::Up),
+            (KeyScheme::Arrows, Key::Down) | (KeyScheme::Wasd, Key::S) => Some(Direction::Down),
+            (KeyScheme::Arrows, Key::Left) | (KeyScheme::Wasd, Key::A) => Some(Direction::Left),
+            (KeyScheme::Arrows, Key::Right) | (KeyScheme::Wasd, Key::D) => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Controller {
+    Human(KeyScheme),
+    Ai,
+    Remote,
+}
+
+#[derive(Clone)]
+pub struct Player {
+    name: String,
+    snake: Snake,
+    controller: Controller,
+    difficulty: Difficulty,
+    speed_ramp: Option<SpeedRamp>,
+    alive: bool,
+    waiting_time: f64,
+    queued_direction: Option<Direction>,
+    score: u32,
+
+    effects: Vec<ActiveEffect>,
+    inventory: Vec<PowerUpType>,
+
+    combo_count: u32,
+    combo_timer: f64,
+    level_up_timer: f64,
+}
+
+impl Player {
+    pub fn new(name: &str, snake: Snake, controller: Controller) -> Player {
+        Player {
+            name: name.to_string(),
+            snake,
+            controller,
+            difficulty: Difficulty::Normal,
+            speed_ramp: None,
+            alive: true,
+            waiting_time: 0.0,
+            queued_direction: None,
+            score: 0,
+            effects: Vec::new(),
+            inventory: Vec::new(),
+            combo_count: 0,
+            combo_timer: 0.0,
+            level_up_timer: 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    pub fn controller(&self) -> Controller {
+        self.controller
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn effects(&self) -> &[ActiveEffect] {
+        &self.effects
+    }
+
+    /// Power-ups held for later, in slot order.
+    pub fn inventory(&self) -> &[PowerUpType] {
+        &self.inventory
+    }
+
+    pub fn has_effect(&self, powerup_type: PowerUpType) -> bool {
+        self.effect_timer(powerup_type).is_some()
+    }
+
+    /// Time left on `powerup_type`'s effect, if it is active.
+    pub fn effect_timer(&self, powerup_type: PowerUpType) -> Option<f64> {
+        self.effects
+            .iter()
+            .find(|e| e.powerup_type == powerup_type)
+            .map(|e| e.remaining)
+    }
+
+    pub fn is_shielded(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|e| e.powerup_type.effect().protects())
+    }
+
+    pub fn passes_own_body(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|e| e.powerup_type.effect().passes_own_body())
+    }
+
+    /// Visible radius around the head, smallest of its effects', if any
+    /// effect fogs the board.
+    pub fn fog_radius(&self) -> Option<i32> {
+        self.effects
+            .iter()
+            .filter_map(|e| e.powerup_type.effect().fog_radius())
+            .min()
+    }
+
+    /// Opacity to draw the snake with, lowest of its effects'.
+    pub fn snake_alpha(&self) -> f32 {
+        self.effects.iter().fold(1.0, |alpha: f32, e| {
+            alpha.min(e.powerup_type.effect().snake_alpha())
+        })
+    }
+
+    pub fn combo_count(&self) -> u32 {
+        self.combo_count
+    }
+
+    /// Speed level reached, starting from 0; always 0 without a speed ramp.
+    pub fn speed_level(&self) -> u32 {
+        self.speed_ramp
+            .map_or(0, |ramp| ramp.level(self.score, self.snake.length()))
+    }
+
+    /// Seconds left of the "level up" flash.
+    pub fn level_up_timer(&self) -> f64 {
+        self.level_up_timer
+    }
+
+    /// Board tint while the "level up" flash fades.
+    pub fn level_up_flash(&self) -> Option<Color> {
+        if self.level_up_timer > 0.0 {
+            Some([
+                1.0,
+                1.0,
+                1.0,
+                (self.level_up_timer / LEVEL_UP_FLASH * 0.15) as f32,
+            ])
+        } else {
+            None
+        }
+    }
+
+    pub fn movement_period(&self) -> f64 {
+        let base = self.speed_ramp.map_or(MOVING_PERIOD, |ramp| {
+            ramp.period(MOVING_PERIOD, self.speed_level())
+        });
+        let scale: f64 = self
+            .effects
+            .iter()
+            .map(|e| e.powerup_type.effect().period_scale())
+            .product();
+        base * scale * self.difficulty.period_scale()
+    }
+
+    pub(crate) fn snake_mut(&mut self) -> &mut Snake {
+        &mut self.snake
+    }
+
+    pub(crate) fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+    }
+
+    pub(crate) fn set_speed_ramp(&mut self, ramp: Option<SpeedRamp>) {
+        self.speed_ramp = ramp;
+    }
+
+    pub(crate) fn level_up(&mut self) {
+        self.level_up_timer = LEVEL_UP_FLASH;
+    }
+
+    /// Advances the player's clocks and returns the effects that ran out.
+    pub(crate) fn update(&mut self, delta_time: f64) -> Vec<PowerUpType> {
+        self.snake.update_tongue(delta_time);
+        let expired = self.update_timers(delta_time);
+        self.waiting_time += delta_time;
+        expired
+    }
+
+    pub(crate) fn update_timers(&mut self, delta_time: f64) -> Vec<PowerUpType> {
+        let mut expired = Vec::new();
+        self.effects.retain_mut(|effect| {
+            effect.remaining -= delta_time;
+            if effect.remaining <= 0.0 {
+                expired.push(effect.powerup_type);
+            }
+            effect.remaining > 0.0
+        });
+
+        if self.combo_timer > 0.0 {
+            self.combo_timer -= delta_time;
+            if self.combo_timer <= 0.0 {
+                self.combo_count = 0;
+            }
+        }
+        self.level_up_timer = (self.level_up_timer - delta_time).max(0.0);
+        expired
+    }
+
+    pub(crate) fn is_ready_to_move(&self) -> bool {
+        self.waiting_time > self.movement_period()
+    }
+
+    pub(crate) fn queue_direction(&mut self, dir: Direction) {
+        self.queued_direction = Some(dir);
+    }
+
+    pub(crate) fn take_queued_direction(&mut self) -> Option<Direction> {
+        self.queued_direction
+            .take()
+            .map(|d| self.steer(d))
+            .filter(|d| *d != self.snake.head_direction().opposite())
+    }
+
+    /// The direction the snake actually takes when steered towards `dir`.
+    pub(crate) fn steer(&self, dir: Direction) -> Direction {
+        self.effects
+            .iter()
+            .fold(dir, |dir, e| e.powerup_type.effect().steer(dir))
+    }
+
+    pub(crate) fn lose_score(&mut self, points: u32) {
+        self.score = self.score.saturating_sub(points);
+    }
+
+    pub(crate) fn move_forward(&mut self, dir: Option<Direction>) {
+        self.snake.move_forward(dir);
+        self.waiting_time = 0.0;
+    }
+
+    pub(crate) fn eat_food(&mut self, food_type: FoodType) {
+        self.snake.grow(food_type.growth());
+        if food_type.is_rotten() {
+            self.combo_count = 0;
+            self.combo_timer = 0.0;
+            return;
+        }
+
+        self.combo_count += 1;
+        self.combo_timer = COMBO_WINDOW;
+
+        let combo_bonus = if self.combo_count > 1 {
+            self.combo_count * 2
+        } else {
+            0
+        };
+
+        let multiplier: u32 = self
+            .effects
+            .iter()
+            .map(|e| e.powerup_type.effect().score_multiplier(e.stacks))
+            .product();
+        self.score += (food_type.score() + combo_bonus) * multiplier;
+    }
+
+    pub(crate) fn collect_powerup(&mut self, powerup_type: PowerUpType) {
+        self.apply_effect(powerup_type);
+        self.score += powerup_type.effect().pickup_score();
+    }
+
+    pub(crate) fn store_powerup(&mut self, powerup_type: PowerUpType) {
+        self.inventory.push(powerup_type);
+        self.score += powerup_type.effect().pickup_score();
+    }
+
+    pub(crate) fn take_stored(&mut self, slot: usize) -> Option<PowerUpType> {
+        if slot < self.inventory.len() {
+            Some(self.inventory.remove(slot))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn apply_effect(&mut self, powerup_type: PowerUpType) {
+        let effect = powerup_type.effect();
+        match self
+            .effects
+            .iter_mut()
+            .find(|e| e.powerup_type == powerup_type)
+        {
+            Some(active) => match effect.stacking() {
+                Stacking::Refresh => active.remaining = effect.duration(),
+                Stacking::Extend => active.remaining += effect.duration(),
+                Stacking::Stack { max } => {
+                    active.stacks = (active.stacks + 1).min(max);
+                    active.remaining = effect.duration();
+                }
+            },
+            None => self.effects.push(ActiveEffect {
+                powerup_type,
+                remaining: effect.duration(),
+                stacks: 1,
+            }),
+        }
+    }
+
+    pub(crate) fn kill(&mut self) {
+        self.alive = false;
+        self.clear_effects();
+    }
+
+    pub(crate) fn clear_effects(&mut self) {
+        self.waiting_time = 0.0;
+        self.effects.clear();
+    }
+
+    pub(crate) fn hash_state(&self, prefix: &str, hashes: &mut StateHashes) {
+        hashes.field(format!("{}.name", prefix), |w| w.string(&self.name));
+        hashes.field(format!("{}.controller", prefix), |w| {
+            w.controller(self.controller)
+        });
+        hashes.field(format!("{}.alive", prefix), |w| w.bool(self.alive));
+        hashes.field(format!("{}.waiting_time", prefix), |w| {
+            w.f64(self.waiting_time)
+        });
+        hashes.field(format!("{}.queued_direction", prefix), |w| {
+            w.option(self.queued_direction.as_ref(), |w, d| w.direction(*d))
+        });
+        hashes.field(format!("{}.score", prefix), |w| w.u32(self.score));
+        hashes.field(format!("{}.effects", prefix), |w| {
+            write_effects(w, &self.effects)
+        });
+        hashes.field(format!("{}.inventory", prefix), |w| {
+            w.list(&self.inventory, |w, t| w.powerup_type(*t))
+        });
+        hashes.field(format!("{}.combo", prefix), |w| {
+            w.u32(self.combo_count);
+            w.f64(self.combo_timer);
+        });
+        hashes.field(format!("{}.level_up", prefix), |w| {
+            w.f64(self.level_up_timer)
+        });
+        self.snake.hash_state(&format!("{}.snake", prefix), hashes);
+    }
+
+    pub(crate) fn write_state(&self, w: &mut Writer) {
+        w.string(&self.name);
+        w.controller(self.controller);
+        w.bool(self.alive);
+        w.f64(self.waiting_time);
+        w.option(self.queued_direction.as_ref(), |w, d| w.direction(*d));
+        w.u32(self.score);
+        write_effects(w, &self.effects);
+        w.list(&self.inventory, |w, t| w.powerup_type(*t));
+        w.u32(self.combo_count);
+        w.f64(self.combo_timer);
+        w.f64(self.level_up_timer);
+        self.snake.write_state(w);
+    }
+
+    pub(crate) fn read_state(r: &mut Reader) -> io::Result<Player> {
+        Ok(Player {
+            name: r.string()?,
+            controller: r.controller()?,
+            difficulty: Difficulty::Normal,
+            speed_ramp: None,
+            alive: r.bool()?,
+            waiting_time: r.f64()?,
+            queued_direction: r.option(|r| r.direction())?,
+            score: r.u32()?,
+            effects: r.list(|r| {
+                Ok(ActiveEffect {
+                    powerup_type: r.powerup_type()?,
+                    remaining: r.f64()?,
+                    stacks: r.u32()?,
+                })
+            })?,
+            inventory: r.list(|r| r.powerup_type())?,
+            combo_count: r.u32()?,
+            combo_timer: r.f64()?,
+            level_up_timer: r.f64()?,
+            snake: Snake::read_state(r)?,
+        })
+    }
+}
+
+fn write_effects(w: &mut Writer, effects: &[ActiveEffect]) {
+    w.list(effects, |w, effect| {
+        w.powerup_type(effect.powerup_type);
+        w.f64(effect.remaining);
+        w.u32(effect.stacks);
+    });
+}
@@ -0,0 +1,609 @@
+use piston_window::types::Color;
+
+use crate::snake::Direction;
+
+use crate::game::{
+    Game, POWERUP_SCORE, SHIELD_DURATION, SHIELD_EFFECT_COLOR, SHIELD_POWERUP_COLOR,
+    SPEED_BOOST_DURATION, SPEED_BOOST_PERIOD_SCALE, SPEED_POWERUP_COLOR,
+};
+
+const SLOW_MO_COLOR: Color = [0.4, 1.0, 0.6, 1.0];
+const SLOW_MO_TINT: Color = [0.5, 0.5, 0.5, 0.25];
+const SLOW_MO_DURATION: f64 = 5.0;
+const SLOW_MO_PERIOD_SCALE: f64 = 1.6;
+const GHOST_COLOR: Color = [0.85, 0.85, 1.0, 1.0];
+const GHOST_DURATION: f64 = 6.0;
+const GHOST_ALPHA: f32 = 0.45;
+const MAGNET_COLOR: Color = [1.0, 0.3, 0.3, 1.0];
+const MAGNET_DURATION: f64 = 8.0;
+const MAGNET_RADIUS: i32 = 5;
+const MAGNET_PULL_PERIOD: f64 = 0.25;
+const SHRINK_COLOR: Color = [0.95, 0.95, 0.4, 1.0];
+const SHRINK_SEGMENTS: usize = 3;
+const MULTIPLIER_COLOR: Color = [1.0, 0.4, 0.8, 1.0];
+const MULTIPLIER_DURATION: f64 = 10.0;
+const MULTIPLIER_MAX_STACKS: u32 = 2;
+const REVERSE_COLOR: Color = [0.55, 0.2, 0.65, 1.0];
+const REVERSE_DURATION: f64 = 5.0;
+const POISON_COLOR: Color = [0.45, 0.6, 0.1, 1.0];
+const POISON_SCORE_PENALTY: u32 = 30;
+const POISON_SEGMENTS: usize = 2;
+const FOG_COLOR: Color = [0.4, 0.4, 0.45, 1.0];
+const FOG_DURATION: f64 = 6.0;
+const FOG_RADIUS: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerUpType {
+    Speed,
+    Shield,
+    SlowMo,
+    Ghost,
+    Magnet,
+    Shrink,
+    Multiplier,
+    Reverse,
+    Poison,
+    Fog,
+}
+
+impl PowerUpType {
+    /// Every power-up, in wire order: the index is what gets serialised.
+    pub const ALL: [PowerUpType; 10] = [
+        PowerUpType::Speed,
+        PowerUpType::Shield,
+        PowerUpType::SlowMo,
+        PowerUpType::Ghost,
+        PowerUpType::Magnet,
+        PowerUpType::Shrink,
+        PowerUpType::Multiplier,
+        PowerUpType::Reverse,
+        PowerUpType::Poison,
+        PowerUpType::Fog,
+    ];
+
+    pub fn effect(self) -> &'static dyn PowerUpEffect {
+        match self {
+            PowerUpType::Speed => &SpeedBoost,
+            PowerUpType::Shield => &Shield,
+            PowerUpType::SlowMo => &SlowMo,
+            PowerUpType::Ghost => &Ghost,
+            PowerUpType::Magnet => &Magnet,
+            PowerUpType::Shrink => &Shrink,
+            PowerUpType::Multiplier => &Multiplier,
+            PowerUpType::Reverse => &Reverse,
+            PowerUpType::Poison => &Poison,
+            PowerUpType::Fog => &Fog,
+        }
+    }
+
+    pub(crate) fn id(self) -> u8 {
+        PowerUpType::ALL.iter().position(|t| *t == self).unwrap() as u8
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<PowerUpType> {
+        PowerUpType::ALL.get(id as usize).copied()
+    }
+
+    /// Name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpType::Speed => "speed",
+            PowerUpType::Shield => "shield",
+            PowerUpType::SlowMo => "slow-mo",
+            PowerUpType::Ghost => "ghost",
+            PowerUpType::Magnet => "magnet",
+            PowerUpType::Shrink => "shrink",
+            PowerUpType::Multiplier => "multiplier",
+            PowerUpType::Reverse => "reverse",
+            PowerUpType::Poison => "poison",
+            PowerUpType::Fog => "fog",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PowerUpType> {
+        PowerUpType::ALL.iter().copied().find(|t| t.name() == name)
+    }
+}
+
+/// Lets players hold power-ups and trigger them later instead of on pickup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InventoryRules {
+    pub capacity: usize,
+    storable: u32,
+}
+
+impl InventoryRules {
+    pub fn new(capacity: usize, storable: &[PowerUpType]) -> InventoryRules {
+        InventoryRules {
+            capacity,
+            storable: storable.iter().fold(0, |bits, t| bits | 1 << t.id()),
+        }
+    }
+
+    pub fn with_capacity(self, capacity: usize) -> InventoryRules {
+        InventoryRules { capacity, ..self }
+    }
+
+    /// Whether `powerup_type` goes into the inventory rather than taking
+    /// effect at once.
+    pub fn stores(&self, powerup_type: PowerUpType) -> bool {
+        self.storable & 1 << powerup_type.id() != 0
+    }
+
+    pub(crate) fn storable_bits(&self) -> u32 {
+        self.storable
+    }
+
+    pub(crate) fn from_bits(capacity: usize, storable: u32) -> InventoryRules {
+        InventoryRules { capacity, storable }
+    }
+}
+
+impl Default for InventoryRules {
+    /// Three slots holding any power-up that is not cursed.
+    fn default() -> InventoryRules {
+        let storable: Vec<PowerUpType> = PowerUpType::ALL
+            .iter()
+            .copied()
+            .filter(|t| !t.effect().is_cursed())
+            .collect();
+        InventoryRules::new(3, &storable)
+    }
+}
+
+/// What happens when a power-up is picked up while its effect is still
+/// active.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stacking {
+    /// Restart the timer at the full duration.
+    Refresh,
+    /// Add the full duration to the time left.
+    Extend,
+    /// Raise the stack count up to `max` and restart the timer.
+    Stack { max: u32 },
+}
+
+/// The behaviour of one kind of power-up. Hooks get the whole game so an
+/// effect can reach beyond the snake that collected it.
+pub trait PowerUpEffect: Sync {
+    /// Name shown in the HUD status list.
+    fn label(&self) -> &'static str;
+    fn color(&self) -> Color;
+    /// Letter drawn on the pickup.
+    fn icon(&self) -> &'static str;
+    fn duration(&self) -> f64;
+
+    fn stacking(&self) -> Stacking {
+        Stacking::Refresh
+    }
+
+    /// Relative chance of this power-up being the one that spawns.
+    fn spawn_weight(&self) -> f64 {
+        1.0
+    }
+
+    /// Cursed pickups harm the player and are drawn with a warning.
+    fn is_cursed(&self) -> bool {
+        false
+    }
+
+    /// Points for picking it up.
+    fn pickup_score(&self) -> u32 {
+        if self.is_cursed() {
+            0
+        } else {
+            POWERUP_SCORE
+        }
+    }
+
+    fn on_pickup(&self, _game: &mut Game, _player: usize) {}
+    fn on_tick(&self, _game: &mut Game, _player: usize, _delta_time: f64) {}
+    fn on_expire(&self, _game: &mut Game, _player: usize) {}
+
+    /// Multiplies the player's movement period while the effect is active.
+    fn period_scale(&self) -> f64 {
+        1.0
+    }
+
+    /// Whether the effect lets the snake survive a crash.
+    fn protects(&self) -> bool {
+        false
+    }
+
+    /// Whether the snake can move through its own body.
+    fn passes_own_body(&self) -> bool {
+        false
+    }
+
+    /// Opacity the snake is drawn with while the effect is active.
+    fn snake_alpha(&self) -> f32 {
+        1.0
+    }
+
+    /// Colour laid over the board while the first player has the effect,
+    /// given the time left.
+    fn board_tint(&self, _remaining: f64) -> Option<Color> {
+        None
+    }
+
+    /// Factor applied to food score, on top of the combo bonus.
+    fn score_multiplier(&self, _stacks: u32) -> u32 {
+        1
+    }
+
+    /// Maps the direction the player steers in to the one the snake takes.
+    fn steer(&self, direction: Direction) -> Direction {
+        direction
+    }
+
+    /// How many cells around the head stay visible to the first player, if
+    /// the effect hides the rest of the board.
+    fn fog_radius(&self) -> Option<i32> {
+        None
+    }
+
+    /// Line shown in the HUD status list.
+    fn status(&self, active: &ActiveEffect) -> String {
+        format!("{}: {:.1}s", self.label(), active.remaining)
+    }
+}
+
+/// An effect running on a player, with the time it has left and how many
+/// times it has stacked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveEffect {
+    pub powerup_type: PowerUpType,
+    pub remaining: f64,
+    pub stacks: u32,
+}
+
+struct SpeedBoost;
+
+impl PowerUpEffect for SpeedBoost {
+    fn label(&self) -> &'static str {
+        "SPEED BOOST"
+    }
+
+    fn color(&self) -> Color {
+        SPEED_POWERUP_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        ">"
+    }
+
+    fn duration(&self) -> f64 {
+        SPEED_BOOST_DURATION
+    }
+
+    fn period_scale(&self) -> f64 {
+        SPEED_BOOST_PERIOD_SCALE
+    }
+}
+
+struct Shield;
+
+impl PowerUpEffect for Shield {
+    fn label(&self) -> &'static str {
+        "SHIELD"
+    }
+
+    fn color(&self) -> Color {
+        SHIELD_POWERUP_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "O"
+    }
+
+    fn duration(&self) -> f64 {
+        SHIELD_DURATION
+    }
+
+    fn protects(&self) -> bool {
+        true
+    }
+
+    fn board_tint(&self, remaining: f64) -> Option<Color> {
+        let pulse = 0.7 + 0.3 * (remaining * 8.0).sin().abs();
+        let mut color = SHIELD_EFFECT_COLOR;
+        color[3] = pulse as f32 * 0.3;
+        Some(color)
+    }
+}
+
+struct SlowMo;
+
+impl PowerUpEffect for SlowMo {
+    fn label(&self) -> &'static str {
+        "SLOW-MO"
+    }
+
+    fn color(&self) -> Color {
+        SLOW_MO_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "<"
+    }
+
+    fn duration(&self) -> f64 {
+        SLOW_MO_DURATION
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.75
+    }
+
+    fn period_scale(&self) -> f64 {
+        SLOW_MO_PERIOD_SCALE
+    }
+
+    fn board_tint(&self, _remaining: f64) -> Option<Color> {
+        Some(SLOW_MO_TINT)
+    }
+}
+
+struct Ghost;
+
+impl PowerUpEffect for Ghost {
+    fn label(&self) -> &'static str {
+        "GHOST"
+    }
+
+    fn color(&self) -> Color {
+        GHOST_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "G"
+    }
+
+    fn duration(&self) -> f64 {
+        GHOST_DURATION
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.75
+    }
+
+    fn passes_own_body(&self) -> bool {
+        true
+    }
+
+    fn snake_alpha(&self) -> f32 {
+        GHOST_ALPHA
+    }
+}
+
+struct Magnet;
+
+impl PowerUpEffect for Magnet {
+    fn label(&self) -> &'static str {
+        "MAGNET"
+    }
+
+    fn color(&self) -> Color {
+        MAGNET_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "M"
+    }
+
+    fn duration(&self) -> f64 {
+        MAGNET_DURATION
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.6
+    }
+
+    /// Pulls the food one cell closer every `MAGNET_PULL_PERIOD`.
+    fn on_tick(&self, game: &mut Game, player: usize, delta_time: f64) {
+        let Some(remaining) = game.players()[player].effect_timer(PowerUpType::Magnet) else {
+            return;
+        };
+        let pulls_before = ((remaining + delta_time) / MAGNET_PULL_PERIOD).floor();
+        if (remaining / MAGNET_PULL_PERIOD).floor() < pulls_before {
+            game.pull_food(player, MAGNET_RADIUS);
+        }
+    }
+}
+
+/// Takes effect on pickup; the duration only keeps it in the HUD briefly.
+struct Shrink;
+
+impl PowerUpEffect for Shrink {
+    fn label(&self) -> &'static str {
+        "SHRINK"
+    }
+
+    fn color(&self) -> Color {
+        SHRINK_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "-"
+    }
+
+    fn duration(&self) -> f64 {
+        1.5
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.6
+    }
+
+    fn on_pickup(&self, game: &mut Game, player: usize) {
+        game.player_mut(player).snake_mut().shrink(SHRINK_SEGMENTS);
+    }
+
+    fn status(&self, _active: &ActiveEffect) -> String {
+        format!("SHRINK -{}", SHRINK_SEGMENTS)
+    }
+}
+
+struct Multiplier;
+
+impl PowerUpEffect for Multiplier {
+    fn label(&self) -> &'static str {
+        "MULTIPLIER"
+    }
+
+    fn color(&self) -> Color {
+        MULTIPLIER_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "x"
+    }
+
+    fn duration(&self) -> f64 {
+        MULTIPLIER_DURATION
+    }
+
+    fn stacking(&self) -> Stacking {
+        Stacking::Stack {
+            max: MULTIPLIER_MAX_STACKS,
+        }
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.5
+    }
+
+    /// x2 for the first pickup, x3 once stacked.
+    fn score_multiplier(&self, stacks: u32) -> u32 {
+        stacks + 1
+    }
+
+    fn status(&self, active: &ActiveEffect) -> String {
+        format!(
+            "SCORE x{}: {:.1}s",
+            self.score_multiplier(active.stacks),
+            active.remaining
+        )
+    }
+}
+
+struct Reverse;
+
+impl PowerUpEffect for Reverse {
+    fn label(&self) -> &'static str {
+        "REVERSED"
+    }
+
+    fn color(&self) -> Color {
+        REVERSE_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "R"
+    }
+
+    fn duration(&self) -> f64 {
+        REVERSE_DURATION
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.5
+    }
+
+    fn is_cursed(&self) -> bool {
+        true
+    }
+
+    fn steer(&self, direction: Direction) -> Direction {
+        direction.opposite()
+    }
+}
+
+/// Cursed pickup that costs points and tail segments as soon as it is
+/// eaten. The short duration only shows the penalty in the HUD.
+struct Poison;
+
+impl PowerUpEffect for Poison {
+    fn label(&self) -> &'static str {
+        "POISON"
+    }
+
+    fn color(&self) -> Color {
+        POISON_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "P"
+    }
+
+    fn duration(&self) -> f64 {
+        1.5
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.5
+    }
+
+    fn is_cursed(&self) -> bool {
+        true
+    }
+
+    fn on_pickup(&self, game: &mut Game, player: usize) {
+        let player = game.player_mut(player);
+        player.lose_score(POISON_SCORE_PENALTY);
+        player.snake_mut().shrink(POISON_SEGMENTS);
+    }
+
+    fn status(&self, _active: &ActiveEffect) -> String {
+        format!("POISONED -{}", POISON_SCORE_PENALTY)
+    }
+}
+
+struct Fog;
+
+impl PowerUpEffect for Fog {
+    fn label(&self) -> &'static str {
+        "FOG"
+    }
+
+    fn color(&self) -> Color {
+        FOG_COLOR
+    }
+
+    fn icon(&self) -> &'static str {
+        "F"
+    }
+
+    fn duration(&self) -> f64 {
+        FOG_DURATION
+    }
+
+    fn spawn_weight(&self) -> f64 {
+        0.5
+    }
+
+    fn is_cursed(&self) -> bool {
+        true
+    }
+
+    fn fog_radius(&self) -> Option<i32> {
+        Some(FOG_RADIUS)
+    }
+}
+
+/// Picks a power-up by spawn weight from a roll in `0.0..1.0`.
+pub(crate) fn choose(roll: f64) -> PowerUpType {
+    let total: f64 = PowerUpType::ALL
+        .iter()
+        .map(|t| t.effect().spawn_weight())
+        .sum();
+    let mut target = roll * total;
+    for powerup_type in PowerUpType::ALL {
+        let weight = powerup_type.effect().spawn_weight();
+        if target < weight {
+            return powerup_type;
+        }
+        target -= weight;
+    }
+    PowerUpType::ALL[PowerUpType::ALL.len() - 1]
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::net::{invalid, Reader, Writer};
+
+pub const PROFILES_PATH: &str = "profiles/profiles";
+pub const PROFILES_VERSION: u16 = 2;
+pub const DEFAULT_PROFILE: &str = "Player";
+
+const PROFILES_MAGIC: u32 = u32::from_le_bytes(*b"SNKP");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub games_played: u32,
+    pub best_score: u32,
+    pub achievements: Vec<String>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            games_played: 0,
+            best_score: 0,
+            achievements: Vec::new(),
+        }
+    }
+
+    pub fn record_game(&mut self, score: u32) {
+        self.games_played += 1;
+        self.best_score = self.best_score.max(score);
+    }
+
+    /// Remembers an unlocked achievement by id. Returns false if it already
+    /// was.
+    pub fn unlock(&mut self, id: &str) -> bool {
+        if self.achievements.iter().any(|a| a == id) {
+            return false;
+        }
+        self.achievements.push(id.to_string());
+        true
+    }
+}
+
+/// Named local players, remembering which one played last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    current: usize,
+}
+
+impl Default for Profiles {
+    fn default() -> Profiles {
+        Profiles {
+            profiles: vec![Profile::new(DEFAULT_PROFILE)],
+            current: 0,
+        }
+    }
+}
+
+impl Profiles {
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub fn current(&self) -> &Profile {
+        &self.profiles[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Profile {
+        &mut self.profiles[self.current]
+    }
+
+    /// Makes `name` the current profile, creating it if it is new.
+    pub fn select(&mut self, name: &str) -> &mut Profile {
+        self.current = match self.profiles.iter().position(|p| p.name == name) {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile::new(name));
+                self.profiles.len() - 1
+            }
+        };
+        self.current_mut()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.u32(PROFILES_MAGIC);
+        w.u16(PROFILES_VERSION);
+        w.u32(self.current as u32);
+        w.list(&self.profiles, |w, profile| {
+            w.string(&profile.name);
+            w.u32(profile.games_played);
+            w.u32(profile.best_score);
+            w.list(&profile.achievements, |w, id| w.string(id));
+        });
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Profiles> {
+        let mut r = Reader::new(payload);
+        if r.u32()? != PROFILES_MAGIC {
+            return Err(invalid("not a profiles file".to_string()));
+        }
+        let version = r.u16()?;
+        if !(1..=PROFILES_VERSION).contains(&version) {
+            return Err(invalid(format!(
+                "profiles version {} is not supported, expected 1 to {}",
+                version, PROFILES_VERSION
+            )));
+        }
+
+        let current = r.u32()? as usize;
+        let profiles = r.list(|r| {
+            Ok(Profile {
+                name: r.string()?,
+                games_played: r.u32()?,
+                best_score: r.u32()?,
+                achievements: if version >= 2 {
+                    r.list(|r| r.string())?
+                } else {
+                    Vec::new()
+                },
+            })
+        })?;
+        if current >= profiles.len() {
+            return Err(invalid(format!("invalid current profile {}", current)));
+        }
+        Ok(Profiles { profiles, current })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    /// Loads the profiles at `path`, or just the default one if there are
+    /// none yet.
+    pub fn load(path: &Path) -> io::Result<Profiles> {
+        match fs::read(path) {
+            Ok(payload) => Profiles::decode(&payload),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Profiles::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
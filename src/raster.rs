@@ -0,0 +1,289 @@
+use piston_window::types::Color;
+
+use crate::drawing::{to_gui_coord, BLOCK_SIZE};
+use crate::game::{
+    Game, BORDER_COLOR, CURSED_WARNING_COLOR, DEAD_SNAKE_ALPHA, FOG_COLOR, GAMEOVER_COLOR,
+};
+use crate::snake::{Direction, Snake};
+
+/// An RGBA8 image that the game can be drawn into without a GPU, using the
+/// same shapes as `drawing.rs`.
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Color) -> Canvas {
+        let pixel = to_rgba8(background);
+        Canvas {
+            width,
+            height,
+            pixels: pixel
+                .iter()
+                .copied()
+                .cycle()
+                .take((width * height * 4) as usize)
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    pub fn rectangle(&mut self, color: Color, [x, y, w, h]: [f64; 4]) {
+        let (x0, x1) = self.clamp_span(x, x + w, self.width);
+        let (y0, y1) = self.clamp_span(y, y + h, self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(px, py, color);
+            }
+        }
+    }
+
+    pub fn ellipse(&mut self, color: Color, [x, y, w, h]: [f64; 4]) {
+        let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+        let (rx, ry) = (w / 2.0, h / 2.0);
+        let (x0, x1) = self.clamp_span(x, x + w, self.width);
+        let (y0, y1) = self.clamp_span(y, y + h, self.height);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let dx = (px as f64 + 0.5 - cx) / rx;
+                let dy = (py as f64 + 0.5 - cy) / ry;
+                if dx * dx + dy * dy <= 1.0 {
+                    self.blend(px, py, color);
+                }
+            }
+        }
+    }
+
+    fn clamp_span(&self, start: f64, end: f64, limit: u32) -> (u32, u32) {
+        let start = start.round().clamp(0.0, limit as f64) as u32;
+        let end = end.round().clamp(0.0, limit as f64) as u32;
+        (start, end)
+    }
+
+    fn blend(&mut self, x: u32, y: u32, color: Color) {
+        let index = ((y * self.width + x) * 4) as usize;
+        let alpha = color[3].clamp(0.0, 1.0);
+        for (dst, src) in self.pixels[index..index + 3].iter_mut().zip(color) {
+            let blended = src.clamp(0.0, 1.0) * alpha + (*dst as f32 / 255.0) * (1.0 - alpha);
+            *dst = (blended * 255.0).round() as u8;
+        }
+        self.pixels[index + 3] = 255;
+    }
+}
+
+fn to_rgba8(color: Color) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+pub fn draw_block(canvas: &mut Canvas, color: Color, x: i32, y: i32) {
+    let gui_x = to_gui_coord(x);
+    let gui_y = to_gui_coord(y);
+
+    canvas.rectangle(
+        [color[0] * 0.7, color[1] * 0.7, color[2] * 0.7, color[3]],
+        [gui_x + 2.0, gui_y + 2.0, BLOCK_SIZE, BLOCK_SIZE],
+    );
+    canvas.rectangle(color, [gui_x, gui_y, BLOCK_SIZE, BLOCK_SIZE]);
+}
+
+pub fn draw_rectangle(canvas: &mut Canvas, color: Color, x: i32, y: i32, width: i32, height: i32) {
+    canvas.rectangle(
+        color,
+        [
+            to_gui_coord(x),
+            to_gui_coord(y),
+            BLOCK_SIZE * width as f64,
+            BLOCK_SIZE * height as f64,
+        ],
+    );
+}
+
+pub fn draw_eyes(canvas: &mut Canvas, direction: Direction, x: i32, y: i32) {
+    let gui_x = to_gui_coord(x);
+    let gui_y = to_gui_coord(y);
+
+    let (offset_x1, offset_y1, offset_x2, offset_y2) = match direction {
+        Direction::Up => (0.25, 0.25, 0.75, 0.25),
+        Direction::Down => (0.25, 0.75, 0.75, 0.75),
+        Direction::Left => (0.25, 0.25, 0.25, 0.75),
+        Direction::Right => (0.75, 0.25, 0.75, 0.75),
+    };
+
+    for &(offset_x, offset_y) in &[(offset_x1, offset_y1), (offset_x2, offset_y2)] {
+        let eye_x = gui_x + offset_x * BLOCK_SIZE;
+        let eye_y = gui_y + offset_y * BLOCK_SIZE;
+        canvas.ellipse([1.0, 1.0, 1.0, 1.0], [eye_x - 4.0, eye_y - 4.0, 8.0, 8.0]);
+        canvas.ellipse([0.1, 0.1, 0.1, 1.0], [eye_x - 2.0, eye_y - 2.0, 4.0, 4.0]);
+    }
+}
+
+pub fn draw_tongue(canvas: &mut Canvas, direction: Direction, x: i32, y: i32) {
+    let gui_x = to_gui_coord(x);
+    let gui_y = to_gui_coord(y);
+    let tongue_color = [0.86, 0.22, 0.27, 1.0];
+
+    let (start_x, start_y, end_x, end_y) = match direction {
+        Direction::Up => (0.5, 0.0, 0.5, -0.3),
+        Direction::Down => (0.5, 1.0, 0.5, 1.3),
+        Direction::Left => (0.0, 0.5, -0.3, 0.5),
+        Direction::Right => (1.0, 0.5, 1.3, 0.5),
+    };
+
+    canvas.rectangle(
+        tongue_color,
+        [
+            gui_x + start_x * BLOCK_SIZE - 2.0,
+            gui_y + start_y * BLOCK_SIZE - 2.0,
+            4.0,
+            BLOCK_SIZE * 0.3,
+        ],
+    );
+    canvas.ellipse(
+        tongue_color,
+        [
+            gui_x + end_x * BLOCK_SIZE - 3.0,
+            gui_y + end_y * BLOCK_SIZE - 3.0,
+            6.0,
+            6.0,
+        ],
+    );
+}
+
+pub fn draw_snake(canvas: &mut Canvas, snake: &Snake, alpha: f32) {
+    let (mut color, mut head_color) = snake.colors();
+    color[3] *= alpha;
+    head_color[3] *= alpha;
+
+    for (i, (x, y)) in snake.body_positions().enumerate() {
+        if i == 0 {
+            draw_block(canvas, head_color, x, y);
+            draw_eyes(canvas, snake.head_direction(), x, y);
+            if snake.is_tongue_out() && alpha >= 1.0 {
+                draw_tongue(canvas, snake.head_direction(), x, y);
+            }
+        } else {
+            draw_block(canvas, color, x, y);
+        }
+    }
+}
+
+/// Renders the board the way `Game::draw` does, minus text and screen shake.
+pub fn render_game(game: &Game, background: Color) -> Canvas {
+    let (width, height) = (game.width(), game.height());
+    let mut canvas = Canvas::new(
+        to_gui_coord(width) as u32,
+        to_gui_coord(height) as u32,
+        background,
+    );
+
+    let player = &game.players()[0];
+    for effect in player.effects() {
+        if let Some(tint) = effect.powerup_type.effect().board_tint(effect.remaining) {
+            draw_rectangle(&mut canvas, tint, 1, 1, width - 2, height - 2);
+        }
+    }
+    if let Some(flash) = player.level_up_flash() {
+        draw_rectangle(&mut canvas, flash, 1, 1, width - 2, height - 2);
+    }
+
+    for player in game.players() {
+        let alpha = if player.is_alive() {
+            player.snake_alpha()
+        } else {
+            DEAD_SNAKE_ALPHA
+        };
+        draw_snake(&mut canvas, player.snake(), alpha);
+    }
+
+    for food in game.foods() {
+        let mut food_color = food.food_type.color();
+        food_color[3] = food.alpha() as f32;
+
+        let size = BLOCK_SIZE * food.scale();
+        let offset = (BLOCK_SIZE - size) / 2.0;
+        canvas.rectangle(
+            food_color,
+            [
+                to_gui_coord(food.x) + offset,
+                to_gui_coord(food.y) + offset,
+                size,
+                size,
+            ],
+        );
+    }
+
+    for powerup in game.powerups() {
+        let effect = powerup.powerup_type.effect();
+        let pulse = 0.5 + 0.5 * (powerup.timer * 5.0).sin().abs();
+        let mut color = effect.color();
+        color[3] = pulse as f32;
+
+        if effect.is_cursed() {
+            let mut warning_color = CURSED_WARNING_COLOR;
+            warning_color[3] = (1.0 - pulse * 0.5) as f32;
+            draw_rectangle(&mut canvas, warning_color, powerup.x, powerup.y, 1, 1);
+        }
+
+        let size = BLOCK_SIZE * 0.8;
+        let offset = (BLOCK_SIZE - size) / 2.0;
+        canvas.rectangle(
+            color,
+            [
+                to_gui_coord(powerup.x) + offset,
+                to_gui_coord(powerup.y) + offset,
+                size,
+                size,
+            ],
+        );
+    }
+
+    for (x, y) in game.fogged_cells() {
+        draw_rectangle(&mut canvas, FOG_COLOR, x, y, 1, 1);
+    }
+
+    for i in 0..3 {
+        let alpha = 1.0 - (i as f32 * 0.2);
+        let border_color = [BORDER_COLOR[0], BORDER_COLOR[1], BORDER_COLOR[2], alpha];
+        draw_rectangle(&mut canvas, border_color, i, i, width - 2 * i, 1);
+        draw_rectangle(
+            &mut canvas,
+            border_color,
+            i,
+            height - 1 - i,
+            width - 2 * i,
+            1,
+        );
+        draw_rectangle(&mut canvas, border_color, i, i, 1, height - 2 * i);
+        draw_rectangle(
+            &mut canvas,
+            border_color,
+            width - 1 - i,
+            i,
+            1,
+            height - 2 * i,
+        );
+    }
+
+    if game.is_game_over() {
+        draw_rectangle(&mut canvas, GAMEOVER_COLOR, 0, 0, width, height);
+    }
+
+    canvas
+}
@@ -0,0 +1,419 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::drawing::{draw_rectangle, to_gui_coord};
+use crate::events::GameEvent;
+use crate::food::FoodRules;
+use crate::game::{CollisionRules, Difficulty, Game, GameMode, SpeedRamp};
+use crate::net::{invalid, Reader, Writer};
+use crate::player::MAX_PLAYERS;
+use crate::powerup::InventoryRules;
+
+/// Bumped whenever the layout or the simulation changes. A replay only plays
+/// back the same in the build that recorded it, so other versions are
+/// rejected rather than re-simulated differently.
+pub const REPLAY_VERSION: u16 = 6;
+pub const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+const REPLAY_MAGIC: u32 = u32::from_le_bytes(*b"SNKR");
+/// Smallest board with a free cell inside the walls.
+const MIN_BOARD_SIZE: i32 = 3;
+const MAX_BOARD_SIZE: i32 = 256;
+const MAX_BEST_OF: u32 = 99;
+/// Six hours at the default 120 updates per second, so checking a replay
+/// always finishes.
+const MAX_TICKS: u32 = 120 * 60 * 60 * 6;
+const SNAPSHOT_INTERVAL: u32 = 600;
+const SEEK_TICKS: u32 = 600;
+const PROGRESS_COLOR: Color = [0.96, 0.71, 0.20, 0.8];
+const OVERLAY_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+/// Everything needed to rebuild the starting state of a game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayConfig {
+    pub width: i32,
+    pub height: i32,
+    pub mode: GameMode,
+    pub rules: CollisionRules,
+    pub difficulty: Difficulty,
+    pub inventory: Option<InventoryRules>,
+    pub food: FoodRules,
+    pub speed_ramp: Option<SpeedRamp>,
+    pub best_of: u32,
+    pub seed: u64,
+    pub tick_duration: f64,
+}
+
+impl ReplayConfig {
+    pub fn new_game(&self) -> Game {
+        let mut game = Game::with_mode(self.width, self.height, self.mode);
+        game.set_collision_rules(self.rules);
+        game.set_difficulty(self.difficulty);
+        game.set_inventory_rules(self.inventory);
+        game.set_food_rules(self.food);
+        game.set_speed_ramp(self.speed_ramp);
+        game.set_best_of(self.best_of);
+        game.reset(self.seed);
+        game
+    }
+
+    /// Rejects settings a game cannot be built from, so a corrupt or
+    /// hand-made file fails to load instead of panicking later.
+    pub(crate) fn validate(&self) -> io::Result<()> {
+        let board = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+        if !board.contains(&self.width) || !board.contains(&self.height) {
+            return Err(invalid(format!(
+                "board of {}x{} is not between {} and {} cells across",
+                self.width, self.height, MIN_BOARD_SIZE, MAX_BOARD_SIZE
+            )));
+        }
+        if let GameMode::Online { players } = self.mode {
+            if !(2..=MAX_PLAYERS).contains(&players) {
+                return Err(invalid(format!("online game with {} players", players)));
+            }
+        }
+        if !(1..=MAX_BEST_OF).contains(&self.best_of) {
+            return Err(invalid(format!("best of {} rounds", self.best_of)));
+        }
+        if !(self.tick_duration.is_finite() && self.tick_duration > 0.0) {
+            return Err(invalid(format!("tick duration {}", self.tick_duration)));
+        }
+        Ok(())
+    }
+}
+
+/// A recorded game: its configuration plus every key press and the number
+/// of fixed-length ticks that preceded it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub config: ReplayConfig,
+    pub ticks: u32,
+    pub events: Vec<(u32, Key)>,
+}
+
+impl Replay {
+    pub fn new(config: ReplayConfig) -> Replay {
+        Replay {
+            config,
+            ticks: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.ticks as f64 * self.config.tick_duration
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.u32(REPLAY_MAGIC);
+        w.u16(REPLAY_VERSION);
+        w.i32(self.config.width);
+        w.i32(self.config.height);
+        w.game_mode(self.config.mode);
+        w.collision_rules(self.config.rules);
+        w.difficulty(self.config.difficulty);
+        w.inventory_rules(self.config.inventory);
+        w.food_rules(self.config.food);
+        w.speed_ramp(self.config.speed_ramp);
+        w.u32(self.config.best_of);
+        w.u64(self.config.seed);
+        w.f64(self.config.tick_duration);
+        w.u32(self.ticks);
+
+        let mut last_tick = 0;
+        w.list(&self.events, |w, (tick, key)| {
+            w.u32(tick - last_tick);
+            w.u32(u32::from(*key));
+            last_tick = *tick;
+        });
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<Replay> {
+        let mut r = Reader::new(payload);
+        if r.u32()? != REPLAY_MAGIC {
+            return Err(invalid("not a replay file".to_string()));
+        }
+        let version = r.u16()?;
+        if version != REPLAY_VERSION {
+            return Err(invalid(format!(
+                "replay version {} cannot be played back, expected {}",
+                version, REPLAY_VERSION
+            )));
+        }
+
+        let config = ReplayConfig {
+            width: r.i32()?,
+            height: r.i32()?,
+            mode: r.game_mode()?,
+            rules: r.collision_rules()?,
+            difficulty: r.difficulty()?,
+            inventory: r.inventory_rules()?,
+            food: r.food_rules()?,
+            speed_ramp: r.speed_ramp()?,
+            best_of: r.u32()?,
+            seed: r.u64()?,
+            tick_duration: r.f64()?,
+        };
+        config.validate()?;
+        let ticks = r.u32()?;
+        if ticks > MAX_TICKS {
+            return Err(invalid(format!(
+                "replay of {} ticks is longer than the limit of {}",
+                ticks, MAX_TICKS
+            )));
+        }
+
+        let mut tick = 0;
+        let events = r.list(|r| {
+            tick += r.u32()?;
+            Ok((tick, Key::from(r.u32()?)))
+        })?;
+
+        Ok(Replay {
+            config,
+            ticks,
+            events,
+        })
+    }
+
+    /// Applies the key presses recorded at `tick`, then advances `game` by
+    /// one tick. Returns the events raised on the way.
+    pub fn simulate_tick(&self, game: &mut Game, tick: u32) -> Vec<GameEvent> {
+        let start = self.events.partition_point(|(t, _)| *t < tick);
+        for (_, key) in self.events[start..].iter().take_while(|(t, _)| *t == tick) {
+            game.key_pressed(*key);
+        }
+        game.update(self.config.tick_duration);
+        game.take_events()
+    }
+
+    /// Simulates the whole recording. The result is the game that was
+    /// running when it stopped, which is the one that just ended when the
+    /// recording was saved at a game over.
+    pub fn play_out(&self) -> Game {
+        let mut game = self.config.new_game();
+        for tick in 0..self.ticks {
+            self.simulate_tick(&mut game, tick);
+        }
+        game
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        Replay::decode(&fs::read(path)?)
+    }
+}
+
+/// Records a live game. Call `key_pressed` for every key passed to the game
+/// and `tick` after every `Game::update`.
+pub struct Recorder {
+    replay: Replay,
+}
+
+impl Recorder {
+    pub fn new(config: ReplayConfig) -> Recorder {
+        Recorder {
+            replay: Replay::new(config),
+        }
+    }
+
+    /// Carries on recording after the ticks already in `replay`.
+    pub fn resume(replay: Replay) -> Recorder {
+        Recorder { replay }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn key_pressed(&mut self, key: Key) {
+        self.replay.events.push((self.replay.ticks, key));
+    }
+
+    pub fn tick(&mut self) {
+        self.replay.ticks += 1;
+    }
+}
+
+/// Re-simulates a replay with pause, variable speed, frame stepping and
+/// seeking. Seeking restores the nearest earlier snapshot and simulates
+/// forward from there.
+pub struct ReplayPlayer {
+    replay: Replay,
+    game: Game,
+    tick: u32,
+    snapshots: Vec<Game>,
+    playing: bool,
+    speed_index: usize,
+    elapsed: f64,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        let game = replay.config.new_game();
+        let mut player = ReplayPlayer {
+            replay,
+            game,
+            tick: 0,
+            snapshots: Vec::new(),
+            playing: true,
+            speed_index: 2,
+            elapsed: 0.0,
+        };
+
+        while player.tick < player.replay.ticks {
+            if player.tick.is_multiple_of(SNAPSHOT_INTERVAL) {
+                player.snapshots.push(player.game.clone());
+            }
+            player.advance();
+        }
+        player.seek(0);
+        player
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed_index]
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed_index = (self.speed_index + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed_index = self.speed_index.saturating_sub(1);
+    }
+
+    pub fn step_forward(&mut self) {
+        self.playing = false;
+        if self.tick < self.replay.ticks {
+            self.advance();
+        }
+    }
+
+    pub fn step_backward(&mut self) {
+        self.playing = false;
+        self.seek(self.tick.saturating_sub(1));
+    }
+
+    pub fn seek(&mut self, tick: u32) {
+        let tick = tick.min(self.replay.ticks);
+        let index =
+            ((tick / SNAPSHOT_INTERVAL) as usize).min(self.snapshots.len().saturating_sub(1));
+        match self.snapshots.get(index) {
+            Some(snapshot) => {
+                self.game = snapshot.clone();
+                self.tick = index as u32 * SNAPSHOT_INTERVAL;
+            }
+            None => {
+                self.game = self.replay.config.new_game();
+                self.tick = 0;
+            }
+        }
+
+        while self.tick < tick {
+            self.advance();
+        }
+        self.elapsed = 0.0;
+    }
+
+    pub fn key_pressed(&mut self, key: Key) {
+        match key {
+            Key::Space => self.toggle_pause(),
+            Key::Up => self.faster(),
+            Key::Down => self.slower(),
+            Key::Period => self.step_forward(),
+            Key::Comma => self.step_backward(),
+            Key::Right => self.seek(self.tick + SEEK_TICKS),
+            Key::Left => self.seek(self.tick.saturating_sub(SEEK_TICKS)),
+            Key::Home => self.seek(0),
+            Key::End => self.seek(self.replay.ticks),
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        if !self.playing {
+            return;
+        }
+
+        self.elapsed += delta_time * self.speed();
+        while self.elapsed >= self.replay.config.tick_duration && self.tick < self.replay.ticks {
+            self.elapsed -= self.replay.config.tick_duration;
+            self.advance();
+        }
+
+        if self.tick >= self.replay.ticks {
+            self.playing = false;
+        }
+    }
+
+    fn advance(&mut self) {
+        self.replay.simulate_tick(&mut self.game, self.tick);
+        self.tick += 1;
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        self.game.draw(con, g, glyphs);
+
+        let config = &self.replay.config;
+        let progress = if self.replay.ticks > 0 {
+            self.tick as f64 / self.replay.ticks as f64
+        } else {
+            1.0
+        };
+        let bar_width = ((config.width - 2) as f64 * progress).round() as i32;
+        draw_rectangle(PROGRESS_COLOR, 1, config.height - 1, bar_width, 1, con, g);
+
+        let status = format!(
+            "{} {}x  {:.1}s / {:.1}s",
+            if self.playing { "Playing" } else { "Paused" },
+            self.speed(),
+            self.tick as f64 * config.tick_duration,
+            self.replay.duration()
+        );
+        text::Text::new_color(OVERLAY_COLOR, 16)
+            .draw(
+                &status,
+                glyphs,
+                &con.draw_state,
+                con.transform.trans(
+                    to_gui_coord(config.width) / 2.0 - 90.0,
+                    to_gui_coord(config.height) - 35.0,
+                ),
+                g,
+            )
+            .unwrap();
+    }
+}
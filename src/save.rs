@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use piston_window::types::Color;
+use piston_window::*;
+
+use crate::drawing::{draw_rectangle, to_gui_coord};
+use crate::game::Game;
+use crate::net::{invalid, Reader, Writer};
+use crate::replay::Replay;
+
+pub const SAVE_VERSION: u16 = 10;
+pub const SAVE_PATH: &str = "saves/game.save";
+
+const SAVE_MAGIC: u32 = u32::from_le_bytes(*b"SNKS");
+const PROMPT_BACKGROUND: Color = [0.0, 0.0, 0.0, 0.7];
+const PROMPT_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+/// A game quit part-way through: its full state plus the recording so far,
+/// so both the game and its replay carry on after resuming.
+#[derive(Clone)]
+pub struct SavedGame {
+    pub replay: Replay,
+    pub game: Game,
+}
+
+impl SavedGame {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.u32(SAVE_MAGIC);
+        w.u16(SAVE_VERSION);
+        w.bytes(&self.replay.encode());
+        self.game.write_state(&mut w);
+        w.finish()
+    }
+
+    pub fn decode(payload: &[u8]) -> io::Result<SavedGame> {
+        let mut r = Reader::new(payload);
+        if r.u32()? != SAVE_MAGIC {
+            return Err(invalid("not a saved game".to_string()));
+        }
+        let version = r.u16()?;
+        if version != SAVE_VERSION {
+            return Err(invalid(format!(
+                "saved game version {} is not supported, expected {}",
+                version, SAVE_VERSION
+            )));
+        }
+
+        Ok(SavedGame {
+            replay: Replay::decode(r.bytes()?)?,
+            game: Game::read_state(&mut r)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    pub fn load(path: &Path) -> io::Result<SavedGame> {
+        SavedGame::decode(&fs::read(path)?)
+    }
+
+    /// Removes a saved game, treating a missing file as already removed.
+    pub fn remove(path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Offers to continue this game over whatever is drawn underneath.
+    pub fn draw_prompt(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let (width, height) = (self.game.width(), self.game.height());
+        draw_rectangle(PROMPT_BACKGROUND, 0, 0, width, height, con, g);
+
+        let center_x = to_gui_coord(width) / 2.0;
+        let center_y = to_gui_coord(height) / 2.0;
+        let lines = [
+            (36, "SAVED GAME".to_string(), -90.0, -40.0),
+            (
+                20,
+                format!("Score: {}  Round: {}", self.game.score(), self.game.round()),
+                -80.0,
+                0.0,
+            ),
+            (20, "C - Continue".to_string(), -60.0, 40.0),
+            (20, "N - New game".to_string(), -60.0, 70.0),
+        ];
+        for (size, line, offset_x, offset_y) in &lines {
+            text::Text::new_color(PROMPT_COLOR, *size)
+                .draw(
+                    line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform
+                        .trans(center_x + offset_x, center_y + offset_y),
+                    g,
+                )
+                .unwrap();
+        }
+    }
+}
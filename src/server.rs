@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::game::{Game, GameMode};
+use crate::net::{ClientMessage, Connection, NetState, ServerMessage, PROTOCOL_VERSION};
+use crate::player::MAX_PLAYERS;
+use crate::snake::Direction;
+use crate::spectate::Broadcaster;
+
+/// Ticks beyond the input delay a client may schedule an input for; later
+/// inputs are dropped so a client cannot fill the queue.
+const MAX_INPUT_LEAD: u32 = 40;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub address: String,
+    pub players: usize,
+    pub width: i32,
+    pub height: i32,
+    pub tick_rate: f64,
+    pub input_delay: u32,
+    pub snapshot_interval: u32,
+    pub restart_delay: u32,
+    pub seed: Option<u64>,
+    /// Address on which spectators can watch the match.
+    pub broadcast: Option<String>,
+    /// How long a new connection has to send its hello before it is dropped.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            address: format!("127.0.0.1:{}", crate::net::DEFAULT_PORT),
+            players: 2,
+            width: 27,
+            height: 22,
+            tick_rate: 20.0,
+            input_delay: 2,
+            snapshot_interval: 20,
+            restart_delay: 60,
+            seed: None,
+            broadcast: None,
+            handshake_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+struct Slot {
+    token: u64,
+    name: String,
+    connection: Option<Connection<TcpStream>>,
+}
+
+/// Runs the authoritative simulation. Clients join until every slot is
+/// taken, then the match starts; a dropped client keeps its slot and can
+/// resume it with the token it was given in `Welcome`. A token is only
+/// accepted once the slot's previous connection has gone.
+pub struct Server {
+    config: ServerConfig,
+    listener: TcpListener,
+    game: Game,
+    tick: u32,
+    started: bool,
+    game_over_ticks: u32,
+    slots: Vec<Slot>,
+    pending: Vec<(Connection<TcpStream>, Instant)>,
+    inputs: BTreeMap<u32, Vec<(usize, Direction)>>,
+    last_state: NetState,
+    broadcaster: Option<Broadcaster>,
+}
+
+impl Server {
+    pub fn bind(config: ServerConfig) -> io::Result<Server> {
+        let listener = TcpListener::bind(&config.address)?;
+        listener.set_nonblocking(true)?;
+
+        let players = config.players.clamp(2, MAX_PLAYERS);
+        let mut game = Game::with_mode(config.width, config.height, GameMode::Online { players });
+        if let Some(seed) = config.seed {
+            game.reset(seed);
+        }
+
+        let broadcaster = match &config.broadcast {
+            Some(address) => Some(Broadcaster::bind(address, config.width, config.height)?),
+            None => None,
+        };
+
+        Ok(Server {
+            config,
+            listener,
+            game,
+            tick: 0,
+            started: false,
+            game_over_ticks: 0,
+            slots: Vec::new(),
+            pending: Vec::new(),
+            inputs: BTreeMap::new(),
+            last_state: NetState::empty(),
+            broadcaster,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Inputs received for ticks not simulated yet.
+    pub fn queued_inputs(&self) -> usize {
+        self.inputs.values().map(Vec::len).sum()
+    }
+
+    /// Runs in real time until `max_ticks` simulation ticks have elapsed,
+    /// or forever when no limit is given.
+    pub fn run(&mut self, max_ticks: Option<u32>) -> io::Result<()> {
+        let period = Duration::from_secs_f64(1.0 / self.config.tick_rate);
+        let mut next_tick = Instant::now();
+        while max_ticks.is_none_or(|max| self.tick < max) {
+            self.poll()?;
+            next_tick += period;
+            let now = Instant::now();
+            if next_tick > now {
+                thread::sleep(next_tick - now);
+            } else {
+                next_tick = now;
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts and reads clients, advances the simulation by one tick once
+    /// the match has started, and broadcasts the result.
+    pub fn poll(&mut self) -> io::Result<()> {
+        self.accept_connections()?;
+        self.read_pending();
+        self.read_players();
+
+        if self.started {
+            self.simulate_tick();
+        }
+
+        self.broadcast()
+    }
+
+    fn accept_connections(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.pending.push((Connection::new(stream), Instant::now()));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn read_pending(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        for (mut connection, accepted) in pending {
+            let hello = match connection.receive() {
+                Ok(frames) => frames.first().map(|frame| ClientMessage::decode(frame)),
+                Err(_) => continue,
+            };
+
+            match hello {
+                None if accepted.elapsed() < self.config.handshake_timeout => {
+                    self.pending.push((connection, accepted))
+                }
+                None => {
+                    let message = ServerMessage::Rejected("no hello received".to_string());
+                    connection.send(&message.encode());
+                    let _ = connection.flush();
+                }
+                Some(Ok(ClientMessage::Hello {
+                    version,
+                    name,
+                    resume_token,
+                })) => self.join(connection, version, &name, resume_token),
+                Some(_) => {
+                    let message = ServerMessage::Rejected("expected hello".to_string());
+                    connection.send(&message.encode());
+                    let _ = connection.flush();
+                }
+            }
+        }
+    }
+
+    fn join(
+        &mut self,
+        mut connection: Connection<TcpStream>,
+        version: u16,
+        name: &str,
+        resume_token: Option<u64>,
+    ) {
+        let rejection = if version != PROTOCOL_VERSION {
+            Some(format!(
+                "protocol version {} is not supported, server speaks {}",
+                version, PROTOCOL_VERSION
+            ))
+        } else {
+            None
+        };
+
+        let slot_index = match (&rejection, resume_token) {
+            (Some(_), _) => None,
+            (None, Some(token)) => self
+                .slots
+                .iter()
+                .position(|s| s.token == token && s.connection.is_none()),
+            (None, None) if self.slots.len() < self.game.players().len() => {
+                self.slots.push(Slot {
+                    token: rand::rng().random(),
+                    name: name.to_string(),
+                    connection: None,
+                });
+                Some(self.slots.len() - 1)
+            }
+            (None, None) => None,
+        };
+
+        let Some(index) = slot_index else {
+            let reason = rejection.unwrap_or_else(|| match resume_token {
+                Some(token) if self.slots.iter().any(|s| s.token == token) => {
+                    "that player is still connected".to_string()
+                }
+                Some(_) => "unknown resume token".to_string(),
+                None => "server is full".to_string(),
+            });
+            connection.send(&ServerMessage::Rejected(reason).encode());
+            let _ = connection.flush();
+            return;
+        };
+
+        let welcome = ServerMessage::Welcome {
+            player: index as u8,
+            token: self.slots[index].token,
+            tick: self.tick,
+            input_delay: self.config.input_delay,
+            width: self.config.width,
+            height: self.config.height,
+        };
+        connection.send(&welcome.encode());
+
+        self.game.set_player_name(index, &self.slots[index].name);
+        let snapshot = ServerMessage::Snapshot(self.last_state.clone());
+        connection.send(&snapshot.encode());
+        self.slots[index].connection = Some(connection);
+
+        if self.slots.len() == self.game.players().len() {
+            self.started = true;
+        }
+    }
+
+    fn read_players(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let Some(connection) = slot.connection.as_mut() else {
+                continue;
+            };
+
+            let frames = match connection.receive() {
+                Ok(frames) => frames,
+                Err(_) => {
+                    slot.connection = None;
+                    continue;
+                }
+            };
+
+            for frame in frames {
+                if let Ok(ClientMessage::Input { tick, direction }) = ClientMessage::decode(&frame)
+                {
+                    let tick = tick.max(self.tick);
+                    if tick > self.tick + self.config.input_delay + MAX_INPUT_LEAD {
+                        continue;
+                    }
+                    // Only a player's last input for a tick would take effect.
+                    let inputs = self.inputs.entry(tick).or_default();
+                    inputs.retain(|(player, _)| *player != index);
+                    inputs.push((index, direction));
+                }
+            }
+        }
+    }
+
+    fn simulate_tick(&mut self) {
+        if let Some(inputs) = self.inputs.remove(&self.tick) {
+            for (index, direction) in inputs {
+                self.game.queue_direction(index, direction);
+            }
+        }
+
+        self.game.update(1.0 / self.config.tick_rate);
+        self.game.take_events();
+        self.tick += 1;
+
+        if self.game.is_game_over() {
+            self.game_over_ticks += 1;
+            if self.game_over_ticks >= self.config.restart_delay {
+                self.game_over_ticks = 0;
+                self.game.next_round();
+            }
+        }
+    }
+
+    fn current_state(&self) -> NetState {
+        let connected: Vec<bool> = (0..self.game.players().len())
+            .map(|i| self.slots.get(i).is_some_and(|s| s.connection.is_some()))
+            .collect();
+        NetState::from_game(&self.game, self.tick, self.started, &connected)
+    }
+
+    fn broadcast(&mut self) -> io::Result<()> {
+        let state = self.current_state();
+        if let Some(broadcaster) = self.broadcaster.as_mut() {
+            broadcaster.publish(state.clone())?;
+        }
+
+        let message = if self
+            .tick
+            .is_multiple_of(self.config.snapshot_interval.max(1))
+        {
+            ServerMessage::Snapshot(state.clone())
+        } else {
+            ServerMessage::Delta(self.last_state.diff(&state))
+        };
+        let payload = message.encode();
+        self.last_state = state;
+
+        for slot in self.slots.iter_mut() {
+            if let Some(connection) = slot.connection.as_mut() {
+                connection.send(&payload);
+                if connection.flush().is_err() {
+                    slot.connection = None;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,22 @@
 use std::collections::LinkedList;
+use std::io;
 
 use piston_window::types::Color;
 use piston_window::Context;
 use piston_window::G2d;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 
+use crate::desync::StateHashes;
 use crate::drawing::{draw_block, draw_eyes, draw_tongue};
+use crate::net::{invalid, Reader, Writer};
 
 const SNAKE_COLOR: Color = [0.30, 0.80, 0.30, 1.0];
 const SNAKE_HEAD_COLOR: Color = [0.20, 0.70, 0.20, 1.0];
+pub const MIN_LENGTH: usize = 3;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Direction {
     Up,
     Down,
@@ -36,73 +41,105 @@ struct Block {
     y: i32,
 }
 
+#[derive(Clone)]
 pub struct Snake {
     moving_direction: Direction,
     body: LinkedList<Block>,
-    last_removed_block: Option<Block>,
+    pending_growth: usize,
     tongue_timer: f64,
     tongue_out: bool,
+    tongue_rng: ChaCha12Rng,
+    color: Color,
+    head_color: Color,
 }
 
 impl Snake {
     pub fn new(init_x: i32, init_y: i32) -> Snake {
+        Snake::with_direction(init_x, init_y, Direction::Right)
+    }
+
+    pub fn with_direction(init_x: i32, init_y: i32, direction: Direction) -> Snake {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        };
+
         let mut body: LinkedList<Block> = LinkedList::new();
-        body.push_back(Block {
-            x: init_x + 2,
-            y: init_y,
-        });
-        body.push_back(Block {
-            x: init_x + 1,
-            y: init_y,
-        });
-        body.push_back(Block {
-            x: init_x,
-            y: init_y,
-        });
+        for i in (0..MIN_LENGTH as i32).rev() {
+            body.push_back(Block {
+                x: init_x + dx * i,
+                y: init_y + dy * i,
+            });
+        }
 
         Snake {
-            moving_direction: Direction::Right,
+            moving_direction: direction,
             body,
-            last_removed_block: None,
+            pending_growth: 0,
             tongue_timer: 0.0,
             tongue_out: false,
+            tongue_rng: ChaCha12Rng::seed_from_u64(((init_x as u64) << 32) | init_y as u32 as u64),
+            color: SNAKE_COLOR,
+            head_color: SNAKE_HEAD_COLOR,
         }
     }
 
+    pub fn set_colors(&mut self, color: Color, head_color: Color) {
+        self.color = color;
+        self.head_color = head_color;
+    }
+
     pub fn draw(&self, con: &Context, g: &mut G2d) {
+        self.draw_with_alpha(1.0, con, g);
+    }
+
+    pub fn draw_with_alpha(&self, alpha: f32, con: &Context, g: &mut G2d) {
+        let mut color = self.color;
+        let mut head_color = self.head_color;
+        color[3] *= alpha;
+        head_color[3] *= alpha;
+
         let mut is_head = true;
         for block in &self.body {
             if is_head {
-                draw_block(SNAKE_HEAD_COLOR, block.x, block.y, con, g);
+                draw_block(head_color, block.x, block.y, con, g);
                 draw_eyes(self.moving_direction, block.x, block.y, con, g);
 
-                if self.tongue_out {
+                if self.tongue_out && alpha >= 1.0 {
                     draw_tongue(self.moving_direction, block.x, block.y, con, g);
                 }
                 is_head = false;
             } else {
-                draw_block(SNAKE_COLOR, block.x, block.y, con, g);
+                draw_block(color, block.x, block.y, con, g);
             }
         }
     }
 
+    pub fn colors(&self) -> (Color, Color) {
+        (self.color, self.head_color)
+    }
+
+    pub fn is_tongue_out(&self) -> bool {
+        self.tongue_out
+    }
+
     pub fn update_tongue(&mut self, delta_time: f64) {
         self.tongue_timer -= delta_time;
         if self.tongue_timer <= 0.0 {
             self.tongue_out = !self.tongue_out;
-            let mut rng = rand::rng();
             self.tongue_timer = if self.tongue_out {
-                rng.random_range(0.15..0.25)
+                self.tongue_rng.random_range(0.15..0.25)
             } else {
-                rng.random_range(1.0..3.0)
+                self.tongue_rng.random_range(1.0..3.0)
             };
         }
     }
 
     pub fn move_forward(&mut self, dir: Option<Direction>) {
-        match dir {
-            Some(d) => self.moving_direction = d,
-            None => {}
+        if let Some(d) = dir {
+            self.moving_direction = d;
         }
 
         let (last_x, last_y): (i32, i32) = self.head_position();
@@ -127,8 +164,11 @@ impl Snake {
         };
 
         self.body.push_front(new_block);
-        let removed_blk = self.body.pop_back().unwrap();
-        self.last_removed_block = Some(removed_blk);
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+        } else {
+            self.body.pop_back();
+        }
     }
 
     pub fn head_position(&self) -> (i32, i32) {
@@ -136,6 +176,19 @@ impl Snake {
         (head_block.x, head_block.y)
     }
 
+    pub fn body_positions(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.body.iter().map(|block| (block.x, block.y))
+    }
+
+    pub fn length(&self) -> usize {
+        self.body.len()
+    }
+
+    /// Segments still to be added by upcoming moves.
+    pub fn pending_growth(&self) -> usize {
+        self.pending_growth
+    }
+
     pub fn head_direction(&self) -> Direction {
         self.moving_direction
     }
@@ -143,11 +196,7 @@ impl Snake {
     pub fn next_head_position(&self, dir: Option<Direction>) -> (i32, i32) {
         let (head_x, head_y): (i32, i32) = self.head_position();
 
-        let mut moving_dir = self.moving_direction;
-        match dir {
-            Some(d) => moving_dir = d,
-            None => {}
-        }
+        let moving_dir = dir.unwrap_or(self.moving_direction);
 
         match moving_dir {
             Direction::Up => (head_x, head_y - 1),
@@ -157,12 +206,34 @@ impl Snake {
         }
     }
 
-    pub fn restore_last_removed(&mut self) {
-        let blk = self.last_removed_block.clone().unwrap();
-        self.body.push_back(blk);
+    /// Queues `n` segments, added one per move by leaving the tail in place.
+    pub fn grow(&mut self, n: usize) {
+        self.pending_growth += n;
+    }
+
+    /// Removes up to `n` segments, never going below `MIN_LENGTH`. Pending
+    /// growth is cancelled first, then segments come off the tail.
+    pub fn shrink(&mut self, n: usize) {
+        let cancelled = self.pending_growth.min(n);
+        self.pending_growth -= cancelled;
+        let n = n - cancelled;
+
+        let removable = self.body.len().saturating_sub(MIN_LENGTH).min(n);
+        for _ in 0..removable {
+            self.body.pop_back();
+        }
+    }
+
+    pub fn occupies(&self, x: i32, y: i32) -> bool {
+        self.body.iter().any(|block| block.x == x && block.y == y)
     }
 
+    /// Whether (x, y) will still be covered after the next move. The tail
+    /// is skipped because it moves away, unless the snake is growing.
     pub fn is_overlap_except_tail(&self, x: i32, y: i32) -> bool {
+        if self.pending_growth > 0 {
+            return self.occupies(x, y);
+        }
         let mut checked = 0;
 
         for block in &self.body {
@@ -177,4 +248,58 @@ impl Snake {
         }
         false
     }
+
+    pub(crate) fn hash_state(&self, prefix: &str, hashes: &mut StateHashes) {
+        hashes.field(format!("{}.direction", prefix), |w| {
+            w.direction(self.moving_direction)
+        });
+        hashes.field(format!("{}.body", prefix), |w| {
+            for (x, y) in self.body_positions() {
+                w.position((x, y));
+            }
+        });
+        hashes.field(format!("{}.pending_growth", prefix), |w| {
+            w.u32(self.pending_growth as u32)
+        });
+        hashes.field(format!("{}.tongue", prefix), |w| {
+            w.f64(self.tongue_timer);
+            w.bool(self.tongue_out);
+            w.u64(self.tongue_rng.clone().random());
+        });
+    }
+
+    pub(crate) fn write_state(&self, w: &mut Writer) {
+        w.direction(self.moving_direction);
+        let body: Vec<(i32, i32)> = self.body_positions().collect();
+        w.list(&body, |w, pos| w.position(*pos));
+        w.u32(self.pending_growth as u32);
+        w.f64(self.tongue_timer);
+        w.bool(self.tongue_out);
+        w.rng(&self.tongue_rng);
+        w.color(self.color);
+        w.color(self.head_color);
+    }
+
+    pub(crate) fn read_state(r: &mut Reader) -> io::Result<Snake> {
+        let moving_direction = r.direction()?;
+        let body: LinkedList<Block> = r
+            .list(|r| r.position())?
+            .into_iter()
+            .map(|(x, y)| Block { x, y })
+            .collect();
+        if body.is_empty() {
+            return Err(invalid("snake has no body".to_string()));
+        }
+
+        Ok(Snake {
+            moving_direction,
+            body,
+            pending_growth: r.u32()? as usize,
+            tongue_timer: r.f64()?,
+            tongue_out: r.bool()?,
+            tongue_rng: r.rng()?,
+            color: r.color()?,
+            head_color: r.color()?,
+        })
+    }
 }
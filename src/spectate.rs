@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use piston_window::*;
+
+use crate::client::draw_state;
+use crate::drawing::to_gui_coord;
+use crate::game::Game;
+use crate::net::{invalid, Connection, NetState, ServerMessage};
+use crate::player::PLAYER_COLORS;
+
+pub const DEFAULT_DELAY: f64 = 1.0;
+
+const BROADCAST_RATE: f64 = 20.0;
+const SNAPSHOT_INTERVAL: u32 = 20;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A read-only socket that streams a running game to spectators.
+pub struct Broadcaster {
+    listener: TcpListener,
+    spectators: Vec<Connection<TcpStream>>,
+    width: i32,
+    height: i32,
+    tick: u32,
+    elapsed: f64,
+    last_state: NetState,
+}
+
+impl Broadcaster {
+    pub fn bind(address: &str, width: i32, height: i32) -> io::Result<Broadcaster> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Broadcaster {
+            listener,
+            spectators: Vec::new(),
+            width,
+            height,
+            tick: 0,
+            elapsed: 0.0,
+            last_state: NetState::empty(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Publishes a local game at a fixed rate, whatever the frame rate.
+    pub fn update(&mut self, delta_time: f64, game: &Game) -> io::Result<()> {
+        self.elapsed += delta_time;
+        if self.elapsed < 1.0 / BROADCAST_RATE {
+            return self.accept_spectators();
+        }
+        self.elapsed = 0.0;
+
+        let connected = vec![true; game.players().len()];
+        let state = NetState::from_game(game, self.tick + 1, true, &connected);
+        self.publish(state)
+    }
+
+    /// Sends `state` to every spectator, as a full snapshot periodically and
+    /// as a delta against the previous state otherwise.
+    pub fn publish(&mut self, state: NetState) -> io::Result<()> {
+        self.accept_spectators()?;
+
+        self.tick = state.tick;
+        let message = if self.tick.is_multiple_of(SNAPSHOT_INTERVAL) {
+            ServerMessage::Snapshot(state.clone())
+        } else {
+            ServerMessage::Delta(self.last_state.diff(&state))
+        };
+        let payload = message.encode();
+        self.last_state = state;
+
+        self.spectators.retain_mut(|connection| {
+            connection.send(&payload);
+            connection.receive().is_ok() && connection.flush().is_ok()
+        });
+        Ok(())
+    }
+
+    fn accept_spectators(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    let mut connection = Connection::new(stream);
+                    let spectate = ServerMessage::Spectate {
+                        width: self.width,
+                        height: self.height,
+                    };
+                    connection.send(&spectate.encode());
+                    connection.send(&ServerMessage::Snapshot(self.last_state.clone()).encode());
+                    if connection.flush().is_ok() {
+                        self.spectators.push(connection);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Watches a broadcast, showing each state `delay` seconds after it arrived
+/// so playback stays smooth despite network jitter.
+pub struct Spectator {
+    connection: Connection<TcpStream>,
+    width: i32,
+    height: i32,
+    delay: f64,
+    clock: f64,
+    latest: NetState,
+    buffered: VecDeque<(f64, NetState)>,
+    shown: NetState,
+    focus: usize,
+}
+
+impl Spectator {
+    pub fn connect(address: &str, delay: f64) -> io::Result<Spectator> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+        let mut connection = Connection::new(stream);
+
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while Instant::now() < deadline {
+            let frames = connection.receive()?;
+            if let Some(frame) = frames.first() {
+                let ServerMessage::Spectate { width, height } = ServerMessage::decode(frame)?
+                else {
+                    return Err(invalid("expected spectate".to_string()));
+                };
+                let mut spectator = Spectator {
+                    connection,
+                    width,
+                    height,
+                    delay,
+                    clock: 0.0,
+                    latest: NetState::empty(),
+                    buffered: VecDeque::new(),
+                    shown: NetState::empty(),
+                    focus: 0,
+                };
+                for frame in &frames[1..] {
+                    spectator.handle(frame)?;
+                }
+                return Ok(spectator);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        Err(io::ErrorKind::TimedOut.into())
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// The state currently on screen, `delay` seconds behind the broadcast.
+    pub fn state(&self) -> &NetState {
+        &self.shown
+    }
+
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, index: usize) {
+        if index < self.shown.players.len() {
+            self.focus = index;
+        }
+    }
+
+    pub fn key_pressed(&mut self, key: Key) {
+        let count = self.shown.players.len().max(1);
+        match key {
+            Key::Tab | Key::Right => self.focus = (self.focus + 1) % count,
+            Key::Left => self.focus = (self.focus + count - 1) % count,
+            Key::D1 => self.set_focus(0),
+            Key::D2 => self.set_focus(1),
+            Key::D3 => self.set_focus(2),
+            Key::D4 => self.set_focus(3),
+            Key::D5 => self.set_focus(4),
+            Key::D6 => self.set_focus(5),
+            Key::D7 => self.set_focus(6),
+            Key::D8 => self.set_focus(7),
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f64) -> io::Result<()> {
+        self.clock += delta_time;
+
+        for frame in self.connection.receive()? {
+            self.handle(&frame)?;
+        }
+
+        while self
+            .buffered
+            .front()
+            .is_some_and(|(received, _)| self.clock - received >= self.delay)
+        {
+            self.shown = self.buffered.pop_front().unwrap().1;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, frame: &[u8]) -> io::Result<()> {
+        match ServerMessage::decode(frame)? {
+            ServerMessage::Snapshot(state) => self.latest = state,
+            ServerMessage::Delta(delta) => self.latest.apply(delta),
+            _ => return Ok(()),
+        }
+        self.buffered.push_back((self.clock, self.latest.clone()));
+        Ok(())
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        draw_state(
+            &self.shown,
+            self.width,
+            self.height,
+            self.focus,
+            con,
+            g,
+            glyphs,
+        );
+
+        let Some(player) = self.shown.players.get(self.focus) else {
+            return;
+        };
+
+        let mut lines = vec![
+            format!("Watching {}", player.name),
+            format!("Score: {}  Length: {}", player.score, player.body.len()),
+        ];
+        for (powerup_type, remaining) in &player.effects {
+            lines.push(format!(
+                "{}: {:.1}s",
+                powerup_type.effect().label(),
+                remaining
+            ));
+        }
+        lines.push("Tab / 1-8: switch player".to_string());
+
+        let color = PLAYER_COLORS[self.focus % PLAYER_COLORS.len()].0;
+        let mut y = to_gui_coord(self.height) - 20.0 * lines.len() as f64;
+        for line in &lines {
+            text::Text::new_color(color, 16)
+                .draw(
+                    line,
+                    glyphs,
+                    &con.draw_state,
+                    con.transform.trans(10.0, y),
+                    g,
+                )
+                .unwrap();
+            y += 20.0;
+        }
+    }
+}
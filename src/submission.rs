@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::daily::Date;
+use crate::leaderboard::{BoardKey, LeaderboardEntry, Leaderboards};
+use crate::net::{invalid, Reader, Writer};
+use crate::replay::Replay;
+use crate::sha256::{constant_time_eq, hmac_sha256, sha256};
+
+pub const SUBMISSION_VERSION: u16 = 1;
+pub const SCORE_KEY_ENV: &str = "SNAKE_SCORE_KEY";
+
+const SUBMISSION_MAGIC: u32 = u32::from_le_bytes(*b"SNKC");
+
+/// A claimed score together with everything needed to check it: the seed,
+/// settings and inputs of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreRecord {
+    pub name: String,
+    pub score: u32,
+    pub length: u32,
+    pub date: Date,
+    pub replay: Replay,
+}
+
+impl ScoreRecord {
+    /// Builds a record claiming whatever `replay` actually scores in the game
+    /// that was running when the recording stopped.
+    pub fn from_replay(name: &str, date: Date, replay: Replay) -> ScoreRecord {
+        let game = replay.play_out();
+        ScoreRecord {
+            name: name.to_string(),
+            score: game.score(),
+            length: game.snake().length() as u32,
+            date,
+            replay,
+        }
+    }
+
+    /// Serialises the record followed by an HMAC-SHA256 of it under `key`.
+    pub fn sign(&self, key: &[u8]) -> Vec<u8> {
+        let mut w = Writer::new();
+        w.string(&self.name);
+        w.u32(self.score);
+        w.u32(self.length);
+        w.i32(self.date.year);
+        w.u8(self.date.month as u8);
+        w.u8(self.date.day as u8);
+        w.bytes(&self.replay.encode());
+        let payload = w.finish();
+
+        let mac = hmac_sha256(key, &signed_bytes(SUBMISSION_VERSION, &payload));
+        let mut w = Writer::new();
+        w.u32(SUBMISSION_MAGIC);
+        w.u16(SUBMISSION_VERSION);
+        w.bytes(&payload);
+        w.bytes(&mac);
+        w.finish()
+    }
+
+    /// Checks the signature, then re-simulates the replay and confirms the
+    /// claimed score and length. A replay with impossible settings or too
+    /// many ticks is rejected before anything is simulated.
+    pub fn verify(signed: &[u8], key: &[u8]) -> io::Result<ScoreRecord> {
+        let mut r = Reader::new(signed);
+        if r.u32()? != SUBMISSION_MAGIC {
+            return Err(invalid("not a score record".to_string()));
+        }
+        let version = r.u16()?;
+        if version != SUBMISSION_VERSION {
+            return Err(invalid(format!(
+                "score record version {} is not supported, expected {}",
+                version, SUBMISSION_VERSION
+            )));
+        }
+        let payload = r.bytes()?;
+        let mac = r.bytes()?;
+        if !constant_time_eq(mac, &hmac_sha256(key, &signed_bytes(version, payload))) {
+            return Err(invalid("signature does not match".to_string()));
+        }
+
+        let mut r = Reader::new(payload);
+        let record = ScoreRecord {
+            name: r.string()?,
+            score: r.u32()?,
+            length: r.u32()?,
+            date: Date {
+                year: r.i32()?,
+                month: r.u8()? as u32,
+                day: r.u8()? as u32,
+            },
+            replay: Replay::decode(r.bytes()?)?,
+        };
+
+        let game = record.replay.play_out();
+        if game.score() != record.score {
+            return Err(invalid(format!(
+                "claimed score {} but the replay scores {}",
+                record.score,
+                game.score()
+            )));
+        }
+        if game.snake().length() as u32 != record.length {
+            return Err(invalid(format!(
+                "claimed length {} but the replay ends at length {}",
+                record.length,
+                game.snake().length()
+            )));
+        }
+        Ok(record)
+    }
+}
+
+/// The bytes covered by the signature: header plus payload.
+fn signed_bytes(version: u16, payload: &[u8]) -> Vec<u8> {
+    let mut w = Writer::new();
+    w.u32(SUBMISSION_MAGIC);
+    w.u16(version);
+    w.bytes(payload);
+    w.finish()
+}
+
+/// A stand-in for a self-hosted leaderboard server, backed by a directory:
+/// accepted records are kept under `accepted/` and the tables in
+/// `leaderboards`.
+pub struct ScoreServer {
+    dir: PathBuf,
+    key: Vec<u8>,
+}
+
+impl ScoreServer {
+    pub fn open(dir: &Path, key: &[u8]) -> io::Result<ScoreServer> {
+        fs::create_dir_all(dir.join("accepted"))?;
+        Ok(ScoreServer {
+            dir: dir.to_path_buf(),
+            key: key.to_vec(),
+        })
+    }
+
+    pub fn leaderboards(&self) -> io::Result<Leaderboards> {
+        Leaderboards::load(&self.dir.join("leaderboards"))
+    }
+
+    /// Verifies and stores a signed record. Returns its leaderboard rank, or
+    /// `None` if it was valid but did not make the table. The same record
+    /// cannot be submitted twice.
+    pub fn submit(&self, signed: &[u8]) -> io::Result<Option<usize>> {
+        let record = ScoreRecord::verify(signed, &self.key)?;
+
+        let digest: String = sha256(signed)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let path = self.dir.join("accepted").join(format!("{}.score", digest));
+        if path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "record was already submitted",
+            ));
+        }
+        fs::write(&path, signed)?;
+
+        let mut leaderboards = self.leaderboards()?;
+        let rank = leaderboards.insert(
+            BoardKey::from_config(&record.replay.config),
+            LeaderboardEntry {
+                name: record.name,
+                score: record.score,
+                length: record.length,
+                duration: record.replay.duration(),
+                date: record.date,
+            },
+        );
+        leaderboards.save(&self.dir.join("leaderboards"))?;
+        Ok(rank)
+    }
+}
@@ -0,0 +1,236 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::achievements::{Achievement, Achievements};
+use rust_snakegame::ai;
+use rust_snakegame::events::{EventSubscriber, GameEvent};
+use rust_snakegame::food::FoodRules;
+use rust_snakegame::game::{Game, GameMode};
+use rust_snakegame::powerup::{InventoryRules, PowerUpType};
+use rust_snakegame::profile::{Profile, Profiles, PROFILES_VERSION};
+use rust_snakegame::snake::Direction;
+
+const STEPS: u32 = 3000;
+
+/// Keeps the events it is sent, next to the achievements.
+#[derive(Default)]
+struct Seen(Vec<GameEvent>);
+
+impl EventSubscriber for Seen {
+    fn on_event(&mut self, event: &GameEvent, _game: &Game) {
+        self.0.push(*event);
+    }
+}
+
+/// Whether `event` is one that can unlock `achievement`.
+fn triggers(achievement: Achievement, event: &GameEvent) -> bool {
+    match (achievement, event) {
+        (
+            Achievement::FirstFood
+            | Achievement::Combo10
+            | Achievement::Length50
+            | Achievement::FullBoard,
+            GameEvent::FoodEaten { player: 0, .. },
+        ) => true,
+        (
+            Achievement::DoublePowered,
+            GameEvent::PowerUpCollected { player: 0, .. }
+            | GameEvent::PowerUpActivated { player: 0, .. },
+        ) => true,
+        (Achievement::ShieldSave, GameEvent::ShieldSaved { player: 0 }) => true,
+        (Achievement::Score1000, GameEvent::ScoreChanged { player: 0, score }) => *score >= 1000,
+        _ => false,
+    }
+}
+
+/// Plays `game` for up to `steps` moves, publishing its events to a fresh
+/// `Achievements`. `steer` picks each move and may use the inventory first.
+/// Every unlock must come with its trigger event.
+fn play(
+    mut game: Game,
+    steps: u32,
+    mut steer: impl FnMut(&mut Game) -> Option<Direction>,
+) -> Achievements {
+    let mut achievements = Achievements::new(&[]);
+    for _ in 0..steps {
+        if game.is_game_over() {
+            break;
+        }
+        let dir = steer(&mut game);
+        game.step(dir);
+        let mut seen = Seen::default();
+        game.publish_events(&mut [&mut achievements, &mut seen]);
+        for achievement in achievements.take_newly_unlocked() {
+            assert!(
+                seen.0.iter().any(|e| triggers(achievement, e)),
+                "{:?} unlocked by {:?}",
+                achievement,
+                seen.0
+            );
+        }
+    }
+    achievements
+}
+
+fn game(width: i32, height: i32, seed: u64) -> Game {
+    let mut game = Game::with_mode(width, height, GameMode::Single);
+    game.reset(seed);
+    game
+}
+
+fn autopilot(game: &mut Game) -> Option<Direction> {
+    ai::choose_direction(game, 0)
+}
+
+#[test]
+fn eating_unlocks_food_achievements() {
+    let mut feast = game(27, 22, 3);
+    feast.set_food_rules(FoodRules {
+        base: 60,
+        ..FoodRules::default()
+    });
+    let achievements = play(feast, STEPS, autopilot);
+    for achievement in [
+        Achievement::FirstFood,
+        Achievement::Combo10,
+        Achievement::Length50,
+        Achievement::Score1000,
+    ] {
+        assert!(achievements.is_unlocked(achievement), "{:?}", achievement);
+    }
+    assert!(!achievements.is_unlocked(Achievement::FullBoard));
+}
+
+#[test]
+fn filling_the_board_unlocks_clean_sweep() {
+    let mut tiny = game(7, 5, 1);
+    tiny.set_food_rules(FoodRules {
+        base: 15,
+        ..FoodRules::default()
+    });
+    let achievements = play(tiny, STEPS, autopilot);
+    assert!(achievements.is_unlocked(Achievement::FullBoard));
+}
+
+/// A game that stores `powerup_type` pickups instead of starting them.
+fn stocked(seed: u64, powerup_type: PowerUpType) -> Game {
+    let mut game = game(27, 22, seed);
+    game.set_inventory_rules(Some(InventoryRules::new(1, &[powerup_type])));
+    game
+}
+
+fn stored(game: &Game, powerup_type: PowerUpType) -> Option<usize> {
+    game.players()[0]
+        .inventory()
+        .iter()
+        .position(|t| *t == powerup_type)
+}
+
+#[test]
+fn speed_and_shield_together_unlock_double_powered() {
+    let achievements = play(stocked(5, PowerUpType::Speed), STEPS, |game| {
+        if game.players()[0].is_shielded() {
+            if let Some(slot) = stored(game, PowerUpType::Speed) {
+                game.activate_powerup(0, slot);
+            }
+        }
+        autopilot(game)
+    });
+    assert!(achievements.is_unlocked(Achievement::DoublePowered));
+}
+
+#[test]
+fn crashing_with_a_shield_unlocks_close_call() {
+    let achievements = play(stocked(1, PowerUpType::Shield), STEPS, |game| {
+        if let Some(slot) = stored(game, PowerUpType::Shield) {
+            game.activate_powerup(0, slot);
+        }
+        if game.players()[0].is_shielded() {
+            return None;
+        }
+        autopilot(game)
+    });
+    assert!(achievements.is_unlocked(Achievement::ShieldSave));
+}
+
+#[test]
+fn staying_alive_unlocks_survivor() {
+    let game = game(27, 22, 1);
+    let mut achievements = Achievements::new(&[]);
+    for _ in 0..299 {
+        achievements.update(1.0, &game);
+    }
+    assert!(achievements.take_newly_unlocked().is_empty());
+    achievements.update(1.0, &game);
+    assert_eq!(
+        achievements.take_newly_unlocked(),
+        vec![Achievement::Survive5Minutes]
+    );
+}
+
+#[test]
+fn stored_achievements_are_not_unlocked_again() {
+    let ids: Vec<String> = ["first_food", "unknown"]
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    let mut achievements = Achievements::new(&ids);
+    assert_eq!(achievements.unlocked(), &[Achievement::FirstFood]);
+
+    let mut feast = game(27, 22, 3);
+    for _ in 0..STEPS {
+        let dir = ai::choose_direction(&feast, 0);
+        feast.step(dir);
+        let events = feast.take_events();
+        if events
+            .iter()
+            .any(|e| matches!(e, GameEvent::FoodEaten { player: 0, .. }))
+        {
+            for event in &events {
+                achievements.on_event(event, &feast);
+            }
+            break;
+        }
+    }
+    assert!(achievements.take_newly_unlocked().is_empty());
+    for achievement in Achievement::ALL {
+        assert_eq!(Achievement::from_id(achievement.id()), Some(achievement));
+    }
+}
+
+#[test]
+fn version_1_profiles_load_without_achievements() {
+    let mut payload = b"SNKP".to_vec();
+    payload.extend(1u16.to_le_bytes());
+    payload.extend(1u32.to_le_bytes());
+    payload.extend(2u32.to_le_bytes());
+    for (name, games, best) in [("Player", 4u32, 120u32), ("Ada", 9, 560)] {
+        payload.extend((name.len() as u16).to_le_bytes());
+        payload.extend(name.as_bytes());
+        payload.extend(games.to_le_bytes());
+        payload.extend(best.to_le_bytes());
+    }
+
+    let mut profiles = Profiles::decode(&payload).unwrap();
+    assert_eq!(profiles.current().name, "Ada");
+    assert_eq!(
+        profiles.profiles(),
+        &[
+            Profile {
+                games_played: 4,
+                best_score: 120,
+                ..Profile::new("Player")
+            },
+            Profile {
+                games_played: 9,
+                best_score: 560,
+                ..Profile::new("Ada")
+            },
+        ]
+    );
+
+    assert!(profiles.current_mut().unlock("first_food"));
+    assert!(!profiles.current_mut().unlock("first_food"));
+    let encoded = profiles.encode();
+    assert_eq!(&encoded[4..6], &PROFILES_VERSION.to_le_bytes());
+    assert_eq!(Profiles::decode(&encoded).unwrap(), profiles);
+}
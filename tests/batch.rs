@@ -0,0 +1,161 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::batch::{BatchConfig, BatchEnv};
+use rust_snakegame::env::{Action, Env, EnvConfig, ObservationKind, FEATURE_COUNT};
+
+/// Danger, heading and length features, which do not depend on where random
+/// food and power-ups spawn.
+const LAYOUT_FEATURES: [usize; 8] = [0, 1, 2, 3, 4, 5, 6, 13];
+
+fn pair(seed: u64) -> (Env, BatchEnv) {
+    let mut env = Env::new(EnvConfig {
+        observation: ObservationKind::Features,
+        ..EnvConfig::default()
+    })
+    .unwrap();
+    env.reset(seed);
+    let mut batch = BatchEnv::new(BatchConfig {
+        num_envs: 1,
+        auto_reset: false,
+        ..BatchConfig::default()
+    })
+    .unwrap();
+    batch.reset(seed);
+    (env, batch)
+}
+
+fn batch_observation(batch: &BatchEnv) -> Vec<f32> {
+    let mut out = vec![0.0; FEATURE_COUNT];
+    batch.observe(&mut out);
+    out
+}
+
+/// Eats the fixed starting food, then runs straight into the bottom wall.
+fn script() -> Vec<Action> {
+    let mut actions = vec![Action::Right, Action::Down];
+    actions.extend(std::iter::repeat_n(Action::Keep, 30));
+    actions
+}
+
+#[test]
+fn starting_observation_matches_env() {
+    let (mut env, batch) = pair(1);
+    assert_eq!(batch_observation(&batch), env.reset(1).data);
+}
+
+#[test]
+fn scripted_episode_matches_env() {
+    let (mut env, mut batch) = pair(1);
+
+    for (step, action) in script().into_iter().enumerate() {
+        let (observation, reward, done, info) = env.step(action);
+        batch.step(&[action]);
+        let batch_observation = batch_observation(&batch);
+
+        assert_eq!(batch.rewards()[0], reward, "reward at step {}", step);
+        assert_eq!(batch.dones()[0], done, "done at step {}", step);
+        assert_eq!(
+            batch.scores().next(),
+            Some(info.score),
+            "score at step {}",
+            step
+        );
+        assert_eq!(
+            batch.lengths().next(),
+            Some(info.length),
+            "length at step {}",
+            step
+        );
+        if !done {
+            for i in LAYOUT_FEATURES {
+                assert_eq!(
+                    batch_observation[i], observation.data[i],
+                    "feature {} at step {}",
+                    i, step
+                );
+            }
+        }
+        if done {
+            assert!(reward < 0.0);
+            assert_eq!(info.length, 4);
+            return;
+        }
+    }
+    panic!("the snake never reached the wall");
+}
+
+#[test]
+fn invalid_boards_are_errors_for_both_envs() {
+    for (width, height) in [(2, 22), (27, 4), (300, 300)] {
+        let config = BatchConfig {
+            num_envs: 1,
+            width,
+            height,
+            ..BatchConfig::default()
+        };
+        assert!(BatchEnv::new(config).is_err());
+        let config = EnvConfig {
+            width,
+            height,
+            ..EnvConfig::default()
+        };
+        assert!(Env::new(config).is_err());
+    }
+}
+
+#[test]
+fn smallest_env_board_plays_to_the_end() {
+    let mut env = Env::new(EnvConfig {
+        width: 7,
+        height: 5,
+        ..EnvConfig::default()
+    })
+    .unwrap();
+    env.reset(1);
+    for _ in 0..100 {
+        if env.step(Action::Keep).2 {
+            return;
+        }
+    }
+    panic!("the snake never hit a wall");
+}
+
+#[test]
+fn smallest_board_keeps_stepping() {
+    let mut batch = BatchEnv::new(BatchConfig {
+        num_envs: 16,
+        width: 7,
+        height: 5,
+        ..BatchConfig::default()
+    })
+    .unwrap();
+    let turns = [Action::Up, Action::Left, Action::Down, Action::Right];
+    for step in 0..5000 {
+        let actions: Vec<Action> = (0..16).map(|i| turns[(step / 3 + i) % 4]).collect();
+        batch.step(&actions);
+    }
+}
+
+#[test]
+fn threaded_steps_match_a_single_thread() {
+    let batch = |threads| {
+        let mut batch = BatchEnv::new(BatchConfig {
+            num_envs: 4096,
+            threads,
+            ..BatchConfig::default()
+        })
+        .unwrap();
+        batch.reset(9);
+        batch
+    };
+    let (mut single, mut threaded) = (batch(1), batch(4));
+    let turns = [Action::Keep, Action::Up, Action::Keep, Action::Right];
+    for step in 0..200 {
+        let actions: Vec<Action> = (0..4096).map(|i| turns[(step + i) % 4]).collect();
+        single.step(&actions);
+        threaded.step(&actions);
+        assert_eq!(single.rewards(), threaded.rewards(), "step {}", step);
+    }
+    assert!(single.scores().eq(threaded.scores()));
+    assert!(single.lengths().eq(threaded.lengths()));
+}
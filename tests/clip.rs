@@ -0,0 +1,120 @@
+extern crate gif;
+extern crate png;
+extern crate rust_snakegame;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use rust_snakegame::clip::{export_clip, ClipBuffer, ClipFormat, CLIP_FPS};
+use rust_snakegame::drawing::{to_gui_coord, BLOCK_SIZE};
+use rust_snakegame::game::Game;
+use rust_snakegame::raster::{render_game, Canvas};
+
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+fn scratch_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("snake-clip-{}-{}", std::process::id(), name))
+}
+
+/// Plays `seconds` of a fixed-seed game into a clip buffer of `length`
+/// seconds.
+fn recorded(length: f64, seconds: f64) -> (Game, ClipBuffer) {
+    let mut game = Game::with_seed(12, 8, 4);
+    let mut clip = ClipBuffer::new(length);
+    let delta_time = 1.0 / (2 * CLIP_FPS) as f64;
+    for _ in 0..(seconds / delta_time) as u32 {
+        game.update(delta_time);
+        clip.update(delta_time, &game);
+    }
+    (game, clip)
+}
+
+fn pixel(canvas: &Canvas, x: f64, y: f64) -> &[u8] {
+    let index = (y as usize * canvas.width() as usize + x as usize) * 4;
+    &canvas.pixels()[index..index + 4]
+}
+
+#[test]
+fn buffer_samples_at_the_clip_rate_and_keeps_the_last_seconds() {
+    let (_, clip) = recorded(10.0, 2.0);
+    assert_eq!(clip.len(), 2 * CLIP_FPS as usize);
+
+    let (game, clip) = recorded(1.0, 3.0);
+    assert_eq!(clip.len(), CLIP_FPS as usize);
+    let latest = clip.frames().pop().unwrap();
+    assert_eq!(latest.snake().head_position(), game.snake().head_position());
+}
+
+#[test]
+fn rendered_board_has_walls_and_the_snake() {
+    let game = Game::with_seed(27, 22, 4);
+    let canvas = render_game(&game, BACKGROUND);
+    assert_eq!(canvas.width(), to_gui_coord(27) as u32);
+    assert_eq!(canvas.height(), to_gui_coord(22) as u32);
+
+    let background = [0, 0, 0, 255];
+    let centre = |x: i32, y: i32| {
+        (
+            to_gui_coord(x) + BLOCK_SIZE / 2.0,
+            to_gui_coord(y) + BLOCK_SIZE / 2.0,
+        )
+    };
+    let (wall_x, wall_y) = centre(0, 0);
+    assert_ne!(pixel(&canvas, wall_x, wall_y), background);
+    let (x, y) = game.snake().head_position();
+    let (head_x, head_y) = centre(x, y);
+    assert_ne!(pixel(&canvas, head_x, head_y), background);
+    let (x, y) = (3..24)
+        .flat_map(|x| (3..19).map(move |y| (x, y)))
+        .find(|&(x, y)| {
+            !game.snake().occupies(x, y) && !game.foods().iter().any(|f| (f.x, f.y) == (x, y))
+        })
+        .unwrap();
+    let (free_x, free_y) = centre(x, y);
+    assert_eq!(pixel(&canvas, free_x, free_y), background);
+}
+
+#[test]
+fn clips_export_as_animated_gif_and_apng() {
+    let (game, clip) = recorded(1.0, 1.5);
+    let frames = clip.frames();
+    let (width, height) = (to_gui_coord(game.width()), to_gui_coord(game.height()));
+
+    let gif_path = scratch_path("clip.gif");
+    export_clip(&frames, BACKGROUND, ClipFormat::Gif, &gif_path).unwrap();
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(File::open(&gif_path).unwrap()).unwrap();
+    assert_eq!(
+        (decoder.width() as f64, decoder.height() as f64),
+        (width, height)
+    );
+    let mut gif_frames = 0;
+    while decoder.read_next_frame().unwrap().is_some() {
+        gif_frames += 1;
+    }
+    assert_eq!(gif_frames, frames.len());
+    fs::remove_file(&gif_path).unwrap();
+
+    let apng_path = scratch_path("clip.png");
+    assert_eq!(ClipFormat::from_path(&apng_path), ClipFormat::Apng);
+    export_clip(&frames, BACKGROUND, ClipFormat::Apng, &apng_path).unwrap();
+    let reader = png::Decoder::new(File::open(&apng_path).unwrap())
+        .read_info()
+        .unwrap();
+    let info = reader.info();
+    assert_eq!((info.width as f64, info.height as f64), (width, height));
+    assert_eq!(
+        info.animation_control.map(|a| a.num_frames),
+        Some(frames.len() as u32)
+    );
+    fs::remove_file(&apng_path).unwrap();
+}
+
+#[test]
+fn empty_clip_is_not_exported() {
+    let path = scratch_path("empty.gif");
+    assert!(export_clip(&[], BACKGROUND, ClipFormat::Gif, &path).is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,41 @@
+extern crate piston_window;
+extern crate rust_snakegame;
+
+use piston_window::Key;
+use rust_snakegame::game::Game;
+use rust_snakegame::snake::Direction;
+
+const TICK: f64 = 1.0 / 120.0;
+
+/// Updates until the snake's head leaves its current cell.
+fn next_move(game: &mut Game) -> (i32, i32) {
+    let start = game.snake().head_position();
+    for _ in 0..1000 {
+        game.update(TICK);
+        if game.snake().head_position() != start {
+            return game.snake().head_position();
+        }
+    }
+    panic!("the snake never moved");
+}
+
+#[test]
+fn mashed_keys_wait_for_the_movement_timer() {
+    let mut game = Game::with_seed(27, 22, 3);
+    let start = game.snake().head_position();
+    let (turn, turned) = match game.snake().head_direction() {
+        Direction::Up | Direction::Down => ([Key::Left, Key::Right], Direction::Left),
+        Direction::Left | Direction::Right => ([Key::Up, Key::Down], Direction::Up),
+    };
+
+    for _ in 0..10 {
+        game.key_pressed(turn[0]);
+        game.key_pressed(turn[1]);
+        game.key_pressed(turn[0]);
+    }
+    assert_eq!(game.snake().head_position(), start);
+
+    let (x, y) = next_move(&mut game);
+    assert_eq!((x - start.0).abs() + (y - start.1).abs(), 1);
+    assert_eq!(game.snake().head_direction(), turned);
+}
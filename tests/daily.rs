@@ -0,0 +1,106 @@
+extern crate rust_snakegame;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rust_snakegame::daily::{Daily, DailyHistory, DailyResult, Date};
+use rust_snakegame::game::{Game, GameMode};
+
+fn date(year: i32, month: u32, day: u32) -> Date {
+    Date { year, month, day }
+}
+
+fn result(date: Date, score: u32) -> DailyResult {
+    DailyResult {
+        date,
+        score,
+        length: 3 + score / 10,
+        duration: 42.5,
+    }
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("snake-daily-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.join("history")
+}
+
+#[test]
+fn days_convert_to_calendar_dates() {
+    assert_eq!(Date::from_days(0), date(1970, 1, 1));
+    assert_eq!(Date::from_days(-1), date(1969, 12, 31));
+    assert_eq!(Date::from_days(11_016), date(2000, 2, 29));
+    assert_eq!(Date::from_days(20_454), date(2026, 1, 1));
+    assert_eq!(date(2026, 1, 1).to_string(), "2026-01-01");
+}
+
+#[test]
+fn each_date_has_its_own_fixed_seed() {
+    let today = date(2026, 3, 14);
+    assert_eq!(today.seed(), date(2026, 3, 14).seed());
+    assert_eq!(today.seed(), 15_478_102_507_969_276_624);
+    assert_ne!(today.seed(), date(2026, 3, 15).seed());
+    assert_ne!(today.seed(), date(2025, 3, 14).seed());
+
+    let play = || {
+        let mut game = Game::with_mode(27, 22, GameMode::Single);
+        game.reset(today.seed());
+        for _ in 0..600 {
+            game.update(1.0 / 60.0);
+        }
+        game.state_hashes().checksum()
+    };
+    assert_eq!(play(), play());
+}
+
+#[test]
+fn only_the_first_attempt_of_the_day_is_scored() {
+    let path = scratch_path("attempts");
+    let today = date(2026, 3, 14);
+    let mut daily = Daily::load(today, &path).unwrap();
+    assert!(!daily.is_scored());
+
+    assert!(daily.finish(120, 15, 60.0).unwrap());
+    assert!(daily.is_scored());
+    assert!(!daily.finish(500, 40, 90.0).unwrap());
+    assert_eq!(daily.history().result(today).unwrap().score, 120);
+
+    let reloaded = Daily::load(today, &path).unwrap();
+    assert!(reloaded.is_scored());
+    assert_eq!(reloaded.history(), daily.history());
+    let tomorrow = Daily::load(date(2026, 3, 15), &path).unwrap();
+    assert!(!tomorrow.is_scored());
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn history_round_trips_in_date_order() {
+    let mut history = DailyHistory::default();
+    assert!(history.record(result(date(2026, 3, 14), 80)));
+    assert!(history.record(result(date(2025, 12, 31), 40)));
+    assert!(history.record(result(date(2026, 1, 2), 60)));
+    assert!(!history.record(result(date(2026, 1, 2), 999)));
+
+    let dates: Vec<Date> = history.results().iter().map(|r| r.date).collect();
+    assert_eq!(
+        dates,
+        vec![date(2025, 12, 31), date(2026, 1, 2), date(2026, 3, 14)]
+    );
+    assert_eq!(DailyHistory::decode(&history.encode()).unwrap(), history);
+
+    let path = scratch_path("history");
+    assert_eq!(DailyHistory::load(&path).unwrap(), DailyHistory::default());
+    history.save(&path).unwrap();
+    assert_eq!(DailyHistory::load(&path).unwrap(), history);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn history_from_another_version_is_rejected() {
+    let mut payload = DailyHistory::default().encode();
+    payload[4] = payload[4].wrapping_add(1);
+    assert!(DailyHistory::decode(&payload).is_err());
+    assert!(DailyHistory::decode(b"not a history").is_err());
+}
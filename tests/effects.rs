@@ -0,0 +1,386 @@
+extern crate piston_window;
+extern crate rust_snakegame;
+
+use piston_window::Key;
+use rust_snakegame::ai;
+use rust_snakegame::events::GameEvent;
+use rust_snakegame::game::{Game, GameMode, RampMetric, SpeedRamp};
+use rust_snakegame::powerup::{InventoryRules, PowerUpType};
+use rust_snakegame::snake::Direction;
+
+const BASE_PERIOD: f64 = 0.2;
+const STEPS: u32 = 3000;
+
+/// Steers the first snake with the built-in AI for `steps` moves from
+/// `seed`, starting a new round whenever it dies. `check` sees the game
+/// before and after each move and the events the move raised.
+fn autopilot(seed: u64, steps: u32, check: impl FnMut(&Game, &Game, &[GameEvent])) {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.reset(seed);
+    drive(game, steps, check);
+}
+
+/// Like `autopilot`, but from a game that is already set up.
+fn drive(mut game: Game, steps: u32, mut check: impl FnMut(&Game, &Game, &[GameEvent])) {
+    for _ in 0..steps {
+        if game.is_game_over() {
+            game.next_round();
+            game.take_events();
+        }
+        let before = game.clone();
+        game.step(ai::choose_direction(&game, 0));
+        let events = game.take_events();
+        check(&before, &game, &events);
+    }
+}
+
+fn collected(events: &[GameEvent]) -> Option<PowerUpType> {
+    events.iter().find_map(|e| match e {
+        GameEvent::PowerUpCollected {
+            player: 0,
+            powerup_type,
+        } => Some(*powerup_type),
+        _ => None,
+    })
+}
+
+fn scaled(period: f64, effects: &[PowerUpType]) -> f64 {
+    effects
+        .iter()
+        .fold(period, |period, e| period * e.effect().period_scale())
+}
+
+#[test]
+fn speed_shortens_the_period_at_a_high_ramp_level() {
+    let ramp = SpeedRamp::new(RampMetric::Score);
+    let floor = ramp.period(BASE_PERIOD, 50);
+    assert_eq!(floor, ramp.period(BASE_PERIOD, 51));
+
+    let boosted = scaled(floor, &[PowerUpType::Speed]);
+    assert!(boosted < floor, "{} is not faster than {}", boosted, floor);
+}
+
+#[test]
+fn effects_scale_the_period_in_any_order() {
+    for a in PowerUpType::ALL {
+        for b in PowerUpType::ALL {
+            let forward = scaled(BASE_PERIOD, &[a, b]);
+            let backward = scaled(BASE_PERIOD, &[b, a]);
+            assert!(
+                (forward - backward).abs() < 1e-12,
+                "{:?} then {:?} gave {}, the other order {}",
+                a,
+                b,
+                forward,
+                backward
+            );
+        }
+    }
+
+    let slowed = scaled(BASE_PERIOD, &[PowerUpType::SlowMo]);
+    let both = scaled(BASE_PERIOD, &[PowerUpType::Speed, PowerUpType::SlowMo]);
+    assert!(both > scaled(BASE_PERIOD, &[PowerUpType::Speed]));
+    assert!(both < slowed);
+}
+
+#[test]
+fn every_power_up_spawns_on_a_free_cell_inside_the_walls() {
+    let mut seen = Vec::new();
+    autopilot(7, STEPS, |_, game, _| {
+        for powerup in game.powerups() {
+            assert!(!game.is_wall(powerup.x, powerup.y));
+            assert!(!game.snake().occupies(powerup.x, powerup.y));
+            assert!(!game
+                .foods()
+                .iter()
+                .any(|f| (f.x, f.y) == (powerup.x, powerup.y)));
+            if !seen.contains(&powerup.powerup_type) {
+                seen.push(powerup.powerup_type);
+            }
+        }
+    });
+    for powerup_type in PowerUpType::ALL {
+        assert!(
+            seen.contains(&powerup_type),
+            "{:?} never spawned",
+            powerup_type
+        );
+    }
+}
+
+#[test]
+fn collected_effects_run_for_their_duration_then_expire() {
+    let mut collected_types = Vec::new();
+    let mut expired_types = Vec::new();
+    autopilot(7, STEPS, |_, game, events| {
+        if let Some(powerup_type) = collected(events) {
+            let player = &game.players()[0];
+            if player.is_alive() {
+                assert_eq!(
+                    player.effect_timer(powerup_type),
+                    Some(powerup_type.effect().duration()),
+                    "{:?}",
+                    powerup_type
+                );
+            }
+            collected_types.push(powerup_type);
+        }
+        for event in events {
+            if let GameEvent::PowerUpExpired {
+                player: 0,
+                powerup_type,
+            } = event
+            {
+                assert!(!game.players()[0].has_effect(*powerup_type));
+                expired_types.push(*powerup_type);
+            }
+        }
+    });
+    assert!(collected_types.len() > 5);
+    assert!(collected_types.iter().any(|t| expired_types.contains(t)));
+}
+
+fn total_length(game: &Game) -> usize {
+    game.snake().length() + game.snake().pending_growth()
+}
+
+#[test]
+fn shrink_removes_tail_segments_down_to_the_starting_length() {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.reset(1);
+    PowerUpType::Shrink.effect().on_pickup(&mut game, 0);
+    assert_eq!(total_length(&game), 3);
+
+    let mut shrunk = 0;
+    autopilot(4, STEPS, |before, game, events| {
+        if collected(events) == Some(PowerUpType::Shrink) && before.snake().pending_growth() == 0 {
+            assert_eq!(
+                total_length(game),
+                total_length(before).saturating_sub(3).max(3)
+            );
+            shrunk += 1;
+        }
+    });
+    assert!(shrunk > 0);
+}
+
+#[test]
+fn multiplier_scales_food_score_on_top_of_the_combo_bonus() {
+    let mut multiplied = 0;
+    let stacks = |game: &Game| {
+        game.players()[0]
+            .effects()
+            .iter()
+            .find(|e| e.powerup_type == PowerUpType::Multiplier)
+            .map_or(0, |e| e.stacks)
+    };
+    autopilot(21, STEPS, |before, game, events| {
+        if collected(events) == Some(PowerUpType::Multiplier) && game.players()[0].is_alive() {
+            assert_eq!(stacks(game), (stacks(before) + 1).min(2));
+        }
+        let Some(food_type) = events.iter().find_map(|e| match e {
+            GameEvent::FoodEaten {
+                player: 0,
+                food_type,
+            } => Some(*food_type),
+            _ => None,
+        }) else {
+            return;
+        };
+        let gained = game.score() - before.score();
+        if food_type.is_rotten() {
+            assert_eq!(gained, 0);
+            return;
+        }
+
+        let combo = game.players()[0].combo_count();
+        let bonus = if combo > 1 { combo * 2 } else { 0 };
+        let multiplier: u32 = game.players()[0]
+            .effects()
+            .iter()
+            .map(|e| e.powerup_type.effect().score_multiplier(e.stacks))
+            .product();
+        assert_eq!(gained, (food_type.score() + bonus) * multiplier);
+        if multiplier > 1 {
+            multiplied += 1;
+        }
+    });
+    assert!(multiplied > 0);
+
+    let multiplier = PowerUpType::Multiplier.effect();
+    assert_eq!(multiplier.score_multiplier(1), 2);
+    assert_eq!(multiplier.score_multiplier(2), 3);
+}
+
+#[test]
+fn magnet_pulls_nearby_food_towards_the_head() {
+    let mut pulls = 0;
+    autopilot(4, STEPS, |_, game, _| {
+        if game.is_game_over() || !game.players()[0].has_effect(PowerUpType::Magnet) {
+            return;
+        }
+        let mut pulled = game.clone();
+        PowerUpType::Magnet.effect().on_tick(&mut pulled, 0, 0.25);
+
+        let (head_x, head_y) = game.snake().head_position();
+        let distance = |x: i32, y: i32| (x - head_x).abs() + (y - head_y).abs();
+        for (food, moved) in game.foods().iter().zip(pulled.foods()) {
+            let (from, to) = (distance(food.x, food.y), distance(moved.x, moved.y));
+            if from > 5 || from <= 1 {
+                assert_eq!((food.x, food.y), (moved.x, moved.y));
+            } else {
+                assert!(to == from || to == from - 1);
+                if to < from {
+                    pulls += 1;
+                }
+            }
+        }
+    });
+    assert!(pulls > 0);
+}
+
+#[test]
+fn cursed_pickups_give_nothing_and_are_not_stored() {
+    let cursed = [PowerUpType::Reverse, PowerUpType::Poison, PowerUpType::Fog];
+    let rules = InventoryRules::default();
+    for powerup_type in PowerUpType::ALL {
+        let effect = powerup_type.effect();
+        assert_eq!(effect.is_cursed(), cursed.contains(&powerup_type));
+        if effect.is_cursed() {
+            assert_eq!(effect.pickup_score(), 0);
+            assert!(!rules.stores(powerup_type));
+        }
+    }
+
+    let mut cursed_pickups = 0;
+    drive(with_inventory(7, rules), STEPS, |before, game, events| {
+        if collected(events).is_some_and(|t| cursed.contains(&t)) {
+            assert_eq!(
+                game.players()[0].inventory(),
+                before.players()[0].inventory()
+            );
+            cursed_pickups += 1;
+        }
+    });
+    assert!(cursed_pickups > 0);
+}
+
+#[test]
+fn poison_costs_score_and_segments() {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.reset(1);
+    PowerUpType::Poison.effect().on_pickup(&mut game, 0);
+    assert_eq!(game.score(), 0);
+    assert_eq!(total_length(&game), 3);
+
+    let mut poisoned = 0;
+    autopilot(7, STEPS, |before, game, events| {
+        if collected(events) == Some(PowerUpType::Poison) && before.snake().pending_growth() == 0 {
+            assert_eq!(game.score(), before.score().saturating_sub(30));
+            assert_eq!(
+                total_length(game),
+                total_length(before).saturating_sub(2).max(3)
+            );
+            poisoned += 1;
+        }
+    });
+    assert!(poisoned > 0);
+}
+
+#[test]
+fn reverse_inverts_steering_and_fog_narrows_the_view() {
+    let (mut reversed, mut fogged) = (0, 0);
+    autopilot(7, STEPS, |_, game, _| {
+        let player = &game.players()[0];
+        assert_eq!(
+            player.fog_radius().is_some(),
+            player.has_effect(PowerUpType::Fog)
+        );
+        if player.has_effect(PowerUpType::Fog) {
+            fogged += 1;
+        }
+
+        if game.is_game_over() || !player.has_effect(PowerUpType::Reverse) {
+            return;
+        }
+        let turn = match game.snake().head_direction() {
+            Direction::Up | Direction::Down => Direction::Left,
+            Direction::Left | Direction::Right => Direction::Up,
+        };
+        let mut steered = game.clone();
+        steered.step(Some(turn));
+        if steered.players()[0].is_alive() {
+            assert_eq!(steered.snake().head_direction(), turn.opposite());
+            reversed += 1;
+        }
+    });
+    assert!(reversed > 0);
+    assert!(fogged > 0);
+}
+
+fn with_inventory(seed: u64, rules: InventoryRules) -> Game {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.set_inventory_rules(Some(rules));
+    game.reset(seed);
+    game
+}
+
+#[test]
+fn pickups_are_stored_while_there_is_room() {
+    let rules = InventoryRules::default().with_capacity(2);
+    let (mut stored, mut started) = (0, 0);
+    drive(with_inventory(7, rules), STEPS, |before, game, events| {
+        let Some(powerup_type) = collected(events) else {
+            return;
+        };
+        if !game.players()[0].is_alive() {
+            return;
+        }
+        let held = before.players()[0].inventory().to_vec();
+        let inventory = game.players()[0].inventory();
+        if rules.stores(powerup_type) && held.len() < rules.capacity {
+            assert_eq!(&inventory[..held.len()], &held[..]);
+            assert_eq!(inventory[held.len()..], [powerup_type]);
+            stored += 1;
+        } else {
+            assert_eq!(inventory, &held[..], "{:?}", powerup_type);
+            assert!(game.players()[0].has_effect(powerup_type));
+            started += 1;
+        }
+    });
+    assert!(stored > 0);
+    assert!(started > 0);
+}
+
+#[test]
+fn number_keys_start_stored_power_ups() {
+    let mut game = with_inventory(7, InventoryRules::default());
+    while game.players()[0].inventory().is_empty() {
+        assert!(!game.is_game_over());
+        game.step(ai::choose_direction(&game, 0));
+    }
+    game.take_events();
+    let powerup_type = game.players()[0].inventory()[0];
+    assert!(!game.players()[0].has_effect(powerup_type));
+
+    game.key_pressed(Key::D2);
+    assert!(game.take_events().is_empty());
+    assert_eq!(game.players()[0].inventory(), &[powerup_type]);
+
+    game.key_pressed(Key::D1);
+    assert_eq!(
+        game.take_events().first(),
+        Some(&GameEvent::PowerUpActivated {
+            player: 0,
+            powerup_type,
+        })
+    );
+    assert!(game.players()[0].inventory().is_empty());
+    assert_eq!(
+        game.players()[0].effect_timer(powerup_type),
+        Some(powerup_type.effect().duration())
+    );
+
+    game.activate_powerup(0, 0);
+    assert!(game.take_events().is_empty());
+}
@@ -0,0 +1,25 @@
+extern crate piston_window;
+extern crate rust_snakegame;
+
+use piston_window::Key;
+use rust_snakegame::events::GameEvent;
+use rust_snakegame::game::{Game, GameMode};
+
+#[test]
+fn restart_event_survives_the_next_update() {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.reset(5);
+    while !game.is_game_over() {
+        game.update(0.05);
+    }
+    assert!(game
+        .take_events()
+        .iter()
+        .any(|e| matches!(e, GameEvent::Died { player: 0, .. })));
+
+    game.key_pressed(Key::Space);
+    game.update(0.05);
+    let events = game.take_events();
+    assert_eq!(events.first(), Some(&GameEvent::Restarted));
+    assert!(game.take_events().is_empty());
+}
@@ -0,0 +1,83 @@
+extern crate rust_snakegame;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use rust_snakegame::food::FoodRules;
+use rust_snakegame::game::{CollisionRules, Difficulty, GameMode};
+use rust_snakegame::ghost::{best_run_path, save_if_best};
+use rust_snakegame::replay::{Replay, ReplayConfig};
+
+fn config() -> ReplayConfig {
+    ReplayConfig {
+        width: 27,
+        height: 22,
+        mode: GameMode::Single,
+        rules: CollisionRules::default(),
+        difficulty: Difficulty::Normal,
+        inventory: None,
+        food: FoodRules::default(),
+        speed_ramp: None,
+        best_of: 1,
+        seed: 1,
+        tick_duration: 1.0 / 120.0,
+    }
+}
+
+fn run(config: ReplayConfig) -> Replay {
+    let mut replay = Replay::new(config);
+    replay.ticks = 300;
+    replay
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("snake-ghost-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn best_runs_are_kept_per_setting_regardless_of_seed() {
+    let dir = scratch_dir("settings");
+    let normal = run(config());
+    let hard = run(ReplayConfig {
+        difficulty: Difficulty::Hard,
+        ..config()
+    });
+    let reseeded = run(ReplayConfig {
+        seed: 2,
+        ..config()
+    });
+
+    assert_eq!(
+        best_run_path(&dir, &normal.config),
+        best_run_path(&dir, &reseeded.config)
+    );
+    assert_ne!(
+        best_run_path(&dir, &normal.config),
+        best_run_path(&dir, &hard.config)
+    );
+
+    assert!(save_if_best(&normal, &dir).unwrap());
+    assert!(save_if_best(&hard, &dir).unwrap());
+    assert!(!save_if_best(&reseeded, &dir).unwrap());
+
+    let kept = Replay::load(&best_run_path(&dir, &normal.config)).unwrap();
+    assert_eq!(kept, normal);
+    let kept = Replay::load(&best_run_path(&dir, &hard.config)).unwrap();
+    assert_eq!(kept, hard);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn multiplayer_runs_are_not_kept() {
+    let dir = scratch_dir("versus");
+    let versus = run(ReplayConfig {
+        mode: GameMode::Versus,
+        ..config()
+    });
+    assert!(!save_if_best(&versus, &dir).unwrap());
+    assert!(!dir.exists());
+}
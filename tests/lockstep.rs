@@ -0,0 +1,174 @@
+extern crate rust_snakegame;
+
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snakegame::lockstep::{Lockstep, LockstepConfig, PeerMessage};
+use rust_snakegame::net::{Connection, PROTOCOL_VERSION};
+use rust_snakegame::snake::Direction;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const TICK_RATE: f64 = 20.0;
+const PERIOD: f64 = 1.0 / TICK_RATE;
+
+fn free_addresses(count: usize) -> Vec<String> {
+    let listeners: Vec<TcpListener> = (0..count)
+        .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+        .collect();
+    listeners
+        .iter()
+        .map(|l| l.local_addr().unwrap().to_string())
+        .collect()
+}
+
+fn config(player: usize, peers: &[String]) -> LockstepConfig {
+    LockstepConfig {
+        player,
+        peers: peers.to_vec(),
+        name: format!("peer {}", player),
+        tick_rate: TICK_RATE,
+        seed: Some(11),
+        ..LockstepConfig::default()
+    }
+}
+
+fn connect_pair() -> (Lockstep, Lockstep) {
+    let peers = free_addresses(2);
+    let first = {
+        let config = config(0, &peers);
+        thread::spawn(move || Lockstep::connect(config).unwrap())
+    };
+    let second = Lockstep::connect(config(1, &peers)).unwrap();
+    (first.join().unwrap(), second)
+}
+
+fn wait_until(mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + TIMEOUT;
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn late_input_rolls_back_and_peers_converge() {
+    let (mut early, mut late) = connect_pair();
+
+    for _ in 0..10 {
+        early.update(PERIOD).unwrap();
+    }
+    assert_eq!(early.tick(), 10);
+    assert_eq!(late.tick(), 0);
+
+    let direction = match late.game().players()[1].snake().head_direction() {
+        Direction::Up | Direction::Down => Direction::Left,
+        Direction::Left | Direction::Right => Direction::Up,
+    };
+    late.set_direction(direction);
+    late.update(PERIOD).unwrap();
+
+    wait_until(|| {
+        early.update(0.0).unwrap();
+        early.rollbacks() > 0
+    });
+
+    while late.tick() < early.tick() {
+        late.update(PERIOD).unwrap();
+    }
+    assert_eq!(late.tick(), early.tick());
+    assert_eq!(
+        early.game().state_hashes(),
+        late.game().state_hashes(),
+        "peers diverged after the rollback"
+    );
+
+    wait_until(|| {
+        early.update(0.0).unwrap();
+        late.update(0.0).unwrap();
+        early.verified_tick() > 3 && late.verified_tick() > 3
+    });
+    assert!(early.desync().is_none());
+    assert!(late.desync().is_none());
+}
+
+#[test]
+fn mismatched_checksum_reports_the_diverging_field() {
+    let peers = free_addresses(2);
+    let honest = {
+        let config = config(0, &peers);
+        thread::spawn(move || Lockstep::connect(config).unwrap())
+    };
+
+    // A hand-driven second peer whose state is corrupt from the first tick.
+    let deadline = Instant::now() + TIMEOUT;
+    let stream = loop {
+        match TcpStream::connect(&peers[0]) {
+            Ok(stream) => break stream,
+            Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            Err(e) => panic!("peer never listened: {}", e),
+        }
+    };
+    stream.set_nonblocking(true).unwrap();
+    let mut corrupt = Connection::new(stream);
+    let hello = PeerMessage::Hello {
+        version: PROTOCOL_VERSION,
+        player: 1,
+        name: "corrupt".to_string(),
+        seed: 0,
+    };
+    corrupt.send(&hello.encode());
+    let delay = LockstepConfig::default().input_delay;
+    for tick in delay..delay + 10 {
+        corrupt.send(
+            &PeerMessage::Input {
+                tick,
+                direction: None,
+            }
+            .encode(),
+        );
+    }
+    corrupt.flush().unwrap();
+    let mut honest = honest.join().unwrap();
+
+    let mut reported = None;
+    let mut replied = false;
+    wait_until(|| {
+        honest.update(PERIOD).unwrap();
+        for frame in corrupt.receive().unwrap() {
+            match PeerMessage::decode(&frame).unwrap() {
+                PeerMessage::Checksum { tick: 0, hash } if !replied => {
+                    let wrong = PeerMessage::Checksum {
+                        tick: 0,
+                        hash: hash ^ 1,
+                    };
+                    corrupt.send(&wrong.encode());
+                    corrupt.flush().unwrap();
+                    replied = true;
+                }
+                PeerMessage::Hashes { tick, hashes } => reported = Some((tick, hashes, frame)),
+                _ => {}
+            }
+        }
+        reported.is_some()
+    });
+
+    let (tick, hashes, mut frame) = reported.unwrap();
+    assert_eq!(tick, 0);
+    assert!(hashes.fields().len() > 1);
+    assert!(honest.desync().is_none());
+
+    // Answer with the same hashes except for the last field.
+    *frame.last_mut().unwrap() ^= 0xff;
+    corrupt.send(&frame);
+    corrupt.flush().unwrap();
+    wait_until(|| {
+        honest.update(0.0).unwrap();
+        honest.desync().is_some()
+    });
+
+    let desync = honest.desync().unwrap();
+    assert_eq!(desync.tick, 0);
+    assert_eq!(desync.peer, 1);
+    assert_eq!(desync.field, hashes.fields().last().unwrap().0);
+}
@@ -0,0 +1,149 @@
+extern crate piston_window;
+extern crate rust_snakegame;
+
+use piston_window::Key;
+use rust_snakegame::food::FoodRules;
+use rust_snakegame::game::{CollisionRules, Difficulty, Game, GameMode, RampMetric, SpeedRamp};
+use rust_snakegame::powerup::InventoryRules;
+use rust_snakegame::replay::{Recorder, Replay, ReplayConfig, REPLAY_VERSION};
+use rust_snakegame::save::{SavedGame, SAVE_VERSION};
+
+const KEYS: [Key; 6] = [
+    Key::Up,
+    Key::Left,
+    Key::Down,
+    Key::Right,
+    Key::D1,
+    Key::Space,
+];
+
+fn config() -> ReplayConfig {
+    ReplayConfig {
+        width: 27,
+        height: 22,
+        mode: GameMode::Versus,
+        rules: CollisionRules::default(),
+        difficulty: Difficulty::Hard,
+        inventory: Some(InventoryRules::default()),
+        food: FoodRules {
+            base: 3,
+            ..FoodRules::default()
+        },
+        speed_ramp: Some(SpeedRamp::new(RampMetric::Length)),
+        best_of: 3,
+        seed: 1234,
+        tick_duration: 1.0 / 120.0,
+    }
+}
+
+/// Plays `ticks` ticks, pressing a key from a fixed schedule every so often.
+fn play(game: &mut Game, recorder: &mut Recorder, ticks: u32) {
+    for _ in 0..ticks {
+        let tick = recorder.replay().ticks;
+        if tick.is_multiple_of(37) {
+            let key = KEYS[(tick / 37) as usize % KEYS.len()];
+            game.key_pressed(key);
+            recorder.key_pressed(key);
+        }
+        game.update(recorder.replay().config.tick_duration);
+        recorder.tick();
+    }
+}
+
+fn recorded(config: ReplayConfig, ticks: u32) -> (Game, Recorder) {
+    let mut game = config.new_game();
+    let mut recorder = Recorder::new(config);
+    play(&mut game, &mut recorder, ticks);
+    (game, recorder)
+}
+
+#[test]
+fn replay_round_trips() {
+    let (_, recorder) = recorded(config(), 2000);
+    let replay = recorder.replay();
+    assert!(!replay.events.is_empty());
+    assert_eq!(&Replay::decode(&replay.encode()).unwrap(), replay);
+}
+
+#[test]
+fn replay_reproduces_the_recorded_game() {
+    let (game, recorder) = recorded(config(), 3000);
+    assert_eq!(
+        recorder.replay().play_out().state_hashes(),
+        game.state_hashes()
+    );
+}
+
+#[test]
+fn saved_game_round_trips() {
+    let (game, recorder) = recorded(config(), 1500);
+    let saved = SavedGame {
+        replay: recorder.replay().clone(),
+        game,
+    };
+    let loaded = SavedGame::decode(&saved.encode()).unwrap();
+    assert_eq!(loaded.replay, saved.replay);
+    assert_eq!(loaded.game.state_hashes(), saved.game.state_hashes());
+}
+
+#[test]
+fn resumed_game_matches_an_uninterrupted_run() {
+    let (mut uninterrupted, mut recorder) = recorded(config(), 1200);
+
+    let saved = SavedGame {
+        replay: recorder.replay().clone(),
+        game: uninterrupted.clone(),
+    };
+    let loaded = SavedGame::decode(&saved.encode()).unwrap();
+    let mut resumed = loaded.game;
+    let mut resumed_recorder = Recorder::resume(loaded.replay);
+
+    play(&mut uninterrupted, &mut recorder, 1800);
+    play(&mut resumed, &mut resumed_recorder, 1800);
+
+    assert_eq!(resumed.state_hashes(), uninterrupted.state_hashes());
+    assert_eq!(resumed_recorder.replay(), recorder.replay());
+    assert_eq!(
+        resumed_recorder.replay().play_out().state_hashes(),
+        uninterrupted.state_hashes()
+    );
+}
+
+/// A Versus match from `config()` with the key schedule of `play`, recorded
+/// over 3000 ticks. Its outcome and final state hash are pinned below; if a
+/// change to the simulation breaks this test, bump `REPLAY_VERSION` and
+/// record the file again.
+const GOLDEN: &[u8] = include_bytes!("data/golden.replay");
+const GOLDEN_CHECKSUM: u64 = 18203172030243037579;
+
+#[test]
+fn recorded_match_plays_back_to_its_outcome() {
+    let replay = Replay::decode(GOLDEN).unwrap();
+    assert_eq!(replay.config, config());
+    assert_eq!(replay.ticks, 3000);
+
+    let game = replay.play_out();
+    assert!(game.is_match_over());
+    assert_eq!(game.round_wins(), &[0, 2]);
+    assert_eq!(game.state_hashes().checksum(), GOLDEN_CHECKSUM);
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    let (game, recorder) = recorded(config(), 100);
+    for version in [1, REPLAY_VERSION - 1, REPLAY_VERSION + 1] {
+        let mut replay = recorder.replay().encode();
+        replay[4..6].copy_from_slice(&version.to_le_bytes());
+        assert!(Replay::decode(&replay).is_err(), "version {}", version);
+    }
+
+    let saved = SavedGame {
+        replay: recorder.replay().clone(),
+        game,
+    };
+    for version in [SAVE_VERSION - 1, SAVE_VERSION + 1] {
+        let mut bytes = saved.encode();
+        bytes[4..6].copy_from_slice(&version.to_le_bytes());
+        assert!(SavedGame::decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+extern crate rust_snakegame;
+
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_snakegame::net::{ClientMessage, Connection, NetState, ServerMessage, PROTOCOL_VERSION};
+use rust_snakegame::server::{Server, ServerConfig};
+use rust_snakegame::snake::Direction;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A bare client that keeps every message it is sent.
+struct TestClient {
+    connection: Connection<TcpStream>,
+    state: NetState,
+}
+
+impl TestClient {
+    fn hello(address: SocketAddr, name: &str, resume_token: Option<u64>) -> TestClient {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut connection = Connection::new(stream);
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+            resume_token,
+        };
+        connection.send(&hello.encode());
+        connection.flush().unwrap();
+        TestClient {
+            connection,
+            state: NetState::empty(),
+        }
+    }
+
+    /// Polls the server until this client has received something, applying
+    /// snapshots and deltas as they arrive.
+    fn receive(&mut self, server: &mut Server) -> Vec<ServerMessage> {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            server.poll().unwrap();
+            let frames = self.connection.receive().unwrap();
+            if !frames.is_empty() {
+                let messages: Vec<ServerMessage> = frames
+                    .iter()
+                    .map(|frame| ServerMessage::decode(frame).unwrap())
+                    .collect();
+                for message in &messages {
+                    match message {
+                        ServerMessage::Snapshot(state) => self.state = state.clone(),
+                        ServerMessage::Delta(delta) => self.state.apply(delta.clone()),
+                        _ => {}
+                    }
+                }
+                return messages;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("timed out waiting for the server");
+    }
+
+    /// Reads messages until a `Welcome` or `Rejected` arrives.
+    fn handshake(&mut self, server: &mut Server) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        while !messages.iter().any(|m| {
+            matches!(
+                m,
+                ServerMessage::Welcome { .. } | ServerMessage::Rejected(_)
+            )
+        }) {
+            messages.extend(self.receive(server));
+        }
+        messages
+    }
+}
+
+fn welcome(messages: &[ServerMessage]) -> (u8, u64) {
+    match messages.first() {
+        Some(ServerMessage::Welcome { player, token, .. }) => (*player, *token),
+        other => panic!("expected welcome, got {:?}", other),
+    }
+}
+
+fn bind() -> Server {
+    Server::bind(ServerConfig {
+        address: "127.0.0.1:0".to_string(),
+        snapshot_interval: 10,
+        seed: Some(7),
+        ..ServerConfig::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn clients_join_then_receive_a_snapshot_and_deltas() {
+    let mut server = bind();
+    let address = server.local_addr().unwrap();
+
+    let mut first = TestClient::hello(address, "first", None);
+    let messages = first.handshake(&mut server);
+    assert_eq!(welcome(&messages).0, 0);
+    assert!(matches!(messages.get(1), Some(ServerMessage::Snapshot(_))));
+
+    let mut second = TestClient::hello(address, "second", None);
+    let messages = second.handshake(&mut server);
+    assert_eq!(welcome(&messages).0, 1);
+    assert!(matches!(messages.get(1), Some(ServerMessage::Snapshot(_))));
+
+    let mut saw_delta = false;
+    while server.tick() < 15 {
+        saw_delta |= second
+            .receive(&mut server)
+            .iter()
+            .any(|m| matches!(m, ServerMessage::Delta(_)));
+    }
+    assert!(saw_delta);
+    assert!(second.state.started);
+    assert_eq!(second.state.players.len(), 2);
+    assert_eq!(second.state.players[0].name, "first");
+    assert_eq!(second.state.players[1].name, "second");
+
+    let mut third = TestClient::hello(address, "third", None);
+    let messages = third.handshake(&mut server);
+    assert!(matches!(messages[0], ServerMessage::Rejected(_)));
+}
+
+#[test]
+fn dropped_client_resumes_its_slot_with_the_token() {
+    let mut server = bind();
+    let address = server.local_addr().unwrap();
+
+    let mut first = TestClient::hello(address, "first", None);
+    first.handshake(&mut server);
+    let mut second = TestClient::hello(address, "second", None);
+    let (_, token) = welcome(&second.handshake(&mut server));
+
+    let mut hijack = TestClient::hello(address, "intruder", Some(token));
+    let messages = hijack.handshake(&mut server);
+    assert!(matches!(messages[0], ServerMessage::Rejected(_)));
+
+    let deadline = Instant::now() + TIMEOUT;
+    while first.state.players.get(1).is_none_or(|p| !p.connected) {
+        assert!(Instant::now() < deadline, "join was never broadcast");
+        first.receive(&mut server);
+    }
+
+    drop(second);
+    while first.state.players.get(1).is_none_or(|p| p.connected) {
+        assert!(Instant::now() < deadline, "disconnect was never noticed");
+        first.receive(&mut server);
+    }
+
+    let mut resumed = TestClient::hello(address, "second", Some(token));
+    let messages = resumed.handshake(&mut server);
+    assert_eq!(welcome(&messages), (1, token));
+    match &messages[1] {
+        ServerMessage::Snapshot(state) => assert_eq!(state.players[1].name, "second"),
+        other => panic!("expected snapshot, got {:?}", other),
+    }
+
+    while !first.state.players[1].connected {
+        assert!(Instant::now() < deadline, "resume was never broadcast");
+        first.receive(&mut server);
+    }
+}
+
+#[test]
+fn silent_connection_is_dropped_after_the_handshake_timeout() {
+    let mut server = Server::bind(ServerConfig {
+        address: "127.0.0.1:0".to_string(),
+        handshake_timeout: Duration::from_millis(50),
+        ..ServerConfig::default()
+    })
+    .unwrap();
+    let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    stream.set_nonblocking(true).unwrap();
+    let mut silent = TestClient {
+        connection: Connection::new(stream),
+        state: NetState::empty(),
+    };
+
+    let messages = silent.receive(&mut server);
+    assert!(matches!(messages[0], ServerMessage::Rejected(_)));
+}
+
+#[test]
+fn inputs_are_capped_per_tick_and_far_ticks_dropped() {
+    let mut server = bind();
+    let address = server.local_addr().unwrap();
+    let mut client = TestClient::hello(address, "flood", None);
+    client.handshake(&mut server);
+    assert_eq!(server.tick(), 0);
+
+    let input = |tick, direction| ClientMessage::Input { tick, direction }.encode();
+    for i in 0..1000 {
+        let direction = [Direction::Up, Direction::Down][i % 2];
+        client.connection.send(&input(5, direction));
+    }
+    for tick in 1000..1100 {
+        client.connection.send(&input(tick, Direction::Up));
+    }
+    client.connection.send(&input(6, Direction::Up));
+    client.connection.flush().unwrap();
+
+    let deadline = Instant::now() + TIMEOUT;
+    while server.queued_inputs() < 2 {
+        assert!(Instant::now() < deadline, "inputs never arrived");
+        server.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    for _ in 0..20 {
+        server.poll().unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(server.queued_inputs(), 2);
+}
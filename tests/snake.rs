@@ -0,0 +1,67 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::snake::{Direction, Snake};
+
+fn body(snake: &Snake) -> Vec<(i32, i32)> {
+    snake.body_positions().collect()
+}
+
+#[test]
+fn new_snake_starts_behind_its_head() {
+    let snake = Snake::with_direction(5, 5, Direction::Up);
+    assert_eq!(body(&snake), vec![(5, 3), (5, 4), (5, 5)]);
+    assert_eq!(snake.head_direction(), Direction::Up);
+    assert_eq!(snake.pending_growth(), 0);
+}
+
+#[test]
+fn growth_is_added_one_segment_per_move() {
+    let mut snake = Snake::with_direction(5, 5, Direction::Right);
+    snake.grow(2);
+    assert_eq!(snake.length(), 3);
+    assert_eq!(snake.pending_growth(), 2);
+
+    snake.move_forward(None);
+    assert_eq!(body(&snake), vec![(8, 5), (7, 5), (6, 5), (5, 5)]);
+    assert_eq!(snake.pending_growth(), 1);
+    assert!(snake.is_overlap_except_tail(5, 5));
+
+    snake.move_forward(Some(Direction::Down));
+    assert_eq!(body(&snake), vec![(8, 6), (8, 5), (7, 5), (6, 5), (5, 5)]);
+    assert_eq!(snake.pending_growth(), 0);
+    assert!(!snake.is_overlap_except_tail(5, 5));
+
+    snake.move_forward(None);
+    assert_eq!(body(&snake), vec![(8, 7), (8, 6), (8, 5), (7, 5), (6, 5)]);
+}
+
+#[test]
+fn shrinking_cancels_pending_growth_first() {
+    let mut snake = Snake::with_direction(5, 5, Direction::Right);
+    snake.grow(3);
+    snake.move_forward(None);
+    snake.move_forward(None);
+    assert_eq!((snake.length(), snake.pending_growth()), (5, 1));
+
+    snake.shrink(1);
+    assert_eq!((snake.length(), snake.pending_growth()), (5, 0));
+    snake.grow(1);
+    snake.shrink(2);
+    assert_eq!(body(&snake), vec![(9, 5), (8, 5), (7, 5), (6, 5)]);
+    assert_eq!(snake.pending_growth(), 0);
+}
+
+#[test]
+fn shrinking_stops_at_the_starting_length() {
+    let mut snake = Snake::with_direction(5, 5, Direction::Right);
+    snake.grow(2);
+    snake.move_forward(None);
+    snake.move_forward(None);
+    snake.shrink(10);
+    assert_eq!(body(&snake), vec![(9, 5), (8, 5), (7, 5)]);
+
+    snake.shrink(1);
+    assert_eq!(snake.length(), 3);
+    snake.move_forward(None);
+    assert_eq!(body(&snake), vec![(10, 5), (9, 5), (8, 5)]);
+}
@@ -0,0 +1,140 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::ai;
+use rust_snakegame::events::GameEvent;
+use rust_snakegame::food::{FoodRules, FoodType, ROT_TIME};
+use rust_snakegame::game::{Game, GameMode};
+use rust_snakegame::powerup::PowerUpType;
+
+/// Steers the first snake with the built-in AI for `steps` moves from
+/// `seed`, starting a new round whenever it dies. `check` sees the game
+/// before and after each move and the events the move raised.
+fn autopilot(seed: u64, steps: u32, mut check: impl FnMut(&Game, &Game, &[GameEvent])) {
+    let mut game = Game::with_mode(27, 22, GameMode::Single);
+    game.reset(seed);
+    for _ in 0..steps {
+        if game.is_game_over() {
+            game.next_round();
+            game.take_events();
+        }
+        let before = game.clone();
+        game.step(ai::choose_direction(&game, 0));
+        let events = game.take_events();
+        check(&before, &game, &events);
+    }
+}
+
+#[test]
+fn food_target_counts_the_cells_inside_the_walls() {
+    let rules = FoodRules {
+        base: 1,
+        cells_per_food: 100,
+        per_extra_player: 0,
+    };
+    let mut game = Game::with_mode(22, 12, GameMode::Single);
+    game.set_food_rules(rules);
+    game.reset(5);
+    assert_eq!(game.food_target(), 1 + 20 * 10 / 100);
+    game.update(0.01);
+    assert_eq!(game.foods().len(), game.food_target());
+}
+
+#[test]
+fn food_target_beyond_the_free_cells_fills_the_board_and_stops() {
+    let mut game = Game::with_mode(7, 7, GameMode::Single);
+    game.set_food_rules(FoodRules {
+        base: 1000,
+        ..FoodRules::default()
+    });
+    game.reset(3);
+    for _ in 0..20 {
+        game.update(0.01);
+    }
+
+    let snake = game.snake().body_positions().count();
+    assert_eq!(game.foods().len() + game.powerups().len() + snake, 5 * 5);
+}
+
+#[test]
+fn smallest_board_does_not_hang() {
+    for mode in [GameMode::Single, GameMode::Versus] {
+        let mut game = Game::with_mode(3, 3, mode);
+        game.reset(1);
+        for _ in 0..200 {
+            game.update(0.05);
+        }
+    }
+}
+
+#[test]
+fn every_food_type_spawns_and_expires_on_time() {
+    let mut seen = Vec::new();
+    autopilot(3, 3000, |_, game, _| {
+        for food in game.foods() {
+            assert!(!game.is_wall(food.x, food.y));
+            if let Some(lifetime) = food.food_type.lifetime() {
+                assert!(food.timer < lifetime, "{:?}", food.food_type);
+            }
+            if food.food_type == FoodType::Normal {
+                assert!(food.timer < ROT_TIME);
+            }
+            if !seen.contains(&food.food_type) {
+                seen.push(food.food_type);
+            }
+        }
+    });
+    for food_type in FoodType::ALL {
+        assert!(seen.contains(&food_type), "{:?} never appeared", food_type);
+    }
+}
+
+#[test]
+fn eaten_food_grows_the_snake_by_its_type() {
+    let mut large = 0;
+    autopilot(3, 3000, |before, game, events| {
+        for event in events {
+            if let GameEvent::FoodEaten {
+                player: 0,
+                food_type,
+            } = event
+            {
+                let length = |g: &Game| g.snake().length() + g.snake().pending_growth();
+                assert_eq!(length(game), length(before) + food_type.growth());
+                if *food_type == FoodType::Large {
+                    large += 1;
+                }
+            }
+        }
+    });
+    assert!(large > 0);
+}
+
+#[test]
+fn only_fleeing_food_moves_and_it_steps_one_cell_at_a_time() {
+    let mut fled = 0;
+    autopilot(3, 3000, |before, game, events| {
+        // Eaten and expiring food is replaced, which reorders the list.
+        let reshuffled = before.foods().len() != game.foods().len()
+            || events
+                .iter()
+                .any(|e| matches!(e, GameEvent::FoodEaten { .. }))
+            || before.foods().iter().any(|f| {
+                f.food_type
+                    .lifetime()
+                    .is_some_and(|lifetime| f.timer + 1.0 >= lifetime)
+            });
+        if reshuffled || game.players()[0].has_effect(PowerUpType::Magnet) {
+            return;
+        }
+        for (old, new) in before.foods().iter().zip(game.foods()) {
+            let moved = (old.x - new.x).abs() + (old.y - new.y).abs();
+            if old.food_type.flees() {
+                assert!(moved <= 1);
+                fled += moved;
+            } else {
+                assert_eq!(moved, 0, "{:?} moved", old.food_type);
+            }
+        }
+    });
+    assert!(fled > 0);
+}
@@ -0,0 +1,114 @@
+extern crate piston_window;
+extern crate rust_snakegame;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use piston_window::Key;
+use rust_snakegame::game::{Game, GameMode};
+use rust_snakegame::spectate::{Broadcaster, Spectator};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const FRAME: f64 = 0.05;
+const DELAY: f64 = 0.5;
+
+/// A versus game broadcast to one spectator.
+struct Watch {
+    game: Game,
+    broadcaster: Broadcaster,
+    spectator: Spectator,
+    /// States broadcast so far; each one is a tick.
+    published: u32,
+}
+
+impl Watch {
+    fn start() -> Watch {
+        let mut game = Game::with_mode(27, 22, GameMode::Versus);
+        game.reset(2);
+        let mut broadcaster = Broadcaster::bind("127.0.0.1:0", 27, 22).unwrap();
+        let address = broadcaster.local_addr().unwrap().to_string();
+        let spectator = thread::spawn(move || Spectator::connect(&address, DELAY));
+
+        let deadline = Instant::now() + TIMEOUT;
+        let mut published = 0;
+        while !spectator.is_finished() {
+            assert!(Instant::now() < deadline, "spectator never connected");
+            broadcaster.update(FRAME, &game).unwrap();
+            published += 1;
+            thread::sleep(Duration::from_millis(1));
+        }
+        let spectator = spectator.join().unwrap().unwrap();
+        assert_eq!(broadcaster.spectator_count(), 1);
+        Watch {
+            game,
+            broadcaster,
+            spectator,
+            published,
+        }
+    }
+
+    /// Plays and broadcasts one frame, then lets the spectator catch up.
+    fn frame(&mut self) {
+        self.game.update(FRAME);
+        self.broadcaster.update(FRAME, &self.game).unwrap();
+        self.published += 1;
+        thread::sleep(Duration::from_millis(2));
+        self.spectator.update(FRAME).unwrap();
+    }
+
+    /// Plays until the spectator shows something, returning the frames it
+    /// took.
+    fn until_shown(&mut self) -> u32 {
+        let deadline = Instant::now() + TIMEOUT;
+        let mut frames = 0;
+        while self.spectator.state().players.is_empty() {
+            assert!(Instant::now() < deadline, "nothing was ever shown");
+            self.frame();
+            frames += 1;
+        }
+        frames
+    }
+}
+
+#[test]
+fn spectator_shows_the_broadcast_after_a_delay() {
+    let mut watch = Watch::start();
+    assert_eq!(
+        (watch.spectator.width(), watch.spectator.height()),
+        (27, 22)
+    );
+
+    let frames = watch.until_shown();
+    assert!(frames as f64 * FRAME >= DELAY - 1e-9);
+    assert_eq!(watch.spectator.state().players.len(), 2);
+
+    for _ in 0..20 {
+        watch.frame();
+    }
+    let behind = watch.published - watch.spectator.state().tick;
+    let delay_frames = (DELAY / FRAME).round() as u32;
+    assert!(
+        (delay_frames - 1..=delay_frames + 1).contains(&behind),
+        "shown {} ticks behind",
+        behind
+    );
+}
+
+#[test]
+fn spectator_switches_the_focused_player() {
+    let mut watch = Watch::start();
+    watch.until_shown();
+    let spectator = &mut watch.spectator;
+
+    assert_eq!(spectator.focus(), 0);
+    spectator.key_pressed(Key::Tab);
+    assert_eq!(spectator.focus(), 1);
+    spectator.key_pressed(Key::Tab);
+    assert_eq!(spectator.focus(), 0);
+    spectator.key_pressed(Key::Left);
+    assert_eq!(spectator.focus(), 1);
+    spectator.key_pressed(Key::D5);
+    assert_eq!(spectator.focus(), 1);
+    spectator.key_pressed(Key::D1);
+    assert_eq!(spectator.focus(), 0);
+}
@@ -0,0 +1,87 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::daily::Date;
+use rust_snakegame::food::FoodRules;
+use rust_snakegame::game::{CollisionRules, Difficulty, GameMode};
+use rust_snakegame::replay::{Replay, ReplayConfig};
+use rust_snakegame::submission::ScoreRecord;
+
+const KEY: &[u8] = b"test key";
+
+fn config() -> ReplayConfig {
+    ReplayConfig {
+        width: 27,
+        height: 22,
+        mode: GameMode::Single,
+        rules: CollisionRules::default(),
+        difficulty: Difficulty::Normal,
+        inventory: None,
+        food: FoodRules::default(),
+        speed_ramp: None,
+        best_of: 1,
+        seed: 42,
+        tick_duration: 1.0 / 120.0,
+    }
+}
+
+fn signed(replay: Replay) -> Vec<u8> {
+    ScoreRecord {
+        name: "tester".to_string(),
+        score: 0,
+        length: 3,
+        date: Date::from_days(0),
+        replay,
+    }
+    .sign(KEY)
+}
+
+#[test]
+fn signed_record_verifies() {
+    let mut replay = Replay::new(config());
+    replay.ticks = 600;
+    let record = ScoreRecord::from_replay("tester", Date::from_days(0), replay);
+    let verified = ScoreRecord::verify(&record.sign(KEY), KEY).unwrap();
+    assert_eq!(verified, record);
+    assert!(ScoreRecord::verify(&record.sign(b"other key"), KEY).is_err());
+}
+
+#[test]
+fn impossible_boards_are_rejected() {
+    for (width, height) in [(2, 22), (27, 0), (-5, 22), (100_000, 22)] {
+        let replay = Replay::new(ReplayConfig {
+            width,
+            height,
+            ..config()
+        });
+        assert!(Replay::decode(&replay.encode()).is_err());
+        assert!(ScoreRecord::verify(&signed(replay), KEY).is_err());
+    }
+}
+
+#[test]
+fn bad_settings_are_rejected() {
+    let configs = [
+        ReplayConfig {
+            mode: GameMode::Online { players: 0 },
+            ..config()
+        },
+        ReplayConfig {
+            best_of: 0,
+            ..config()
+        },
+        ReplayConfig {
+            tick_duration: f64::NAN,
+            ..config()
+        },
+    ];
+    for config in configs {
+        assert!(ScoreRecord::verify(&signed(Replay::new(config)), KEY).is_err());
+    }
+}
+
+#[test]
+fn endless_replay_is_rejected_without_simulating() {
+    let mut replay = Replay::new(config());
+    replay.ticks = u32::MAX;
+    assert!(ScoreRecord::verify(&signed(replay), KEY).is_err());
+}
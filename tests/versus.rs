@@ -0,0 +1,100 @@
+extern crate rust_snakegame;
+
+use rust_snakegame::ai;
+use rust_snakegame::events::{DeathCause, GameEvent};
+use rust_snakegame::game::{CollisionRules, Game, GameMode, HeadOnRule};
+use rust_snakegame::snake::Direction;
+
+/// Two snakes on one row, heads three cells apart and facing each other.
+fn facing(rules: CollisionRules) -> Game {
+    let mut game = Game::with_mode(12, 5, GameMode::TwoPlayer);
+    game.set_collision_rules(rules);
+    game.reset(1);
+    assert_eq!(game.players()[0].snake().head_position(), (4, 2));
+    assert_eq!(game.players()[1].snake().head_position(), (7, 2));
+    game
+}
+
+/// Moves both snakes once, the second one towards `second`.
+fn step(game: &mut Game, second: Option<Direction>) -> Vec<GameEvent> {
+    if let Some(direction) = second {
+        game.queue_direction(1, direction);
+    }
+    game.step(None);
+    game.take_events()
+}
+
+fn deaths(events: &[GameEvent]) -> Vec<(usize, DeathCause)> {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            GameEvent::Died { player, cause } => Some((*player, *cause)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn head_on_between_equal_snakes_kills_both() {
+    for head_on in [HeadOnRule::BothDie, HeadOnRule::ShorterDies] {
+        let mut game = facing(CollisionRules {
+            snakes_collide: true,
+            head_on,
+        });
+        step(&mut game, None);
+        let events = step(&mut game, None);
+
+        assert_eq!(deaths(&events).len(), 2, "{:?}", head_on);
+        assert!(game.is_game_over());
+        assert_eq!(game.round_wins(), &[0, 0]);
+    }
+}
+
+#[test]
+fn running_into_a_body_kills_only_the_attacker() {
+    let mut game = facing(CollisionRules::default());
+    step(&mut game, Some(Direction::Up));
+    step(&mut game, Some(Direction::Left));
+    let events = step(&mut game, None);
+
+    assert_eq!(deaths(&events), vec![(0, DeathCause::Body)]);
+    assert!(game.players()[1].is_alive());
+    assert!(game.is_game_over());
+    assert_eq!(game.round_wins(), &[0, 1]);
+}
+
+#[test]
+fn snakes_pass_through_each_other_when_collisions_are_off() {
+    let mut game = facing(CollisionRules {
+        snakes_collide: false,
+        head_on: HeadOnRule::BothDie,
+    });
+    for _ in 0..3 {
+        assert!(deaths(&step(&mut game, None)).is_empty());
+    }
+    assert!(game.players().iter().all(|p| p.is_alive()));
+}
+
+#[test]
+fn ai_snake_scores_on_its_own() {
+    let mut game = Game::with_mode(27, 22, GameMode::Versus);
+    game.reset(5);
+    let mut eaten = [0, 0];
+    for _ in 0..300 {
+        if game.is_game_over() {
+            break;
+        }
+        game.step(ai::choose_direction(&game, 0));
+        for event in game.take_events() {
+            if let GameEvent::FoodEaten { player, .. } = event {
+                eaten[player] += 1;
+            }
+        }
+    }
+    assert!(eaten[1] > 0, "the AI never ate");
+    assert!(game.players()[1].score() > 0);
+    assert_ne!(
+        game.players()[0].snake().colors(),
+        game.players()[1].snake().colors()
+    );
+}